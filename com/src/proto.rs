@@ -22,6 +22,20 @@ pub struct RpcPose {
     #[prost(double, tag = "6")]
     pub angle5: f64,
 }
+/// Define the message for representing the motion profile of a pose change in the RPC
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcMotionProfile {
+    /// The kind of motion profile
+    #[prost(enumeration = "RpcMotionProfileKind", tag = "1")]
+    pub kind: i32,
+    /// The maximum acceleration, in degrees per second squared
+    #[prost(double, tag = "2")]
+    pub max_acceleration: f64,
+    /// The maximum jerk, in degrees per second cubed
+    #[prost(double, tag = "3")]
+    pub max_jerk: f64,
+}
 /// Define the message for representing a pose change in the RPC
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -32,6 +46,9 @@ pub struct RpcPoseChange {
     /// The duration of the pose change
     #[prost(double, tag = "2")]
     pub duration: f64,
+    /// The motion profile, constant velocity if not provided
+    #[prost(message, optional, tag = "3")]
+    pub motion_profile: ::core::option::Option<RpcMotionProfile>,
 }
 /// Define the message for requesting a pose change in the RPC
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -56,11 +73,78 @@ pub struct RpcMultiPoseChangeRequest {
 /// Define the message for the response to a multiple pose change request in the RPC
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct RpcMultiPoseChangeResponse {}
+pub struct RpcMultiPoseChangeResponse {
+    /// The number of pose changes that were executed
+    #[prost(uint32, tag = "1")]
+    pub executed_pose_changes: u32,
+    /// The sum of the durations of the executed pose changes, stretched to the limits of their motion profiles
+    #[prost(double, tag = "2")]
+    pub planned_duration: f64,
+    /// The time it took to execute the pose changes, in seconds
+    #[prost(double, tag = "3")]
+    pub elapsed_time: f64,
+}
+/// Define the message for requesting to stop the running pose changes in the RPC
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcStopRequest {}
+/// Define the message for the response to a stop request in the RPC
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcStopResponse {}
 /// Define the message for requesting a pose stream in the RPC
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RpcPoseStreamRequest {}
+/// Define the message for requesting a change of the log filter in the RPC
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcLogFilterRequest {
+    /// The new filter, in the syntax of RUST_LOG (such as "info,pca9685=trace")
+    #[prost(string, tag = "1")]
+    pub filter: ::prost::alloc::string::String,
+}
+/// Define the message for the response to a log filter request in the RPC
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RpcLogFilterResponse {
+    /// The filter that was replaced
+    #[prost(string, tag = "1")]
+    pub previous_filter: ::prost::alloc::string::String,
+}
+/// Define the kinds of motion profiles in the RPC
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RpcMotionProfileKind {
+    /// Move at a constant velocity
+    ConstantVelocity = 0,
+    /// Accelerate, cruise and decelerate, limited by maxAcceleration
+    Trapezoidal = 1,
+    /// Follow a minimum-jerk curve, limited by maxAcceleration and maxJerk
+    SCurve = 2,
+}
+impl RpcMotionProfileKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            RpcMotionProfileKind::ConstantVelocity => "CONSTANT_VELOCITY",
+            RpcMotionProfileKind::Trapezoidal => "TRAPEZOIDAL",
+            RpcMotionProfileKind::SCurve => "S_CURVE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CONSTANT_VELOCITY" => Some(Self::ConstantVelocity),
+            "TRAPEZOIDAL" => Some(Self::Trapezoidal),
+            "S_CURVE" => Some(Self::SCurve),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod rpc_servo_writer_api_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -147,7 +231,7 @@ pub mod rpc_servo_writer_api_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
-        /// RPC method for changing a pose
+        /// RPC method for changing a pose, preempting the running pose changes
         pub async fn change_pose(
             &mut self,
             request: impl tonic::IntoRequest<super::RpcPoseChangeRequest>,
@@ -173,7 +257,7 @@ pub mod rpc_servo_writer_api_client {
                 .insert(GrpcMethod::new("proto.RpcServoWriterApi", "ChangePose"));
             self.inner.unary(req, path, codec).await
         }
-        /// RPC method for changing multiple poses
+        /// RPC method for changing multiple poses, preempting the running pose changes
         pub async fn multi_change_pose(
             &mut self,
             request: impl tonic::IntoRequest<super::RpcMultiPoseChangeRequest>,
@@ -199,6 +283,32 @@ pub mod rpc_servo_writer_api_client {
                 .insert(GrpcMethod::new("proto.RpcServoWriterApi", "MultiChangePose"));
             self.inner.unary(req, path, codec).await
         }
+        /// RPC method for stopping the running pose changes, holding the servos at their current angles
+        pub async fn stop(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RpcStopRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RpcStopResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/proto.RpcServoWriterApi/Stop",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("proto.RpcServoWriterApi", "Stop"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated client implementations.
@@ -314,6 +424,119 @@ pub mod rpc_servo_reader_api_client {
         }
     }
 }
+/// Generated client implementations.
+pub mod rpc_log_api_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct RpcLogApiClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl RpcLogApiClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> RpcLogApiClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> RpcLogApiClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            RpcLogApiClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// RPC method for changing which spans and events the firmware logs, without restarting it
+        pub async fn set_log_filter(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RpcLogFilterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RpcLogFilterResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/proto.RpcLogApi/SetLogFilter",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("proto.RpcLogApi", "SetLogFilter"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
 /// Generated server implementations.
 pub mod rpc_servo_writer_api_server {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -321,7 +544,7 @@ pub mod rpc_servo_writer_api_server {
     /// Generated trait containing gRPC methods that should be implemented for use with RpcServoWriterApiServer.
     #[async_trait]
     pub trait RpcServoWriterApi: Send + Sync + 'static {
-        /// RPC method for changing a pose
+        /// RPC method for changing a pose, preempting the running pose changes
         async fn change_pose(
             &self,
             request: tonic::Request<super::RpcPoseChangeRequest>,
@@ -329,7 +552,7 @@ pub mod rpc_servo_writer_api_server {
             tonic::Response<super::RpcPoseChangeResponse>,
             tonic::Status,
         >;
-        /// RPC method for changing multiple poses
+        /// RPC method for changing multiple poses, preempting the running pose changes
         async fn multi_change_pose(
             &self,
             request: tonic::Request<super::RpcMultiPoseChangeRequest>,
@@ -337,6 +560,11 @@ pub mod rpc_servo_writer_api_server {
             tonic::Response<super::RpcMultiPoseChangeResponse>,
             tonic::Status,
         >;
+        /// RPC method for stopping the running pose changes, holding the servos at their current angles
+        async fn stop(
+            &self,
+            request: tonic::Request<super::RpcStopRequest>,
+        ) -> std::result::Result<tonic::Response<super::RpcStopResponse>, tonic::Status>;
     }
     /// Define the service for the RPC API of the servo driver
     #[derive(Debug)]
@@ -511,6 +739,51 @@ pub mod rpc_servo_writer_api_server {
                     };
                     Box::pin(fut)
                 }
+                "/proto.RpcServoWriterApi/Stop" => {
+                    #[allow(non_camel_case_types)]
+                    struct StopSvc<T: RpcServoWriterApi>(pub Arc<T>);
+                    impl<
+                        T: RpcServoWriterApi,
+                    > tonic::server::UnaryService<super::RpcStopRequest> for StopSvc<T> {
+                        type Response = super::RpcStopResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RpcStopRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as RpcServoWriterApi>::stop(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StopSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -740,3 +1013,185 @@ pub mod rpc_servo_reader_api_server {
         const NAME: &'static str = "proto.RpcServoReaderApi";
     }
 }
+/// Generated server implementations.
+pub mod rpc_log_api_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with RpcLogApiServer.
+    #[async_trait]
+    pub trait RpcLogApi: Send + Sync + 'static {
+        /// RPC method for changing which spans and events the firmware logs, without restarting it
+        async fn set_log_filter(
+            &self,
+            request: tonic::Request<super::RpcLogFilterRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RpcLogFilterResponse>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct RpcLogApiServer<T: RpcLogApi> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: RpcLogApi> RpcLogApiServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for RpcLogApiServer<T>
+    where
+        T: RpcLogApi,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/proto.RpcLogApi/SetLogFilter" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetLogFilterSvc<T: RpcLogApi>(pub Arc<T>);
+                    impl<
+                        T: RpcLogApi,
+                    > tonic::server::UnaryService<super::RpcLogFilterRequest>
+                    for SetLogFilterSvc<T> {
+                        type Response = super::RpcLogFilterResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RpcLogFilterRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as RpcLogApi>::set_log_filter(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SetLogFilterSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: RpcLogApi> Clone for RpcLogApiServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: RpcLogApi> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: RpcLogApi> tonic::server::NamedService for RpcLogApiServer<T> {
+        const NAME: &'static str = "proto.RpcLogApi";
+    }
+}
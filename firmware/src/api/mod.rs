@@ -1,5 +1,7 @@
 use pca9685_servo::servo::{reader::ServoReader, writer::ServoWriter};
 
+use crate::bus::Bus;
+
 use self::{
     servo_group_reader::{ServoGroupReader, ServoGroupReaderHandle, ServoGroupReaderTask},
     servo_group_writer::ServoGroupWriter,
@@ -11,13 +13,14 @@ pub(crate) mod servo_group_writer;
 pub(crate) struct ServoGroup;
 
 impl ServoGroup {
+    #[allow(clippy::new_ret_no_self)]
     pub(crate) fn new(
-        (s01_w, s01_r): (ServoWriter, ServoReader),
-        (s02_w, s02_r): (ServoWriter, ServoReader),
-        (s03_w, s03_r): (ServoWriter, ServoReader),
-        (s04_w, s04_r): (ServoWriter, ServoReader),
-        (s05_w, s05_r): (ServoWriter, ServoReader),
-        (s06_w, s06_r): (ServoWriter, ServoReader),
+        (s01_w, s01_r): (ServoWriter<Bus>, ServoReader),
+        (s02_w, s02_r): (ServoWriter<Bus>, ServoReader),
+        (s03_w, s03_r): (ServoWriter<Bus>, ServoReader),
+        (s04_w, s04_r): (ServoWriter<Bus>, ServoReader),
+        (s05_w, s05_r): (ServoWriter<Bus>, ServoReader),
+        (s06_w, s06_r): (ServoWriter<Bus>, ServoReader),
     ) -> (
         ServoGroupWriter,
         ServoGroupReaderHandle,
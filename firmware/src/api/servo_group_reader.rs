@@ -3,14 +3,15 @@ use std::time::Duration;
 use com::proto::RpcPose;
 use pca9685_servo::servo::reader::ServoReader;
 use thiserror::Error;
-use tokio::select;
-use tokio_util::sync::CancellationToken;
+use tokio::{select, sync::broadcast::error::RecvError};
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum Error {
     #[error("Task closed")]
     TaskClosedError,
     #[error("Cancelled")]
+    #[allow(unused)]
     Cancelled,
     #[error("Servo reader error: {0}")]
     ServoReaderError(#[from] pca9685_servo::servo::reader::Error),
@@ -19,6 +20,7 @@ pub(crate) enum Error {
 pub(crate) struct ServoGroupReader;
 
 impl ServoGroupReader {
+    #[allow(clippy::new_ret_no_self)]
     pub(crate) fn new(
         s01_r: ServoReader,
         s02_r: ServoReader,
@@ -82,14 +84,16 @@ impl ServoGroupReaderTask {
 
             tokio::time::sleep(Self::SLEEP_DURATION).await;
 
-            if let Err(_) = self.pose_sender.send(RpcPose {
+            let pose = RpcPose {
                 angle0: self.s01_r.read_angle(),
                 angle1: self.s02_r.read_angle(),
                 angle2: self.s03_r.read_angle(),
                 angle3: self.s04_r.read_angle(),
                 angle4: self.s05_r.read_angle(),
                 angle5: self.s06_r.read_angle(),
-            }) {
+            };
+
+            if self.pose_sender.send(pose).is_err() {
                 break;
             }
         }
@@ -108,11 +112,22 @@ impl ServoGroupReaderHandle {
         Self { pose_receiver }
     }
 
+    /// Creates a new handle with its own receiver, which receives all the poses sent from now on.
+    pub(crate) fn subscribe(&self) -> Self {
+        Self::new(self.pose_receiver.resubscribe())
+    }
+
+    /// Receives the next pose.
+    ///
+    /// If the receiver fell behind, the poses it missed are skipped, and the oldest pose still
+    ///  buffered is returned instead.
     pub(crate) async fn recv_pose(&mut self) -> Result<RpcPose, Error> {
-        if let Ok(pose) = self.pose_receiver.recv().await {
-            Ok(pose)
-        } else {
-            Err(Error::TaskClosedError)
+        loop {
+            match self.pose_receiver.recv().await {
+                Ok(pose) => return Ok(pose),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Err(Error::TaskClosedError),
+            }
         }
     }
 }
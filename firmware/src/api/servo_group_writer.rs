@@ -1,25 +1,44 @@
-use com::proto::{RpcPose, RpcPoseChange};
-use pca9685_servo::servo::writer::ServoWriter;
-use tokio::try_join;
+use std::{
+    array,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use com::proto::{
+    RpcMotionProfile, RpcMotionProfileKind, RpcMultiPoseChangeResponse, RpcPose, RpcPoseChange,
+};
+use pca9685_servo::{profile::MotionProfile, servo::writer::ServoWriter};
+use tokio::{
+    select,
+    time::{interval, Instant, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
 use tonic::Status;
+use tracing::{debug, instrument};
+
+use crate::bus::Bus;
+
+/// The id of the next pose change, which identifies the pose change in the spans and events of
+///  the servo writes it causes.
+static NEXT_MOVE_ID: AtomicU64 = AtomicU64::new(1_u64);
 
 pub(crate) struct ServoGroupWriter {
-    s01_w: ServoWriter,
-    s02_w: ServoWriter,
-    s03_w: ServoWriter,
-    s04_w: ServoWriter,
-    s05_w: ServoWriter,
-    s06_w: ServoWriter,
+    s01_w: ServoWriter<Bus>,
+    s02_w: ServoWriter<Bus>,
+    s03_w: ServoWriter<Bus>,
+    s04_w: ServoWriter<Bus>,
+    s05_w: ServoWriter<Bus>,
+    s06_w: ServoWriter<Bus>,
 }
 
 impl ServoGroupWriter {
     pub(super) fn new(
-        s01_w: ServoWriter,
-        s02_w: ServoWriter,
-        s03_w: ServoWriter,
-        s04_w: ServoWriter,
-        s05_w: ServoWriter,
-        s06_w: ServoWriter,
+        s01_w: ServoWriter<Bus>,
+        s02_w: ServoWriter<Bus>,
+        s03_w: ServoWriter<Bus>,
+        s04_w: ServoWriter<Bus>,
+        s05_w: ServoWriter<Bus>,
+        s06_w: ServoWriter<Bus>,
     ) -> Self {
         Self {
             s01_w,
@@ -31,20 +50,129 @@ impl ServoGroupWriter {
         }
     }
 
+    /// Validates a pose change without moving any of the servos.
+    ///
+    /// The pose change must contain a new pose with an angle within the range of every servo,
+    ///  a finite, non-negative duration, and a valid motion profile if one is provided.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn validate_rpc_pose_change(
+        &self,
+        pose_change: &RpcPoseChange,
+    ) -> Result<(), Status> {
+        let RpcPoseChange {
+            new_pose,
+            duration,
+            motion_profile,
+        } = pose_change;
+
+        let RpcPose {
+            angle0,
+            angle1,
+            angle2,
+            angle3,
+            angle4,
+            angle5,
+        } = new_pose
+            .as_ref()
+            .ok_or_else(|| Status::invalid_argument("new_pose must be provided"))?;
+
+        if !duration.is_finite() || *duration < 0_f64 {
+            return Err(Status::invalid_argument(format!(
+                "duration {} must be finite and non-negative",
+                duration
+            )));
+        }
+
+        Self::motion_profile(motion_profile.as_ref())?;
+
+        let angles = [angle0, angle1, angle2, angle3, angle4, angle5];
+
+        for (joint, (servo, &angle)) in self.servos().into_iter().zip(angles).enumerate() {
+            let settings = servo.settings();
+
+            if !settings.contains_angle(angle) {
+                let (min_angle, max_angle) = settings.angle_range();
+
+                return Err(Status::invalid_argument(format!(
+                    "angle{} {} out of range {} to {}",
+                    joint, angle, min_angle, max_angle
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes a sequence of pose changes in order.
+    ///
+    /// All the pose changes are validated before any of the servos is moved. If a pose change is
+    ///  invalid, fails to execute or is cancelled, the returned status contains its index.
+    pub(crate) async fn write_rpc_pose_changes(
+        &mut self,
+        pose_changes: Vec<RpcPoseChange>,
+        cancellation_token: &CancellationToken,
+    ) -> Result<RpcMultiPoseChangeResponse, Status> {
+        for (index, pose_change) in pose_changes.iter().enumerate() {
+            self.validate_rpc_pose_change(pose_change).map_err(|status| {
+                Status::invalid_argument(format!("pose change {}: {}", index, status.message()))
+            })?;
+        }
+
+        let started_at = Instant::now();
+        let mut response = RpcMultiPoseChangeResponse::default();
+
+        for (index, pose_change) in pose_changes.into_iter().enumerate() {
+            let duration = self
+                .write_rpc_pose_change(pose_change, cancellation_token)
+                .await
+                .map_err(|status| {
+                    Status::new(
+                        status.code(),
+                        format!("pose change {} failed: {}", index, status.message()),
+                    )
+                })?;
+
+            response.executed_pose_changes += 1;
+            response.planned_duration += duration;
+        }
+
+        response.elapsed_time = started_at.elapsed().as_secs_f64();
+
+        Ok(response)
+    }
+
+    /// Executes a single pose change, moving all the servos at once.
+    ///
+    /// A single control loop interpolates all the joints together and writes every servo in each
+    ///  tick. The joints follow the progress of the joint that moves furthest, so the arm moves
+    ///  along a straight line in joint space and all the joints start and stop together.
+    ///
+    /// The duration is stretched to the shortest duration in which the furthest joint can make
+    ///  its move within the limits of the motion profile. When the token is cancelled, the servos
+    ///  stop at their current angles, and the next pose change starts from there.
+    ///
+    /// # Returns
+    ///
+    /// The duration of the pose change in seconds.
+    #[instrument(
+        name = "move",
+        skip_all,
+        fields(move_id = NEXT_MOVE_ID.fetch_add(1_u64, Ordering::Relaxed))
+    )]
     pub(crate) async fn write_rpc_pose_change(
         &mut self,
         pose_change: RpcPoseChange,
-    ) -> Result<(), Status> {
-        let ServoGroupWriter {
-            s01_w,
-            s02_w,
-            s03_w,
-            s04_w,
-            s05_w,
-            s06_w,
-        } = self;
+        cancellation_token: &CancellationToken,
+    ) -> Result<f64, Status> {
+        self.validate_rpc_pose_change(&pose_change)?;
 
-        let RpcPoseChange { new_pose, duration } = pose_change;
+        let RpcPoseChange {
+            new_pose,
+            duration,
+            motion_profile,
+        } = pose_change;
+
+        let profile = Self::motion_profile(motion_profile.as_ref())?;
 
         let RpcPose {
             angle0,
@@ -55,17 +183,462 @@ impl ServoGroupWriter {
             angle5,
         } = new_pose.ok_or_else(|| Status::invalid_argument("new_pose must be provided"))?;
 
-        if let Err(error) = try_join!(
-            s01_w.write_with_duration(angle0, duration),
-            s02_w.write_with_duration(angle1, duration),
-            s03_w.write_with_duration(angle2, duration),
-            s04_w.write_with_duration(angle3, duration),
-            s05_w.write_with_duration(angle4, duration),
-            s06_w.write_with_duration(angle5, duration),
-        ) {
-            return Err(Status::internal(error.to_string()));
+        if cancellation_token.is_cancelled() {
+            return Err(Status::aborted("pose change was cancelled"));
         }
 
-        Ok(())
+        let start_angles = self.servos().map(ServoWriter::angle);
+        let target_angles = [angle0, angle1, angle2, angle3, angle4, angle5];
+
+        let distance = start_angles
+            .iter()
+            .zip(target_angles.iter())
+            .map(|(start_angle, target_angle)| (target_angle - start_angle).abs())
+            .fold(0_f64, f64::max);
+
+        let duration = profile.duration(distance, duration);
+
+        debug!(?start_angles, ?target_angles, ?profile, duration, "moving");
+
+        // Interpolate only if there is a distance to cover and time to cover it in.
+        if distance > 0_f64 && duration > 0_f64 {
+            let started_at = Instant::now();
+
+            let mut interval = interval(self.update_interval());
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                select! {
+                    _ = interval.tick() => {}
+                    _ = cancellation_token.cancelled() => {
+                        debug!(angles = ?self.servos().map(ServoWriter::angle), "move cancelled");
+                        return Err(Status::aborted("pose change was cancelled"));
+                    }
+                }
+
+                let time = started_at.elapsed().as_secs_f64();
+
+                if time >= duration {
+                    break;
+                }
+
+                let progress = profile.progress(distance, duration, time);
+
+                let angles = array::from_fn(|joint| {
+                    start_angles[joint] + (target_angles[joint] - start_angles[joint]) * progress
+                });
+
+                self.write_angles(angles).await?;
+            }
+        }
+
+        // Always end exactly at the new pose.
+        self.write_angles(target_angles).await?;
+
+        debug!("move finished");
+
+        Ok(duration)
+    }
+
+    /// Writes an angle to every servo, in the order of the joints, as a single frame.
+    async fn write_angles(&mut self, angles: [f64; 6]) -> Result<(), Status> {
+        let mut frame: Vec<_> = self.servos_mut().into_iter().zip(angles).collect();
+
+        ServoWriter::write_frame(&mut frame)
+            .await
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+
+    /// Gets the interval between the updates of the control loop, which is the longest update
+    ///  interval of the servos, so no driver is written more than once per PWM period.
+    fn update_interval(&self) -> Duration {
+        self.servos()
+            .into_iter()
+            .map(ServoWriter::update_interval)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Gets the servos in the order of the joints.
+    fn servos(&self) -> [&ServoWriter<Bus>; 6] {
+        [
+            &self.s01_w,
+            &self.s02_w,
+            &self.s03_w,
+            &self.s04_w,
+            &self.s05_w,
+            &self.s06_w,
+        ]
+    }
+
+    /// Gets the servos in the order of the joints, for writing.
+    fn servos_mut(&mut self) -> [&mut ServoWriter<Bus>; 6] {
+        [
+            &mut self.s01_w,
+            &mut self.s02_w,
+            &mut self.s03_w,
+            &mut self.s04_w,
+            &mut self.s05_w,
+            &mut self.s06_w,
+        ]
+    }
+
+    /// Converts the motion profile of a pose change, using a constant velocity if none is
+    ///  provided.
+    ///
+    /// The limits of a trapezoidal or S-curve profile must be positive, see
+    ///  `MotionProfile::validate`.
+    #[allow(clippy::result_large_err)]
+    fn motion_profile(motion_profile: Option<&RpcMotionProfile>) -> Result<MotionProfile, Status> {
+        let Some(motion_profile) = motion_profile else {
+            return Ok(MotionProfile::ConstantVelocity);
+        };
+
+        let kind = RpcMotionProfileKind::try_from(motion_profile.kind).map_err(|_| {
+            Status::invalid_argument(format!(
+                "motion profile kind {} is unknown",
+                motion_profile.kind
+            ))
+        })?;
+
+        let profile = match kind {
+            RpcMotionProfileKind::ConstantVelocity => MotionProfile::ConstantVelocity,
+            RpcMotionProfileKind::Trapezoidal => MotionProfile::Trapezoidal {
+                max_acceleration: motion_profile.max_acceleration,
+            },
+            RpcMotionProfileKind::SCurve => MotionProfile::SCurve {
+                max_acceleration: motion_profile.max_acceleration,
+                max_jerk: motion_profile.max_jerk,
+            },
+        };
+
+        profile
+            .validate()
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use pca9685::{
+        device::Device,
+        recording::{Decoder, Operation, Reader, Recorder, SharedWriter},
+        sim, Channel, Driver, INTERNAL_OSC_CLOCK,
+    };
+    use pca9685_servo::{servo::Servo, settings::ServoSettings};
+
+    use super::*;
+    use crate::bus::Interface;
+
+    const ADDRESS: u8 = 0x40;
+
+    /// The writer of six servos on channels 0 to 5 of a simulated chip, recording all the
+    ///  transactions sent to the chip.
+    struct Fixture {
+        bus: sim::Bus,
+        buffer: SharedWriter,
+        writer: ServoGroupWriter,
+    }
+
+    impl Fixture {
+        /// Builds the fixture with all the servos at 0 degrees.
+        async fn new() -> Self {
+            let bus = sim::Bus::with_chip(ADDRESS);
+            let buffer = SharedWriter::new();
+
+            let recorder = Recorder::new(Interface::Simulated(bus.clone()));
+            recorder.start(buffer.clone()).unwrap();
+
+            let driver = Driver::builder(Device::new(recorder, ADDRESS))
+                .with_osc_clock(INTERNAL_OSC_CLOCK)
+                .build()
+                .await
+                .unwrap();
+
+            let driver = Arc::new(tokio::sync::Mutex::new(driver));
+            let mut writers = Vec::new();
+
+            for channel in 0..6 {
+                let channel = Channel::new(driver.clone(), channel);
+                let (writer, _) = Servo::new(channel, ServoSettings::new(), 0_f64)
+                    .await
+                    .unwrap();
+
+                writers.push(writer);
+            }
+
+            let [s01_w, s02_w, s03_w, s04_w, s05_w, s06_w] = writers.try_into().ok().unwrap();
+            let writer = ServoGroupWriter::new(s01_w, s02_w, s03_w, s04_w, s05_w, s06_w);
+
+            Self {
+                bus,
+                buffer,
+                writer,
+            }
+        }
+
+        /// Decodes the writes of all six servos in a single transaction, which are the frames of
+        ///  the control loop, as the pulse widths of the joints in microseconds.
+        fn frames(&self) -> Vec<[f64; 6]> {
+            let recording = self.buffer.bytes();
+            let mut decoder = Decoder::new(INTERNAL_OSC_CLOCK);
+
+            Reader::new(recording.as_slice())
+                .unwrap()
+                .map(|record| decoder.decode(&record.unwrap()))
+                .filter_map(|operations| {
+                    let pulse_widths: Vec<_> = operations
+                        .iter()
+                        .filter_map(|operation| match operation {
+                            Operation::Channel { pulse_width, .. } => Some(*pulse_width),
+                            _ => None,
+                        })
+                        .collect();
+
+                    pulse_widths.try_into().ok()
+                })
+                .collect()
+        }
+
+        /// Gets the outputs of the six servo channels of the chip.
+        fn channels(&self) -> Vec<sim::ChannelState> {
+            let chip = self.bus.chip(ADDRESS).unwrap();
+
+            (0..6).map(|channel| chip.channel(channel)).collect()
+        }
+
+        /// Gets the angles the servos were last written to.
+        fn angles(&self) -> [f64; 6] {
+            self.writer.servos().map(ServoWriter::angle)
+        }
+    }
+
+    /// Creates a pose change at a constant velocity.
+    fn pose_change(angles: [f64; 6], duration: f64) -> RpcPoseChange {
+        let [angle0, angle1, angle2, angle3, angle4, angle5] = angles;
+
+        RpcPoseChange {
+            new_pose: Some(RpcPose {
+                angle0,
+                angle1,
+                angle2,
+                angle3,
+                angle4,
+                angle5,
+            }),
+            duration,
+            motion_profile: None,
+        }
+    }
+
+    /// Converts the pulse widths of a frame to the angles of the joints.
+    fn angles(frame: [f64; 6]) -> [f64; 6] {
+        frame.map(|pulse_width| ServoSettings::new().angle(pulse_width))
+    }
+
+    /// Checks that the angles match, within the resolution of the chip.
+    fn assert_angles(actual: [f64; 6], expected: [f64; 6]) {
+        for (actual, expected) in actual.iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 0.5_f64,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_changes() {
+        let mut fixture = Fixture::new().await;
+
+        let pose_changes = vec![
+            pose_change([10_f64, 20_f64, 30_f64, 40_f64, 50_f64, 60_f64], 0.1_f64),
+            pose_change([0_f64; 6], 0.2_f64),
+        ];
+
+        let response = fixture
+            .writer
+            .write_rpc_pose_changes(pose_changes, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(response.executed_pose_changes, 2);
+        assert!((response.planned_duration - 0.3_f64).abs() < 1e-9);
+        assert!(
+            (0.3_f64..0.4_f64).contains(&response.elapsed_time),
+            "{}",
+            response.elapsed_time
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_changes_invalid() {
+        let mut fixture = Fixture::new().await;
+
+        let channels_before = fixture.channels();
+        let frames_before = fixture.frames().len();
+
+        let pose_changes = vec![
+            pose_change([10_f64; 6], 0.1_f64),
+            pose_change([20_f64; 6], 0.1_f64),
+            pose_change([30_f64, 30_f64, 120_f64, 30_f64, 30_f64, 30_f64], 0.1_f64),
+        ];
+
+        let status = fixture
+            .writer
+            .write_rpc_pose_changes(pose_changes, &CancellationToken::new())
+            .await
+            .unwrap_err();
+
+        // The invalid pose change is reported, and none of the servos moved.
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(
+            status.message().starts_with("pose change 2:"),
+            "{}",
+            status.message()
+        );
+        assert!(
+            status.message().contains("angle2 120"),
+            "{}",
+            status.message()
+        );
+
+        assert_eq!(fixture.channels(), channels_before);
+        assert_eq!(fixture.frames().len(), frames_before);
+        assert_eq!(fixture.angles(), [0_f64; 6]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_changes_cancelled() {
+        let mut fixture = Fixture::new().await;
+        let cancellation_token = CancellationToken::new();
+
+        let pose_changes = vec![
+            pose_change([10_f64; 6], 0.1_f64),
+            pose_change([20_f64; 6], 0.1_f64),
+        ];
+
+        let cancel = async {
+            // During the second pose change, which starts after the first one took 100 ms.
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            cancellation_token.cancel();
+        };
+
+        let (result, _) = tokio::join!(
+            fixture
+                .writer
+                .write_rpc_pose_changes(pose_changes, &cancellation_token),
+            cancel,
+        );
+
+        // The index of the cancelled pose change is reported once.
+        let status = result.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        assert_eq!(
+            status.message(),
+            "pose change 1 failed: pose change was cancelled"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_change_synchronous() {
+        let mut fixture = Fixture::new().await;
+        let frames_before = fixture.frames().len();
+
+        let target_angles = [10_f64, -20_f64, 30_f64, -40_f64, 50_f64, -60_f64];
+
+        let duration = fixture
+            .writer
+            .write_rpc_pose_change(
+                pose_change(target_angles, 0.2_f64),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(duration, 0.2_f64);
+
+        // Every update of the control loop writes all six servos in a single frame, at the same
+        //  progress towards their target, so they all arrive in the last frame.
+        let frames = fixture.frames().split_off(frames_before);
+        assert_eq!(frames.len(), 11);
+
+        for frame in &frames {
+            let angles = angles(*frame);
+            let progress = angles[5] / target_angles[5];
+
+            assert_angles(angles, target_angles.map(|angle| angle * progress));
+        }
+
+        assert_angles(angles(frames[0]), [0_f64; 6]);
+        assert_angles(angles(frames[10]), target_angles);
+        assert!((angles(frames[9])[5] - target_angles[5]).abs() > 1_f64);
+        assert_eq!(fixture.angles(), target_angles);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_change_zero_duration() {
+        let mut fixture = Fixture::new().await;
+        let frames_before = fixture.frames().len();
+
+        let target_angles = [10_f64, -20_f64, 30_f64, -40_f64, 50_f64, -60_f64];
+
+        let duration = fixture
+            .writer
+            .write_rpc_pose_change(pose_change(target_angles, 0_f64), &CancellationToken::new())
+            .await
+            .unwrap();
+
+        // The new pose is written at once, in a single frame.
+        assert_eq!(duration, 0_f64);
+
+        let frames = fixture.frames().split_off(frames_before);
+        assert_eq!(frames.len(), 1);
+        assert_angles(angles(frames[0]), target_angles);
+        assert_eq!(fixture.angles(), target_angles);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_rpc_pose_change_cancelled() {
+        let mut fixture = Fixture::new().await;
+
+        let target_angles = [10_f64, -20_f64, 30_f64, -40_f64, 50_f64, -60_f64];
+        let cancellation_token = CancellationToken::new();
+
+        let cancel = async {
+            // The control loop writes a frame every PWM period of about 20 ms from the start, so
+            //  the last frame before the cancellation is written at 80 ms.
+            tokio::time::sleep(Duration::from_millis(90)).await;
+            cancellation_token.cancel();
+        };
+
+        let (result, _) = tokio::join!(
+            fixture
+                .writer
+                .write_rpc_pose_change(pose_change(target_angles, 0.2_f64), &cancellation_token),
+            cancel,
+        );
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Aborted);
+
+        // The servos stop at the angles of the last frame, which was 40% of the way.
+        let current_angles = fixture.angles();
+        assert_angles(current_angles, target_angles.map(|angle| angle * 0.4_f64));
+
+        let frames = fixture.frames();
+        let frame_count = frames.len();
+        assert_angles(angles(frames[frame_count - 1]), current_angles);
+
+        // Nothing is written after the cancellation.
+        let channels_after = fixture.channels();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(fixture.frames().len(), frame_count);
+        assert_eq!(fixture.channels(), channels_after);
     }
 }
@@ -0,0 +1,78 @@
+use std::convert::Infallible;
+
+use embedded_hal::{
+    digital::OutputPin,
+    i2c::{ErrorKind, ErrorType, I2c, Operation},
+};
+#[cfg(feature = "rppal")]
+use pca9685::bus::SharedBus;
+use pca9685::{recording::Recorder, sim};
+
+/// The I2C bus the PCA9685 chips driving the servos are attached to, whose transactions can be
+///  recorded.
+///
+/// The bus can be cloned, all the clones use the same underlying bus and the same recording.
+pub(crate) type Bus = Recorder<Interface>;
+
+/// The I2C interface the PCA9685 chips are attached to.
+///
+/// The interface can be cloned, all the clones use the same underlying bus.
+#[derive(Clone)]
+pub(crate) enum Interface {
+    /// The I2C bus of the Raspberry Pi.
+    #[cfg(feature = "rppal")]
+    Rppal(SharedBus<rppal::i2c::I2c>),
+    /// A simulated bus with a simulated PCA9685 attached to it.
+    Simulated(sim::Bus),
+}
+
+impl ErrorType for Interface {
+    type Error = ErrorKind;
+}
+
+impl I2c for Interface {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        match self {
+            #[cfg(feature = "rppal")]
+            Interface::Rppal(i2c) => i2c
+                .transaction(address, operations)
+                .map_err(|error| embedded_hal::i2c::Error::kind(&error)),
+            Interface::Simulated(bus) => bus.transaction(address, operations),
+        }
+    }
+}
+
+/// The pin connected to the Output Enable pin of the PCA9685.
+pub(crate) enum OutputEnablePin {
+    /// A GPIO pin of the Raspberry Pi.
+    #[cfg(feature = "rppal")]
+    Rppal(rppal::gpio::OutputPin),
+    /// A simulated pin.
+    Simulated(sim::Pin),
+}
+
+impl embedded_hal::digital::ErrorType for OutputEnablePin {
+    type Error = Infallible;
+}
+
+impl OutputPin for OutputEnablePin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        match self {
+            #[cfg(feature = "rppal")]
+            OutputEnablePin::Rppal(pin) => OutputPin::set_low(pin),
+            OutputEnablePin::Simulated(pin) => pin.set_low(),
+        }
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        match self {
+            #[cfg(feature = "rppal")]
+            OutputEnablePin::Rppal(pin) => OutputPin::set_high(pin),
+            OutputEnablePin::Simulated(pin) => pin.set_high(),
+        }
+    }
+}
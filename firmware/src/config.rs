@@ -0,0 +1,517 @@
+use std::{collections::BTreeMap, fs, io, net::SocketAddr, path::Path};
+
+use pca9685::{
+    address::{self, DEFAULT_ALL_CALL_ADDRESS},
+    math::compute_prescale,
+    PhaseStrategy, CHANNEL_COUNT, INTERNAL_OSC_CLOCK, MAX_EXTERNAL_CLOCK,
+};
+use pca9685_servo::settings::ServoSettings;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// The configuration of the arm the firmware was written for, used when no configuration file is
+///  given.
+const BUILT_IN_CONFIG: &str = include_str!("../config/arm.toml");
+
+/// The name of the built-in configuration in errors.
+const BUILT_IN_CONFIG_NAME: &str = "<built-in>";
+
+/// The number of joints of the arm.
+pub(crate) const JOINT_COUNT: usize = 6;
+
+/// The update rate of the PWM signals in Hz, when it is not configured.
+const DEFAULT_UPDATE_RATE: u16 = 50;
+
+/// Represents the possible errors that can occur while loading the configuration.
+#[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Error {
+    #[error("IO Error: {path}: {error}")]
+    IoError { path: String, error: io::Error },
+    #[error("Format Error: {0}: the extension must be .toml, .yaml or .yml")]
+    FormatError(String),
+    #[error("TOML Error: {path}: {error}")]
+    TomlError {
+        path: String,
+        error: Box<toml::de::Error>,
+    },
+    #[error("YAML Error: {path}: {error}")]
+    YamlError {
+        path: String,
+        error: serde_yaml::Error,
+    },
+    #[error("Chip Count Error: at least one chip must be configured")]
+    ChipCountError,
+    #[error("Chip Error: chip {chip}: {message}")]
+    ChipError { chip: usize, message: String },
+    #[error("Profile Error: profile {profile:?}: {message}")]
+    ProfileError { profile: String, message: String },
+    #[error("Joint Count Error: {0} joints are configured, the arm has {JOINT_COUNT}")]
+    JointCountError(usize),
+    #[error("Joint Error: joint {joint:?}: {message}")]
+    JointError { joint: String, message: String },
+}
+
+/// The configuration of the arm, which describes the PCA9685 chips, the servo calibrations, the
+///  joints and the address of the gRPC APIs.
+///
+/// Every arm is wired and calibrated slightly differently, so the configuration is loaded from a
+///  TOML or YAML file at startup, see `config/arm.toml` for the format.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// The address the gRPC APIs listen on.
+    pub(crate) listen_address: SocketAddr,
+    /// The PCA9685 chips on the I2C bus, numbered from 0 in this order.
+    pub(crate) chips: Vec<ChipConfig>,
+    /// The calibrations of the servos by name.
+    pub(crate) profiles: BTreeMap<String, ServoSettings>,
+    /// The joints of the arm, from the base to the gripper.
+    pub(crate) joints: Vec<JointConfig>,
+}
+
+/// The configuration of a PCA9685 chip.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ChipConfig {
+    /// The levels of the A0 to A5 pins, with A0 in the least significant bit.
+    pub(crate) address_pins: u8,
+    /// The oscillator clock frequency in Hz.
+    #[serde(default = "default_osc_clock")]
+    pub(crate) osc_clock: u32,
+    /// The update rate of the PWM signals in Hz.
+    #[serde(default = "default_update_rate")]
+    pub(crate) update_rate: u16,
+    /// The strategy for choosing the phase offsets of the channels.
+    #[serde(default)]
+    pub(crate) phase_strategy: PhaseStrategy,
+    /// The GPIO pin connected to the Output Enable pin, if any.
+    #[serde(default)]
+    pub(crate) oe_pin: Option<u8>,
+}
+
+/// The configuration of a joint of the arm, and the servo that moves it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct JointConfig {
+    /// The name of the joint in errors.
+    pub(crate) name: String,
+    /// The number of the chip the servo is attached to.
+    pub(crate) chip: u8,
+    /// The channel of the chip the servo is attached to.
+    pub(crate) channel: u8,
+    /// The name of the calibration of the servo.
+    pub(crate) profile: String,
+    /// The angle the servo is moved to at startup.
+    #[serde(default)]
+    pub(crate) initial_angle: f64,
+    /// The minimum and maximum angle the servo may be moved to.
+    #[serde(default)]
+    pub(crate) limits: Option<(f64, f64)>,
+}
+
+fn default_osc_clock() -> u32 {
+    INTERNAL_OSC_CLOCK
+}
+
+fn default_update_rate() -> u16 {
+    DEFAULT_UPDATE_RATE
+}
+
+impl Config {
+    /// Loads and validates the configuration from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file, which is parsed as TOML or YAML depending on its
+    ///   extension.
+    ///
+    /// # Returns
+    ///
+    /// The configuration, or an `Error` if the file cannot be read or parsed, or describes an
+    ///  invalid arm.
+    pub(crate) fn load(path: &str) -> Result<Self, Error> {
+        let source = fs::read_to_string(path).map_err(|error| Error::IoError {
+            path: path.to_string(),
+            error,
+        })?;
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str());
+
+        match extension {
+            Some("toml") => Self::from_toml(path, &source),
+            Some("yaml" | "yml") => Self::from_yaml(path, &source),
+            _ => Err(Error::FormatError(path.to_string())),
+        }
+    }
+
+    /// Loads the configuration of the arm the firmware was written for.
+    pub(crate) fn built_in() -> Result<Self, Error> {
+        Self::from_toml(BUILT_IN_CONFIG_NAME, BUILT_IN_CONFIG)
+    }
+
+    /// Parses and validates a TOML configuration.
+    fn from_toml(path: &str, source: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(source).map_err(|error| Error::TomlError {
+            path: path.to_string(),
+            error: Box::new(error),
+        })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses and validates a YAML configuration.
+    fn from_yaml(path: &str, source: &str) -> Result<Self, Error> {
+        let config: Self = serde_yaml::from_str(source).map_err(|error| Error::YamlError {
+            path: path.to_string(),
+            error,
+        })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Gets the I2C address of every chip.
+    pub(crate) fn chip_addresses(&self) -> Result<Vec<u8>, Error> {
+        self.chips
+            .iter()
+            .enumerate()
+            .map(|(chip, config)| {
+                address::from_pins(config.address_pins).map_err(|_| Error::ChipError {
+                    chip,
+                    message: format!(
+                        "address pins {:#b} must only set A0 to A5",
+                        config.address_pins
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the settings of the servo of a joint, which are the calibration of its profile with
+    ///  the limits of the joint.
+    ///
+    /// # Arguments
+    ///
+    /// * `joint` - The configuration of the joint, which must have been validated.
+    pub(crate) fn servo_settings(&self, joint: &JointConfig) -> ServoSettings {
+        let settings = self.profiles[&joint.profile].clone();
+
+        match joint.limits {
+            Some((min_angle, max_angle)) => settings.with_limits(min_angle, max_angle),
+            None => settings,
+        }
+    }
+
+    /// Checks that the configuration describes an arm the firmware can drive.
+    fn validate(&self) -> Result<(), Error> {
+        if self.chips.is_empty() {
+            return Err(Error::ChipCountError);
+        }
+
+        let addresses = self.chip_addresses()?;
+
+        for (chip, config) in self.chips.iter().enumerate() {
+            let chip_error = |message: String| Error::ChipError { chip, message };
+
+            if addresses[..chip].contains(&addresses[chip]) {
+                return Err(chip_error(format!(
+                    "address {:#04x} is used by another chip",
+                    addresses[chip]
+                )));
+            }
+
+            // All the chips listen to the LED All Call address, see `main`.
+            if addresses[chip] == DEFAULT_ALL_CALL_ADDRESS {
+                return Err(chip_error(format!(
+                    "address {:#04x} is the LED All Call address",
+                    addresses[chip]
+                )));
+            }
+
+            if config.osc_clock == 0 || config.update_rate == 0 {
+                return Err(chip_error(
+                    "osc_clock and update_rate must be positive".to_string(),
+                ));
+            }
+
+            if config.osc_clock > MAX_EXTERNAL_CLOCK {
+                return Err(chip_error(format!(
+                    "osc_clock {} Hz must be at most {} Hz",
+                    config.osc_clock, MAX_EXTERNAL_CLOCK
+                )));
+            }
+
+            if let Err(error) = compute_prescale(config.osc_clock, config.update_rate) {
+                return Err(chip_error(format!(
+                    "update_rate {} Hz cannot be reached with osc_clock {} Hz: {}",
+                    config.update_rate, config.osc_clock, error
+                )));
+            }
+
+            let used_oe_pin = self.chips[..chip]
+                .iter()
+                .any(|other| other.oe_pin.is_some() && other.oe_pin == config.oe_pin);
+
+            if used_oe_pin {
+                return Err(chip_error(format!(
+                    "OE pin {} is used by another chip",
+                    config.oe_pin.unwrap_or_default()
+                )));
+            }
+        }
+
+        for (profile, settings) in self.profiles.iter() {
+            settings
+                .validate()
+                .map_err(|error| Error::ProfileError {
+                    profile: profile.clone(),
+                    message: error.to_string(),
+                })?;
+        }
+
+        if self.joints.len() != JOINT_COUNT {
+            return Err(Error::JointCountError(self.joints.len()));
+        }
+
+        for (index, joint) in self.joints.iter().enumerate() {
+            let joint_error = |message: String| Error::JointError {
+                joint: joint.name.clone(),
+                message,
+            };
+
+            let Some(chip) = self.chips.get(joint.chip as usize) else {
+                return Err(joint_error(format!(
+                    "chip {} is not configured, there are {} chips",
+                    joint.chip,
+                    self.chips.len()
+                )));
+            };
+
+            if joint.channel >= CHANNEL_COUNT {
+                return Err(joint_error(format!(
+                    "channel {} must be below {}",
+                    joint.channel, CHANNEL_COUNT
+                )));
+            }
+
+            if let Some(other) = self.joints[..index]
+                .iter()
+                .find(|other| (other.chip, other.channel) == (joint.chip, joint.channel))
+            {
+                return Err(joint_error(format!(
+                    "chip {} channel {} is used by joint {:?}",
+                    joint.chip, joint.channel, other.name
+                )));
+            }
+
+            let Some(settings) = self.profiles.get(&joint.profile) else {
+                return Err(joint_error(format!(
+                    "profile {:?} is not configured, the profiles are {:?}",
+                    joint.profile,
+                    self.profiles.keys().collect::<Vec<_>>()
+                )));
+            };
+
+            // The pulses must fit in the period of the chip the servo is attached to.
+            let period = 1_000_000_f64 / chip.update_rate as f64;
+            let max_pulse_width = settings.start_pulse_width().max(settings.end_pulse_width());
+
+            if max_pulse_width >= period {
+                return Err(joint_error(format!(
+                    "pulse width {} us of profile {:?} does not fit in the {} us period of chip {}",
+                    max_pulse_width, joint.profile, period, joint.chip
+                )));
+            }
+
+            if let Some((min_angle, max_angle)) = joint.limits {
+                if !(min_angle < max_angle
+                    && settings.contains_angle(min_angle)
+                    && settings.contains_angle(max_angle))
+                {
+                    let (start_angle, end_angle) = settings.angle_range();
+
+                    return Err(joint_error(format!(
+                        "limits {} to {} must be increasing and within the range {} to {} of \
+                         profile {:?}",
+                        min_angle, max_angle, start_angle, end_angle, joint.profile
+                    )));
+                }
+            }
+
+            let settings = self.servo_settings(joint);
+
+            if !settings.contains_angle(joint.initial_angle) {
+                let (min_angle, max_angle) = settings.angle_range();
+
+                return Err(joint_error(format!(
+                    "initial angle {} out of range {} to {}",
+                    joint.initial_angle, min_angle, max_angle
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A YAML configuration of an arm with its servos spread over two chips.
+    const YAML_CONFIG: &str = r#"
+listen_address: "127.0.0.1:50052"
+chips:
+  - address_pins: 0
+    oe_pin: 23
+  - address_pins: 1
+    update_rate: 60
+    phase_strategy: staggered
+profiles:
+  linear:
+    start_pulse_width: 600
+    end_pulse_width: 2400
+  calibrated:
+    calibration:
+      points: [[-90, 600], [0, 1450], [90, 2400]]
+      interpolation: spline
+joints:
+  - { name: base, chip: 0, channel: 0, profile: calibrated, limits: [-80, 80] }
+  - { name: shoulder, chip: 0, channel: 1, profile: linear, initial_angle: 10 }
+  - { name: elbow, chip: 0, channel: 2, profile: linear }
+  - { name: wrist, chip: 1, channel: 0, profile: linear }
+  - { name: roll, chip: 1, channel: 1, profile: linear }
+  - { name: gripper, chip: 1, channel: 2, profile: linear }
+"#;
+
+    /// Parses the YAML configuration after applying the given replacement.
+    fn yaml_config(from: &str, to: &str) -> Result<Config, Error> {
+        Config::from_yaml("arm.yaml", &YAML_CONFIG.replace(from, to))
+    }
+
+    #[test]
+    fn test_built_in() {
+        let config = Config::built_in().unwrap();
+
+        assert_eq!(config.chip_addresses().unwrap(), vec![0x40]);
+        assert_eq!(config.chips[0].osc_clock, 26_600_000);
+        assert_eq!(config.chips[0].phase_strategy, PhaseStrategy::Staggered);
+        assert_eq!(config.joints.len(), JOINT_COUNT);
+
+        let settings = config.servo_settings(&config.joints[0]);
+        assert_eq!(settings.start_pulse_width(), 660_f64);
+        assert_eq!(settings.end_pulse_width(), 2540_f64);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let config = yaml_config("", "").unwrap();
+
+        assert_eq!(config.listen_address, "127.0.0.1:50052".parse().unwrap());
+        assert_eq!(config.chip_addresses().unwrap(), vec![0x40, 0x41]);
+        assert_eq!(config.chips[0].osc_clock, INTERNAL_OSC_CLOCK);
+        assert_eq!(config.chips[1].update_rate, 60);
+        assert_eq!(config.chips[1].oe_pin, None);
+
+        // The calibration table sets the range of the servo, and the limits of the joint narrow it.
+        let settings = config.servo_settings(&config.joints[0]);
+        assert_eq!(settings.pulse_width(0_f64), 1450_f64);
+        assert_eq!(settings.angle_range(), (-80_f64, 80_f64));
+        assert!(!settings.contains_angle(85_f64));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(matches!(
+            yaml_config("address_pins: 1", "address_pins: 0"),
+            Err(Error::ChipError { chip: 1, .. })
+        ));
+        assert!(matches!(
+            yaml_config("address_pins: 1", "address_pins: 64"),
+            Err(Error::ChipError { chip: 1, .. })
+        ));
+        assert!(matches!(
+            yaml_config("start_pulse_width: 600", "start_pulse_width: -600"),
+            Err(Error::ProfileError { profile, .. }) if profile == "linear"
+        ));
+        assert!(matches!(
+            yaml_config(
+                "points: [[-90, 600], [0, 1450]",
+                "points: [[-90, 600], [0, 2450]"
+            ),
+            Err(Error::YamlError { .. })
+        ));
+        assert!(matches!(
+            yaml_config(
+                "  - { name: gripper, chip: 1, channel: 2, profile: linear }\n",
+                ""
+            ),
+            Err(Error::JointCountError(5))
+        ));
+        assert!(matches!(
+            yaml_config("chip: 1, channel: 2", "chip: 2, channel: 2"),
+            Err(Error::JointError { joint, .. }) if joint == "gripper"
+        ));
+        assert!(matches!(
+            yaml_config("chip: 1, channel: 2", "chip: 1, channel: 16"),
+            Err(Error::JointError { joint, .. }) if joint == "gripper"
+        ));
+        assert!(matches!(
+            yaml_config("chip: 1, channel: 2", "chip: 1, channel: 1"),
+            Err(Error::JointError { joint, .. }) if joint == "gripper"
+        ));
+        assert!(matches!(
+            yaml_config(
+                "channel: 2, profile: linear }\n  - { name: wrist",
+                "channel: 2, profile: missing }\n  - { name: wrist"
+            ),
+            Err(Error::JointError { joint, .. }) if joint == "elbow"
+        ));
+        assert!(matches!(
+            yaml_config("limits: [-80, 80]", "limits: [80, -80]"),
+            Err(Error::JointError { joint, .. }) if joint == "base"
+        ));
+        assert!(matches!(
+            yaml_config("limits: [-80, 80]", "limits: [-80, 80], initial_angle: -85"),
+            Err(Error::JointError { joint, .. }) if joint == "base"
+        ));
+        assert!(matches!(
+            yaml_config("update_rate: 60", "update_rate: 500"),
+            Err(Error::JointError { joint, .. }) if joint == "wrist"
+        ));
+    }
+
+    #[test]
+    fn test_validate_chips() {
+        // The address of chip 1 would be 0x70, which all the chips listen to.
+        assert!(matches!(
+            yaml_config("address_pins: 1", "address_pins: 0b110000"),
+            Err(Error::ChipError { chip: 1, .. })
+        ));
+
+        // The prescale of 2000 Hz with the internal oscillator is below the minimum of 3, and the
+        //  prescale of 20 Hz above the maximum of 255.
+        for update_rate in [2000, 20] {
+            assert!(matches!(
+                yaml_config("update_rate: 60", &format!("update_rate: {}", update_rate)),
+                Err(Error::ChipError { chip: 1, .. })
+            ));
+        }
+
+        // An external clock runs at up to 50 MHz.
+        let with_osc_clock = |osc_clock: u32| {
+            let chip = format!("update_rate: 60\n    osc_clock: {}", osc_clock);
+            yaml_config("update_rate: 60", &chip)
+        };
+
+        assert!(with_osc_clock(MAX_EXTERNAL_CLOCK).is_ok());
+        assert!(matches!(
+            with_osc_clock(MAX_EXTERNAL_CLOCK + 1),
+            Err(Error::ChipError { chip: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,33 @@
+use com::proto::{rpc_log_api_server::RpcLogApi, RpcLogFilterRequest, RpcLogFilterResponse};
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::logging::LogFilter;
+
+pub struct LogApi {
+    log_filter: LogFilter,
+}
+
+impl LogApi {
+    pub(crate) fn new(log_filter: LogFilter) -> Self {
+        Self { log_filter }
+    }
+}
+
+#[tonic::async_trait]
+impl RpcLogApi for LogApi {
+    async fn set_log_filter(
+        &self,
+        request: Request<RpcLogFilterRequest>,
+    ) -> Result<Response<RpcLogFilterResponse>, Status> {
+        let RpcLogFilterRequest { filter } = request.into_inner();
+
+        let previous_filter = self.log_filter.set_directives(&filter).map_err(|error| {
+            Status::invalid_argument(format!("filter {:?} is invalid: {}", filter, error))
+        })?;
+
+        info!(filter, previous_filter, "log filter changed");
+
+        Ok(Response::new(RpcLogFilterResponse { previous_filter }))
+    }
+}
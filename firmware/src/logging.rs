@@ -0,0 +1,72 @@
+use std::sync::{Arc, Mutex};
+
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// The filter used when `RUST_LOG` is not set, which logs the RPCs, the recoveries and the I2C
+///  errors, but not the individual moves and writes.
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// The filter of the spans and events logged by the firmware, which can be replaced while the
+///  firmware runs.
+///
+/// The filter can be cloned, all the clones change the same filter.
+#[derive(Clone)]
+pub(crate) struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    directives: Arc<Mutex<String>>,
+}
+
+impl LogFilter {
+    /// Installs the global subscriber, which writes to stdout, with the filter from `RUST_LOG`
+    ///  or `DEFAULT_LOG_FILTER`.
+    ///
+    /// # Returns
+    ///
+    /// The filter of the subscriber, or an error if `RUST_LOG` is invalid or a global subscriber
+    ///  was already installed.
+    pub(crate) fn init() -> Result<Self, Box<dyn std::error::Error>> {
+        let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+            .unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_string());
+
+        let (filter, handle) = reload::Layer::new(EnvFilter::try_new(&directives)?);
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer())
+            .try_init()?;
+
+        Ok(Self {
+            handle,
+            directives: Arc::new(Mutex::new(directives)),
+        })
+    }
+
+    /// Gets the directives of the current filter.
+    pub(crate) fn directives(&self) -> String {
+        self.directives.lock().unwrap().clone()
+    }
+
+    /// Replaces the filter, taking effect for all the spans and events from then on.
+    ///
+    /// # Arguments
+    ///
+    /// * `directives` - The directives of the new filter, in the syntax of `RUST_LOG`, such as
+    ///   `info,pca9685=trace`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the directives of the replaced filter, or an error if the directives are invalid.
+    pub(crate) fn set_directives(
+        &self,
+        directives: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let filter = EnvFilter::try_new(directives)?;
+
+        let mut current = self.directives.lock().unwrap();
+        self.handle.reload(filter)?;
+
+        Ok(std::mem::replace(&mut current, directives.to_string()))
+    }
+}
@@ -1,74 +1,184 @@
-use std::sync::Arc;
+use std::{fs::File, time::Duration};
 
 use api::{
     servo_group_reader::{ServoGroupReaderHandle, ServoGroupReaderTask},
     servo_group_writer::ServoGroupWriter,
     ServoGroup,
 };
-use com::proto::rpc_servo_writer_api_server::RpcServoWriterApiServer;
-use pca9685::{device::Device, Driver};
-use pca9685_servo::{servo::Servo, settings::ServoSettings};
+use bus::{Bus, Interface, OutputEnablePin};
+use com::proto::{
+    rpc_log_api_server::RpcLogApiServer, rpc_servo_reader_api_server::RpcServoReaderApiServer,
+    rpc_servo_writer_api_server::RpcServoWriterApiServer,
+};
+use config::{Config, JOINT_COUNT};
+use log_api::LogApi;
+use logging::LogFilter;
+use pca9685::{
+    address::DEFAULT_ALL_CALL_ADDRESS,
+    device::{Device, ErrorCounters, RetryPolicy},
+    driver_set::{ChannelAddress, DriverSet},
+    recording::Recorder,
+    sim, Driver,
+};
+use pca9685_servo::servo::Servo;
+use servo_reader_api::ServoReaderApi;
 use servo_writer_api::ServoWriterApi;
-use rppal::{gpio::Gpio, i2c::I2c};
-use tokio::{sync::Mutex, try_join};
 use tonic::transport::Server;
+use tracing::{error, info, warn};
 
 pub(crate) mod api;
+pub(crate) mod bus;
+pub(crate) mod config;
+pub(crate) mod log_api;
+pub(crate) mod logging;
+pub(crate) mod servo_reader_api;
 pub(crate) mod servo_writer_api;
 
-pub struct ServoSettingsProfiles;
+/// The number of attempts of an I2C transaction, so a single NAK on a noisy bus does not abort a
+///  motion.
+const I2C_ATTEMPTS: u32 = 5;
 
-impl ServoSettingsProfiles {
-    pub fn s06nf_01() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.033_f64)
-            .with_end_duty_cycle(0.127_f64)
-    }
+/// The time after the first attempt of an I2C transaction after which it is not retried anymore,
+///  which is shorter than the update interval of the servos.
+const I2C_DEADLINE: Duration = Duration::from_millis(15);
 
-    pub fn s06nf_02() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.021_f64)
-            .with_end_duty_cycle(0.127_f64)
-    }
+/// The interval at which the PCA9685 chips are checked for a loss of their configuration.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The command line flag that selects the simulated PCA9685 instead of the real hardware.
+const SIMULATE_FLAG: &str = "--simulate";
+
+/// The command line flag, followed by a path, that records all the I2C transactions to the file at
+///  that path, to be inspected with `pca9685-replay`.
+const RECORD_FLAG: &str = "--record";
+
+/// The command line flag, followed by a path, that loads the configuration of the arm from the
+///  TOML or YAML file at that path instead of using the built-in one.
+const CONFIG_FLAG: &str = "--config";
+
+/// The I2C bus, and the OE pin of every configured chip that has one.
+type Hardware = (Bus, Vec<Option<OutputEnablePin>>);
+
+/// Opens the I2C bus and the OE pins of the Raspberry Pi.
+#[cfg(feature = "rppal")]
+fn open_hardware(config: &Config) -> Result<Hardware, Box<dyn std::error::Error>> {
+    let i2c = rppal::i2c::I2c::new()?;
+    let gpio = rppal::gpio::Gpio::new()?;
+
+    let oe_pins = config
+        .chips
+        .iter()
+        .map(|chip| {
+            chip.oe_pin
+                .map(|pin| Ok(OutputEnablePin::Rppal(gpio.get(pin)?.into_output())))
+                .transpose()
+        })
+        .collect::<Result<_, rppal::gpio::Error>>()?;
+
+    Ok((
+        Recorder::new(Interface::Rppal(pca9685::bus::SharedBus::new(i2c))),
+        oe_pins,
+    ))
+}
+
+/// Reports that the real hardware is not available in this build.
+#[cfg(not(feature = "rppal"))]
+fn open_hardware(_config: &Config) -> Result<Hardware, Box<dyn std::error::Error>> {
+    Err(format!("built without the rppal feature, run with {}", SIMULATE_FLAG).into())
+}
+
+/// Creates a simulated I2C bus with a PCA9685 for every configured chip, and simulated OE pins.
+fn open_simulation(config: &Config) -> Result<Hardware, Box<dyn std::error::Error>> {
+    let bus = sim::Bus::new();
 
-    pub fn s06nf_03() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.028_f64)
-            .with_end_duty_cycle(0.127_f64)
+    for address in config.chip_addresses()? {
+        bus.attach(sim::Pca9685::new(address));
     }
 
-    pub fn s06nf_04() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.024_f64)
-            .with_end_duty_cycle(0.128_f64)
+    let oe_pins = config
+        .chips
+        .iter()
+        .map(|chip| {
+            chip.oe_pin
+                .map(|_| OutputEnablePin::Simulated(sim::Pin::new()))
+        })
+        .collect();
+
+    Ok((Recorder::new(Interface::Simulated(bus)), oe_pins))
+}
+
+async fn create_driver_set(
+    config: &Config,
+    simulate: bool,
+    record: Option<String>,
+) -> Result<DriverSet<Bus>, Box<dyn std::error::Error>> {
+    // Open the real hardware, or the simulated one if requested
+    let (bus, oe_pins) = if simulate {
+        open_simulation(config)?
+    } else {
+        open_hardware(config)?
+    };
+
+    // Record the I2C transactions from the start if requested, including the software reset
+    if let Some(path) = record {
+        bus.start(File::create(&path)?)?;
+        info!(path, "recording I2C transactions");
     }
 
-    pub fn s06nf_05() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.026_f64)
-            .with_end_duty_cycle(0.104_f64)
+    // Create a PCA9685 device for every chip
+    let retry_policy = RetryPolicy::new()
+        .with_attempts(I2C_ATTEMPTS)
+        .with_deadline(I2C_DEADLINE);
+
+    let mut devices = config
+        .chip_addresses()?
+        .into_iter()
+        .map(|address| Device::new(bus.clone(), address).with_retry_policy(retry_policy))
+        .collect::<Vec<_>>();
+
+    // The software reset is a general call, which resets all the chips on the bus at once
+    devices[0].software_reset().await?;
+
+    // Create the set of PCA9685 drivers on the bus. All the chips listen to "LED All Calls", so
+    //  they can be turned off at once.
+    let mut builder = DriverSet::builder(bus)
+        .with_all_call_address(DEFAULT_ALL_CALL_ADDRESS)
+        .with_retry_policy(retry_policy);
+
+    for ((device, oe_pin), chip) in devices.into_iter().zip(oe_pins).zip(&config.chips) {
+        let driver = Driver::builder(device)
+            .with_osc_clock(chip.osc_clock)
+            .with_update_rate(chip.update_rate)
+            .with_phase_strategy(chip.phase_strategy);
+
+        builder = builder.with_chip(match oe_pin {
+            Some(oe_pin) => driver.with_oe_pin(oe_pin),
+            None => driver,
+        });
     }
 
-    pub fn s06nf_06() -> ServoSettings {
-        ServoSettings::new()
-            .with_start_angle(-90_f64)
-            .with_end_angle(90_f64)
-            .with_start_duty_cycle(0.026_f64)
-            .with_end_duty_cycle(0.104_f64)
+    let driver_set = builder.build().await?;
+
+    // Wake up the drivers, and report the update rate they actually run at
+    for chip in 0..driver_set.len() as u8 {
+        let mut driver = driver_set.driver(chip)?.lock().await;
+        driver.wake().await?;
+
+        info!(
+            address = driver.address(),
+            update_rate = driver.update_rate(),
+            tick_resolution = driver.tick_resolution(),
+            "PCA9685 woken up"
+        );
     }
+
+    Ok(driver_set)
 }
 
-async fn create_servo_group() -> Result<
+async fn create_servo_group(
+    config: &Config,
+    driver_set: &DriverSet<Bus>,
+) -> Result<
     (
         ServoGroupWriter,
         ServoGroupReaderHandle,
@@ -76,84 +186,143 @@ async fn create_servo_group() -> Result<
     ),
     Box<dyn std::error::Error>,
 > {
-    // Initialize I2C communication
-    let mut i2c = I2c::new()?;
-    i2c.set_slave_address(0b100_0000)?;
-
-    // Create a new PCA9685 device
-    let mut device = Device::new(i2c, 0b100_0000);
-    device.software_reset().await?;
-
-    // Set the OE pin for output enable
-    let oe_pin = Gpio::new().unwrap().get(23).unwrap().into_output();
-
-    // Create a new PCA9685 driver
-    let mut driver = Driver::builder(device, oe_pin)
-        .with_osc_clock(26_600_000)
-        .with_update_rate(50)
-        .build()
-        .unwrap();
-
-    // Wake up the driver
-    driver.wake().await.unwrap();
-
-    // Create an Arc-wrapped Mutex for thread-safe access to the driver
-    let driver = Arc::new(Mutex::new(driver));
-
-    // Create and initialize each servo
-    let s01 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 0_u8),
-        ServoSettingsProfiles::s06nf_01(),
-        0.0_f64,
-    ).await?;
-
-    let s02 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 1_u8),
-        ServoSettingsProfiles::s06nf_02(),
-        0.0_f64,
-    ).await?;
-
-    let s03 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 2_u8),
-        ServoSettingsProfiles::s06nf_03(),
-        0.0_f64,
-    ).await?;
-
-    let s04 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 3_u8),
-        ServoSettingsProfiles::s06nf_04(),
-        0.0_f64,
-    ).await?;
-
-    let s05 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 4_u8),
-        ServoSettingsProfiles::s06nf_05(),
-        0.0_f64,
-    ).await?;
-
-    let s06 = Servo::new(
-        pca9685::Channel::new(driver.clone(), 5_u8),
-        ServoSettingsProfiles::s06nf_06(),
-        0.0_f64,
-    ).await?;
+    // Create and initialize the servo of each joint
+    let mut servos = Vec::with_capacity(JOINT_COUNT);
+
+    for joint in config.joints.iter() {
+        let servo = Servo::new(
+            driver_set.channel(ChannelAddress::new(joint.chip, joint.channel))?,
+            config.servo_settings(joint),
+            joint.initial_angle,
+        )
+        .await?;
+
+        servos.push(servo);
+    }
+
+    let Ok([s01, s02, s03, s04, s05, s06]) = <[_; JOINT_COUNT]>::try_from(servos) else {
+        unreachable!("the configuration has {} joints", JOINT_COUNT);
+    };
 
     Ok(ServoGroup::new(s01, s02, s03, s04, s05, s06))
 }
 
+/// Periodically checks whether the PCA9685 chips lost their configuration, such as after the servo
+///  supply dipped, recovering them if so, and reports new I2C errors.
+async fn check_driver_set(driver_set: DriverSet<Bus>) {
+    let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    let mut reported = vec![ErrorCounters::default(); driver_set.len()];
+    let mut reported_all_call = ErrorCounters::default();
+
+    loop {
+        interval.tick().await;
+
+        for (chip, reported) in (0..driver_set.len() as u8).zip(reported.iter_mut()) {
+            let Ok(driver) = driver_set.driver(chip) else {
+                continue;
+            };
+
+            let mut driver = driver.lock().await;
+
+            match driver.check().await {
+                Ok(Some(event)) => warn!("{}", event),
+                Ok(None) => {}
+                Err(error) => error!(chip, %error, "PCA9685 could not be checked"),
+            }
+
+            let counters = driver.error_counters();
+
+            if counters.errors != reported.errors {
+                warn!(
+                    address = driver.address(),
+                    errors = counters.errors,
+                    retries = counters.retries,
+                    failures = counters.failures,
+                    transactions = counters.transactions,
+                    "I2C errors"
+                );
+
+                *reported = counters;
+            }
+        }
+
+        // The writes to all the chips at once are not counted by any of their drivers
+        if let Some(all_call_address) = driver_set.all_call_address() {
+            let Ok(counters) = driver_set.call_error_counters(all_call_address).await else {
+                continue;
+            };
+
+            if counters.errors != reported_all_call.errors {
+                warn!(
+                    address = all_call_address,
+                    errors = counters.errors,
+                    retries = counters.retries,
+                    failures = counters.failures,
+                    transactions = counters.transactions,
+                    "I2C errors"
+                );
+
+                reported_all_call = counters;
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let (servo_group_writer, servo_group_reader_handle, mut servo_group_reader_task) = create_servo_group().await?;
+    let log_filter = LogFilter::init()?;
+    info!(filter = log_filter.directives(), "logging");
+
+    let simulate = std::env::args().any(|arg| arg == SIMULATE_FLAG);
+    let record = std::env::args().skip_while(|arg| arg != RECORD_FLAG).nth(1);
+    let config_path = std::env::args().skip_while(|arg| arg != CONFIG_FLAG).nth(1);
+
+    // Load the configuration before touching the hardware, so a mistake in it cannot move a servo
+    let config = match &config_path {
+        Some(path) => Config::load(path),
+        None => Config::built_in(),
+    }
+    .inspect_err(|error| error!(%error, "invalid configuration"))?;
+
+    info!(
+        config = config_path.as_deref().unwrap_or("built-in"),
+        chips = config.chips.len(),
+        "configuration loaded"
+    );
+
+    let driver_set = create_driver_set(&config, simulate, record).await?;
+
+    let (servo_group_writer, servo_group_reader_handle, mut servo_group_reader_task) =
+        create_servo_group(&config, &driver_set).await?;
+
+    // Enable the outputs only now that every servo was written to its initial angle, so none of
+    //  them jumps to whatever its channel held before
+    for chip in 0..driver_set.len() as u8 {
+        driver_set.driver(chip)?.lock().await.enable_outputs()?;
+    }
 
     tokio::spawn(async move {
         servo_group_reader_task.run().await.unwrap();
     });
 
+    tokio::spawn(async move {
+        check_driver_set(driver_set).await;
+    });
+
     let servo_writer_api = ServoWriterApi::new(servo_group_writer);
     let servo_writer_api_server = RpcServoWriterApiServer::new(servo_writer_api);
 
+    let servo_reader_api = ServoReaderApi::new(servo_group_reader_handle);
+    let servo_reader_api_server = RpcServoReaderApiServer::new(servo_reader_api);
+
+    let log_api = LogApi::new(log_filter);
+    let log_api_server = RpcLogApiServer::new(log_api);
+
     Server::builder()
         .add_service(servo_writer_api_server)
-        .serve("0.0.0.0:50051".parse()?)
+        .add_service(servo_reader_api_server)
+        .add_service(log_api_server)
+        .serve(config.listen_address)
         .await?;
 
     Ok(())
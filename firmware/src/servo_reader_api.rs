@@ -0,0 +1,54 @@
+use com::proto::{rpc_servo_reader_api_server::RpcServoReaderApi, RpcPose, RpcPoseStreamRequest};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::api::servo_group_reader::ServoGroupReaderHandle;
+
+pub struct ServoReaderApi {
+    servo_group_reader_handle: ServoGroupReaderHandle,
+}
+
+impl ServoReaderApi {
+    /// The number of poses buffered for a single subscriber before the stream applies backpressure.
+    const STREAM_BUFFER_SIZE: usize = 16_usize;
+
+    pub fn new(servo_group_reader_handle: ServoGroupReaderHandle) -> Self {
+        Self {
+            servo_group_reader_handle,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl RpcServoReaderApi for ServoReaderApi {
+    type PoseStreamStream = ReceiverStream<Result<RpcPose, Status>>;
+
+    async fn pose_stream(
+        &self,
+        _request: Request<RpcPoseStreamRequest>,
+    ) -> Result<Response<Self::PoseStreamStream>, Status> {
+        // Give the subscriber its own receiver, so slow subscribers do not affect the others.
+        let mut servo_group_reader_handle = self.servo_group_reader_handle.subscribe();
+
+        let (pose_sender, pose_receiver) = mpsc::channel(Self::STREAM_BUFFER_SIZE);
+
+        tokio::spawn(async move {
+            loop {
+                let pose = servo_group_reader_handle
+                    .recv_pose()
+                    .await
+                    .map_err(|error| Status::unavailable(error.to_string()));
+
+                let closed = pose.is_err();
+
+                // Stop forwarding poses once the subscriber disconnected, or the reader task stopped.
+                if pose_sender.send(pose).await.is_err() || closed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(pose_receiver)))
+    }
+}
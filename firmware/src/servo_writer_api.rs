@@ -1,54 +1,129 @@
 use com::proto::{
     rpc_servo_writer_api_server::RpcServoWriterApi, RpcMultiPoseChangeRequest,
-    RpcMultiPoseChangeResponse, RpcPoseChangeRequest, RpcPoseChangeResponse,
+    RpcMultiPoseChangeResponse, RpcPoseChangeRequest, RpcPoseChangeResponse, RpcStopRequest,
+    RpcStopResponse,
 };
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use tonic::{Request, Response, Status};
+use tracing::{info, instrument};
 
 use crate::api::servo_group_writer::ServoGroupWriter;
 
+/// The id of the next RPC, which identifies the RPC in the spans and events it causes.
+static NEXT_RPC_ID: AtomicU64 = AtomicU64::new(1_u64);
+
+/// Allocates the id of an RPC.
+fn next_rpc_id() -> u64 {
+    NEXT_RPC_ID.fetch_add(1_u64, Ordering::Relaxed)
+}
+
 pub struct ServoWriterApi {
     servo_group_writer: Mutex<ServoGroupWriter>,
+    cancellation_token: std::sync::Mutex<CancellationToken>,
 }
 
 impl ServoWriterApi {
     pub fn new(servo_group_writer: ServoGroupWriter) -> Self {
         let servo_group_writer = Mutex::new(servo_group_writer);
+        let cancellation_token = std::sync::Mutex::new(CancellationToken::new());
 
-        Self { servo_group_writer }
+        Self {
+            servo_group_writer,
+            cancellation_token,
+        }
+    }
+
+    /// Cancels the running pose changes, and creates the token for the pose changes that replace
+    ///  them.
+    ///
+    /// The running pose changes stop at the next update of the servos and release the servo
+    ///  group, so the new pose changes start from the angles the servos stopped at. This happens
+    ///  before the new pose changes are validated, so even an invalid request stops the arm.
+    fn preempt(&self) -> CancellationToken {
+        let cancellation_token = CancellationToken::new();
+
+        let previous = std::mem::replace(
+            &mut *self.cancellation_token.lock().unwrap(),
+            cancellation_token.clone(),
+        );
+
+        previous.cancel();
+
+        cancellation_token
     }
 }
 
 #[tonic::async_trait]
 impl RpcServoWriterApi for ServoWriterApi {
+    #[instrument(name = "change_pose", skip_all, fields(rpc_id = next_rpc_id()), err(Display))]
     async fn change_pose(
         &self,
         request: Request<RpcPoseChangeRequest>,
     ) -> Result<Response<RpcPoseChangeResponse>, Status> {
         let RpcPoseChangeRequest { pose_change } = request.into_inner();
 
+        info!(?pose_change, "pose change requested");
+
+        let cancellation_token = self.preempt();
+
         let pose_change =
             pose_change.ok_or_else(|| Status::invalid_argument("pose_change must be provided"))?;
 
         let mut servos = self.servo_group_writer.lock().await;
 
-        servos.write_rpc_pose_change(pose_change).await?;
+        servos
+            .write_rpc_pose_change(pose_change, &cancellation_token)
+            .await?;
+
+        info!("pose change executed");
 
         Ok(Response::new(RpcPoseChangeResponse {}))
     }
 
+    #[instrument(
+        name = "multi_change_pose",
+        skip_all,
+        fields(rpc_id = next_rpc_id()),
+        err(Display)
+    )]
     async fn multi_change_pose(
         &self,
         request: Request<RpcMultiPoseChangeRequest>,
     ) -> Result<Response<RpcMultiPoseChangeResponse>, Status> {
         let RpcMultiPoseChangeRequest { pose_changes } = request.into_inner();
 
+        info!(count = pose_changes.len(), "pose changes requested");
+
+        let cancellation_token = self.preempt();
+
         let mut servos = self.servo_group_writer.lock().await;
 
-        for pose_change in pose_changes {
-            servos.write_rpc_pose_change(pose_change).await?;
-        }
+        let response = servos
+            .write_rpc_pose_changes(pose_changes, &cancellation_token)
+            .await?;
+
+        info!(
+            executed = response.executed_pose_changes,
+            planned_duration = response.planned_duration,
+            elapsed_time = response.elapsed_time,
+            "pose changes executed"
+        );
+
+        Ok(Response::new(response))
+    }
+
+    #[instrument(name = "stop", skip_all, fields(rpc_id = next_rpc_id()))]
+    async fn stop(
+        &self,
+        _request: Request<RpcStopRequest>,
+    ) -> Result<Response<RpcStopResponse>, Status> {
+        info!("stop requested");
+
+        self.cancellation_token.lock().unwrap().cancel();
 
-        unimplemented!()
+        Ok(Response::new(RpcStopResponse {}))
     }
 }
@@ -0,0 +1,85 @@
+use crate::transform::Transform;
+
+/// The convention used to interpret Denavit–Hartenberg parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Convention {
+    /// The classic convention, where the transformation of a joint is
+    ///  `Rot_z(theta) * Trans_z(d) * Trans_x(a) * Rot_x(alpha)`.
+    #[default]
+    Standard,
+    /// The modified (Craig) convention, where the transformation of a joint is
+    ///  `Rot_x(alpha) * Trans_x(a) * Rot_z(theta) * Trans_z(d)`.
+    Modified,
+}
+
+/// Represents the Denavit–Hartenberg parameters of a single revolute joint.
+///
+/// Angles are in degrees, like the joint angles of the servos. Lengths can be in any unit, as long
+///  as the whole chain uses the same one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DhParameters {
+    /// The link length (distance along the x axis).
+    pub a: f64,
+    /// The link twist (rotation around the x axis) in degrees.
+    pub alpha: f64,
+    /// The link offset (distance along the z axis).
+    pub d: f64,
+    /// The angle added to the joint angle (rotation around the z axis) in degrees.
+    pub theta_offset: f64,
+}
+
+impl DhParameters {
+    /// Creates a new `DhParameters` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The link length.
+    /// * `alpha` - The link twist in degrees.
+    /// * `d` - The link offset.
+    /// * `theta_offset` - The angle added to the joint angle in degrees.
+    ///
+    /// # Returns
+    ///
+    /// A new `DhParameters` instance.
+    pub const fn new(a: f64, alpha: f64, d: f64, theta_offset: f64) -> Self {
+        Self {
+            a,
+            alpha,
+            d,
+            theta_offset,
+        }
+    }
+
+    /// Computes the transformation of the joint for the given joint angle.
+    ///
+    /// # Arguments
+    ///
+    /// * `convention` - The convention the parameters are expressed in.
+    /// * `angle` - The angle of the joint in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The transformation from the frame of the previous joint to the frame of this joint.
+    pub fn transform(&self, convention: Convention, angle: f64) -> Transform {
+        let (st, ct) = (angle + self.theta_offset).to_radians().sin_cos();
+        let (sa, ca) = self.alpha.to_radians().sin_cos();
+        let DhParameters { a, d, .. } = *self;
+
+        let matrix = match convention {
+            Convention::Standard => [
+                [ct, -st * ca, st * sa, a * ct],
+                [st, ct * ca, -ct * sa, a * st],
+                [0_f64, sa, ca, d],
+                [0_f64, 0_f64, 0_f64, 1_f64],
+            ],
+            Convention::Modified => [
+                [ct, -st, 0_f64, a],
+                [st * ca, ct * ca, -sa, -sa * d],
+                [st * sa, ct * sa, ca, ca * d],
+                [0_f64, 0_f64, 0_f64, 1_f64],
+            ],
+        };
+
+        Transform::from_matrix(matrix)
+    }
+}
@@ -0,0 +1,731 @@
+use thiserror::Error;
+
+use crate::{dh::Convention, transform::Transform, KinematicChain};
+
+/// Represents the possible errors that can occur while solving the inverse kinematics.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Unreachable: the target lies further away from the base than the chain can reach.
+    #[error("Target at distance {distance} is out of reach {reach}")]
+    Unreachable { distance: f64, reach: f64 },
+    /// Did not converge: the solver ran out of iterations before reaching the target.
+    #[error("Did not converge after {iterations} iterations (position error {position_error}, orientation error {orientation_error} degrees)")]
+    DidNotConverge {
+        iterations: usize,
+        position_error: f64,
+        orientation_error: f64,
+    },
+    /// Limits violated: the target can only be reached by moving a joint outside its limits.
+    #[error("Joint {joint} would have to move to {angle}, outside of {min} to {max}")]
+    LimitsViolated {
+        joint: usize,
+        angle: f64,
+        min: f64,
+        max: f64,
+    },
+    /// Kinematics error: an error occurred while computing the forward kinematics.
+    #[error("Kinematics error: {0}")]
+    KinematicsError(#[from] crate::Error),
+}
+
+/// Represents the range of angles a joint is allowed to move in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointLimits {
+    /// The minimum angle in degrees.
+    pub min: f64,
+    /// The maximum angle in degrees.
+    pub max: f64,
+}
+
+impl JointLimits {
+    /// Creates a new `JointLimits` instance from the two ends of the range of a joint.
+    ///
+    /// The ends can be given in any order, so the starting and ending angle of a servo can be
+    ///  passed as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_angle` - One end of the range in degrees.
+    /// * `end_angle` - The other end of the range in degrees.
+    ///
+    /// # Returns
+    ///
+    /// A new `JointLimits` instance.
+    pub fn new(start_angle: f64, end_angle: f64) -> Self {
+        Self {
+            min: start_angle.min(end_angle),
+            max: start_angle.max(end_angle),
+        }
+    }
+
+    /// Creates a new `JointLimits` instance that does not limit the joint.
+    pub fn unlimited() -> Self {
+        Self {
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
+        }
+    }
+
+    /// Checks whether the given angle lies within the limits.
+    pub fn contains(&self, angle: f64) -> bool {
+        (self.min..=self.max).contains(&angle)
+    }
+
+    /// Clamps the given angle to the limits.
+    pub fn clamp(&self, angle: f64) -> f64 {
+        angle.clamp(self.min, self.max)
+    }
+}
+
+/// Represents the target of the inverse kinematics, a position and optionally an orientation of
+///  the end effector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    position: [f64; 3],
+    orientation: Option<[[f64; 3]; 3]>,
+}
+
+impl Target {
+    /// Creates a new `Target` that only constrains the position of the end effector.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the end effector in the world frame.
+    ///
+    /// # Returns
+    ///
+    /// A new `Target` instance.
+    pub fn position(position: [f64; 3]) -> Self {
+        Self {
+            position,
+            orientation: None,
+        }
+    }
+
+    /// Creates a new `Target` that constrains both the position and the orientation of the end
+    ///  effector.
+    ///
+    /// # Arguments
+    ///
+    /// * `pose` - The pose of the end effector in the world frame.
+    ///
+    /// # Returns
+    ///
+    /// A new `Target` instance.
+    pub fn pose(pose: Transform) -> Self {
+        Self {
+            position: pose.position(),
+            orientation: Some(pose.rotation()),
+        }
+    }
+}
+
+/// Solves the inverse kinematics of a `KinematicChain` numerically, using damped least squares on
+///  the Jacobian with a Levenberg–Marquardt style adaptive damping factor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IkSolver {
+    max_iterations: usize,
+    damping: f64,
+    max_step: f64,
+    position_tolerance: f64,
+    orientation_tolerance: f64,
+}
+
+impl IkSolver {
+    pub const DEFAULT_MAX_ITERATIONS: usize = 200_usize;
+    pub const DEFAULT_DAMPING: f64 = 0.05_f64;
+    pub const DEFAULT_MAX_STEP: f64 = 10_f64;
+    pub const DEFAULT_POSITION_TOLERANCE: f64 = 1e-3_f64;
+    pub const DEFAULT_ORIENTATION_TOLERANCE: f64 = 0.05_f64;
+
+    /// Creates a new `IkSolver` instance with default values.
+    ///
+    /// # Returns
+    ///
+    /// The new `IkSolver` instance.
+    pub fn new() -> Self {
+        Self {
+            max_iterations: Self::DEFAULT_MAX_ITERATIONS,
+            damping: Self::DEFAULT_DAMPING,
+            max_step: Self::DEFAULT_MAX_STEP,
+            position_tolerance: Self::DEFAULT_POSITION_TOLERANCE,
+            orientation_tolerance: Self::DEFAULT_ORIENTATION_TOLERANCE,
+        }
+    }
+
+    /// Sets the maximum number of iterations and returns the modified `IkSolver` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_iterations`: The maximum number of iterations before giving up.
+    ///
+    /// # Returns
+    ///
+    /// The modified `IkSolver` instance.
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Sets the initial damping factor and returns the modified `IkSolver` instance.
+    ///
+    /// The damping factor is relative to the reach of the chain, so it does not depend on the unit
+    ///  of length the chain is described in. Higher values are more stable near singularities, but
+    ///  converge slower.
+    ///
+    /// # Arguments
+    ///
+    /// * `damping`: The initial damping factor.
+    ///
+    /// # Returns
+    ///
+    /// The modified `IkSolver` instance.
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Sets the largest change of a joint angle in a single iteration and returns the modified
+    ///  `IkSolver` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_step`: The largest change of a joint angle in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The modified `IkSolver` instance.
+    pub fn with_max_step(mut self, max_step: f64) -> Self {
+        self.max_step = max_step;
+        self
+    }
+
+    /// Sets the position tolerance and returns the modified `IkSolver` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `position_tolerance`: The largest accepted distance to the target position, in the unit
+    ///   of length of the chain.
+    ///
+    /// # Returns
+    ///
+    /// The modified `IkSolver` instance.
+    pub fn with_position_tolerance(mut self, position_tolerance: f64) -> Self {
+        self.position_tolerance = position_tolerance;
+        self
+    }
+
+    /// Sets the orientation tolerance and returns the modified `IkSolver` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `orientation_tolerance`: The largest accepted angle to the target orientation in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The modified `IkSolver` instance.
+    pub fn with_orientation_tolerance(mut self, orientation_tolerance: f64) -> Self {
+        self.orientation_tolerance = orientation_tolerance;
+        self
+    }
+
+    /// Computes joint angles that bring the end effector of the chain to the target.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - The chain to solve the inverse kinematics of.
+    /// * `target` - The target of the end effector.
+    /// * `seed` - The joint angles to start searching from in degrees, usually the current pose.
+    /// * `limits` - The limits of every joint.
+    ///
+    /// # Returns
+    ///
+    /// The joint angles in degrees, or an `Error` describing why the target cannot be reached.
+    pub fn solve(
+        &self,
+        chain: &KinematicChain,
+        target: &Target,
+        seed: &[f64],
+        limits: &[JointLimits],
+    ) -> Result<Vec<f64>, Error> {
+        chain.check_joint_count(seed)?;
+
+        if limits.len() != chain.joint_count() {
+            return Err(crate::Error::JointCountMismatch {
+                expected: chain.joint_count(),
+                actual: limits.len(),
+            }
+            .into());
+        }
+
+        // The seed itself must respect the limits.
+        for (joint, (&angle, limits)) in seed.iter().zip(limits.iter()).enumerate() {
+            if !limits.contains(angle) {
+                return Err(Error::LimitsViolated {
+                    joint,
+                    angle,
+                    min: limits.min,
+                    max: limits.max,
+                });
+            }
+        }
+
+        // Reject targets that are further away than the sum of all the link lengths.
+        let reach = reach(chain);
+        let distance = norm(&sub(target.position, chain.base.position()));
+
+        if distance > reach + self.position_tolerance {
+            return Err(Error::Unreachable { distance, reach });
+        }
+
+        // Orientation errors (in radians) are scaled by the reach, so they are of the same order
+        //  of magnitude as the position errors.
+        let scale = if reach > 0_f64 { reach } else { 1_f64 };
+
+        let mut angles = seed.to_vec();
+        let mut error = self.error(chain, target, &angles, scale)?;
+        let mut damping = self.damping * scale;
+        let mut pinned = None;
+
+        for _ in 0..self.max_iterations {
+            if self.converged(&error) {
+                return Ok(angles);
+            }
+
+            let jacobian = jacobian(chain, &angles, target.orientation.is_some(), scale)?;
+
+            let Some(step) = damped_least_squares(&jacobian, &error.residual, damping) else {
+                damping *= 2_f64;
+                continue;
+            };
+
+            // Limit the size of the step, and keep the joints within their limits.
+            let largest = step.iter().fold(0_f64, |largest, x| largest.max(x.abs()));
+            let factor = if largest > self.max_step {
+                self.max_step / largest
+            } else {
+                1_f64
+            };
+
+            let mut candidate = angles.clone();
+            let mut candidate_pinned = None;
+
+            for (joint, (angle, step)) in candidate.iter_mut().zip(step.iter()).enumerate() {
+                let desired = *angle + step * factor;
+                *angle = limits[joint].clamp(desired);
+
+                if *angle != desired {
+                    candidate_pinned.get_or_insert((joint, desired));
+                }
+            }
+
+            let candidate_error = self.error(chain, target, &candidate, scale)?;
+
+            // Accept the step if it improved the solution and decrease the damping, otherwise
+            //  reject it and increase the damping.
+            if candidate_error.squared_norm() < error.squared_norm() {
+                angles = candidate;
+                error = candidate_error;
+                pinned = candidate_pinned;
+                damping = (damping * 0.5_f64).max(self.damping * scale * 1e-3_f64);
+            } else {
+                damping *= 2_f64;
+            }
+        }
+
+        if self.converged(&error) {
+            return Ok(angles);
+        }
+
+        // If a joint was held back by its limits in the last accepted step, that is why the target
+        //  was not reached. Rejected steps did not move the joints, so they pin nothing.
+        if let Some((joint, angle)) = pinned {
+            return Err(Error::LimitsViolated {
+                joint,
+                angle,
+                min: limits[joint].min,
+                max: limits[joint].max,
+            });
+        }
+
+        Err(Error::DidNotConverge {
+            iterations: self.max_iterations,
+            position_error: error.position,
+            orientation_error: error.orientation.to_degrees(),
+        })
+    }
+
+    /// Checks whether the error is within the tolerances.
+    fn converged(&self, error: &Residual) -> bool {
+        error.position <= self.position_tolerance
+            && error.orientation.to_degrees() <= self.orientation_tolerance
+    }
+
+    /// Computes the error between the end effector and the target.
+    fn error(
+        &self,
+        chain: &KinematicChain,
+        target: &Target,
+        angles: &[f64],
+        scale: f64,
+    ) -> Result<Residual, Error> {
+        let end_effector = chain.end_effector(angles)?;
+
+        let position_error = sub(target.position, end_effector.position());
+        let mut residual = position_error.to_vec();
+
+        let orientation = match target.orientation {
+            Some(rotation) => {
+                let target = Transform::from_rotation(rotation);
+
+                // The orientation error is approximated by half the sum of the cross products of the
+                //  current and the target axes, which is the rotation vector for small errors.
+                let mut orientation_error = [0_f64; 3];
+
+                for (current, target) in [
+                    (end_effector.x_axis(), target.x_axis()),
+                    (end_effector.y_axis(), target.y_axis()),
+                    (end_effector.z_axis(), target.z_axis()),
+                ] {
+                    let product = cross(current, target);
+
+                    for (error, product) in orientation_error.iter_mut().zip(product.iter()) {
+                        *error += 0.5_f64 * product;
+                    }
+                }
+
+                residual.extend(orientation_error.iter().map(|error| error * scale));
+
+                rotation_angle(&end_effector.rotation(), &rotation)
+            }
+            None => 0_f64,
+        };
+
+        Ok(Residual {
+            residual,
+            position: norm(&position_error),
+            orientation,
+        })
+    }
+}
+
+impl Default for IkSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error between the end effector and the target.
+struct Residual {
+    /// The position error, followed by the scaled orientation error if the target has one.
+    residual: Vec<f64>,
+    /// The distance to the target position.
+    position: f64,
+    /// The angle to the target orientation in radians.
+    orientation: f64,
+}
+
+impl Residual {
+    fn squared_norm(&self) -> f64 {
+        self.residual.iter().map(|x| x * x).sum()
+    }
+}
+
+/// Computes the largest distance the end effector can be away from the base of the chain.
+fn reach(chain: &KinematicChain) -> f64 {
+    let links: f64 = chain
+        .joints()
+        .iter()
+        .map(|joint| (joint.a * joint.a + joint.d * joint.d).sqrt())
+        .sum();
+
+    links + norm(&chain.tool.position())
+}
+
+/// Computes the geometric Jacobian of the chain, with the joint angles in degrees.
+///
+/// Every column contains the linear velocity of the end effector for a rotation of the joint,
+///  followed by the scaled angular velocity if `orientation` is set.
+fn jacobian(
+    chain: &KinematicChain,
+    angles: &[f64],
+    orientation: bool,
+    scale: f64,
+) -> Result<Vec<Vec<f64>>, Error> {
+    let frames = chain.joint_frames(angles)?;
+    let end_effector = chain.end_effector(angles)?.position();
+
+    let columns = (0..chain.joint_count())
+        .map(|joint| {
+            // The standard convention rotates joint `i` around the z axis of the previous frame,
+            //  the modified convention around the z axis of its own frame.
+            let frame = match chain.convention() {
+                Convention::Standard if joint == 0 => chain.base,
+                Convention::Standard => frames[joint - 1],
+                Convention::Modified => frames[joint],
+            };
+
+            let axis = frame.z_axis();
+            let linear = cross(axis, sub(end_effector, frame.position()));
+
+            let mut column: Vec<f64> = linear.iter().map(|x| x.to_radians()).collect();
+
+            if orientation {
+                column.extend(axis.iter().map(|x| x.to_radians() * scale));
+            }
+
+            column
+        })
+        .collect();
+
+    Ok(columns)
+}
+
+/// Computes a damped least squares step `J^T (J J^T + damping^2 I)^-1 e`.
+///
+/// # Arguments
+///
+/// * `columns` - The columns of the Jacobian.
+/// * `residual` - The error to eliminate.
+/// * `damping` - The damping factor.
+///
+/// # Returns
+///
+/// The change of every joint angle, or `None` if the system could not be solved.
+fn damped_least_squares(columns: &[Vec<f64>], residual: &[f64], damping: f64) -> Option<Vec<f64>> {
+    let rows = residual.len();
+
+    // Compute J J^T + damping^2 I.
+    let mut matrix = vec![vec![0_f64; rows]; rows];
+
+    for (i, values) in matrix.iter_mut().enumerate() {
+        for (j, value) in values.iter_mut().enumerate() {
+            *value = columns.iter().map(|column| column[i] * column[j]).sum();
+        }
+
+        values[i] += damping * damping;
+    }
+
+    let solution = solve_linear(matrix, residual.to_vec())?;
+
+    let step = columns
+        .iter()
+        .map(|column| column.iter().zip(solution.iter()).map(|(a, b)| a * b).sum())
+        .collect();
+
+    Some(step)
+}
+
+/// Solves the linear system `A x = b` using Gaussian elimination with partial pivoting.
+fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+
+    for column in 0..n {
+        let pivot =
+            (column..n).max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+
+        if a[pivot][column].abs() < f64::EPSILON {
+            return None;
+        }
+
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        for row in column + 1..n {
+            let factor = a[row][column] / a[column][column];
+            let pivot_row = a[column].clone();
+
+            for (value, pivot) in a[row].iter_mut().zip(pivot_row.iter()).skip(column) {
+                *value -= factor * pivot;
+            }
+
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = vec![0_f64; n];
+
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+
+    Some(x)
+}
+
+/// Computes the angle of the rotation between two orientations in radians.
+fn rotation_angle(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> f64 {
+    // The trace of A^T B is 1 + 2 cos(angle).
+    let trace: f64 = (0..3)
+        .map(|i| (0..3).map(|k| a[k][i] * b[k][i]).sum::<f64>())
+        .sum();
+
+    ((trace - 1_f64) / 2_f64).clamp(-1_f64, 1_f64).acos()
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: &[f64]) -> f64 {
+    a.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::dh::DhParameters;
+
+    /// A planar arm with three links of length 10, 8 and 4.
+    fn planar_arm() -> KinematicChain {
+        KinematicChain::new(Convention::Standard)
+            .with_joint(DhParameters::new(10_f64, 0_f64, 0_f64, 0_f64))
+            .with_joint(DhParameters::new(8_f64, 0_f64, 0_f64, 0_f64))
+            .with_joint(DhParameters::new(4_f64, 0_f64, 0_f64, 0_f64))
+    }
+
+    /// A spatial arm with a rotating base, a shoulder, an elbow and a wrist.
+    fn spatial_arm() -> KinematicChain {
+        KinematicChain::new(Convention::Standard)
+            .with_joint(DhParameters::new(0_f64, 90_f64, 5_f64, 0_f64))
+            .with_joint(DhParameters::new(10_f64, 0_f64, 0_f64, 90_f64))
+            .with_joint(DhParameters::new(8_f64, 0_f64, 0_f64, 0_f64))
+            .with_joint(DhParameters::new(0_f64, 90_f64, 0_f64, 0_f64))
+            .with_joint(DhParameters::new(0_f64, -90_f64, 4_f64, 0_f64))
+            .with_joint(DhParameters::new(0_f64, 0_f64, 2_f64, 0_f64))
+    }
+
+    fn limits(count: usize) -> Vec<JointLimits> {
+        vec![JointLimits::new(-90_f64, 90_f64); count]
+    }
+
+    #[test]
+    fn test_solve_position() {
+        let chain = spatial_arm();
+        let expected = chain
+            .end_effector(&[20_f64, -30_f64, 40_f64, 10_f64, 15_f64, 0_f64])
+            .unwrap();
+
+        let angles = IkSolver::new()
+            .solve(
+                &chain,
+                &Target::position(expected.position()),
+                &[0_f64; 6],
+                &limits(6),
+            )
+            .unwrap();
+
+        let actual = chain.end_effector(&angles).unwrap().position();
+        assert!(norm(&sub(actual, expected.position())) < 1e-3);
+    }
+
+    #[test]
+    fn test_solve_pose() {
+        let chain = spatial_arm();
+        let expected = chain
+            .end_effector(&[-25_f64, -20_f64, 50_f64, 30_f64, -20_f64, 45_f64])
+            .unwrap();
+
+        let angles = IkSolver::new()
+            .with_max_iterations(500)
+            .solve(
+                &chain,
+                &Target::pose(expected),
+                &[0_f64, 0_f64, 30_f64, 0_f64, 0_f64, 0_f64],
+                &limits(6),
+            )
+            .unwrap();
+
+        let actual = chain.end_effector(&angles).unwrap();
+        assert!(norm(&sub(actual.position(), expected.position())) < 1e-3);
+        assert!(rotation_angle(&actual.rotation(), &expected.rotation()).to_degrees() < 0.05);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let result = IkSolver::new().solve(
+            &planar_arm(),
+            &Target::position([30_f64, 0_f64, 0_f64]),
+            &[0_f64; 3],
+            &limits(3),
+        );
+
+        assert!(matches!(result, Err(Error::Unreachable { .. })));
+    }
+
+    #[test]
+    fn test_limits_violated() {
+        // Reaching a point close to the base requires folding the arm beyond its limits.
+        let limits = vec![
+            JointLimits::new(-90_f64, 90_f64),
+            JointLimits::new(-30_f64, 30_f64),
+            JointLimits::new(-30_f64, 30_f64),
+        ];
+
+        let result = IkSolver::new().solve(
+            &planar_arm(),
+            &Target::position([2_f64, 1_f64, 0_f64]),
+            &[0_f64; 3],
+            &limits,
+        );
+
+        assert!(matches!(result, Err(Error::LimitsViolated { .. })));
+
+        let result = IkSolver::new().solve(
+            &planar_arm(),
+            &Target::position([2_f64, 1_f64, 0_f64]),
+            &[0_f64, 45_f64, 0_f64],
+            &limits,
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::LimitsViolated { joint: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_did_not_converge() {
+        let result = IkSolver::new().with_max_iterations(1).solve(
+            &planar_arm(),
+            &Target::position([0_f64, 15_f64, 0_f64]),
+            &[0_f64; 3],
+            &[JointLimits::unlimited(); 3],
+        );
+
+        assert!(matches!(result, Err(Error::DidNotConverge { .. })));
+    }
+
+    #[test]
+    fn test_rejected_step_not_pinned() {
+        // The only step is clamped by the limits of the base joint, but is rejected as it does
+        //  not get closer to the target, so the solver stalled rather than being held back.
+        let limits = vec![
+            JointLimits::new(-21_f64, -19_f64),
+            JointLimits::new(-90_f64, 90_f64),
+            JointLimits::new(-90_f64, 90_f64),
+        ];
+
+        let result = IkSolver::new()
+            .with_max_iterations(1)
+            .with_damping(1e-3_f64)
+            .with_max_step(5_f64)
+            .solve(
+                &planar_arm(),
+                &Target::position([0_f64, 15_f64, 0_f64]),
+                &[-20_f64, -20_f64, 0_f64],
+                &limits,
+            );
+
+        assert!(matches!(result, Err(Error::DidNotConverge { .. })));
+    }
+}
@@ -0,0 +1,340 @@
+use dh::{Convention, DhParameters};
+use thiserror::Error;
+use transform::Transform;
+
+pub mod dh;
+pub mod ik;
+pub mod transform;
+
+/// Represents the possible errors that can occur while computing the kinematics of a chain.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Joint count mismatch: the number of joint angles does not match the number of joints.
+    #[error("Expected {expected} joint angles, got {actual}")]
+    JointCountMismatch { expected: usize, actual: usize },
+}
+
+/// The number of joints of the arm, which is also the number of angles in an `RpcPose`.
+pub const ARM_JOINT_COUNT: usize = 6;
+
+/// The DH parameters (standard convention) of the joints of the arm, from the base to the
+///  gripper, with lengths in millimeters. The offsets are chosen so that the arm stands upright
+///  when all servos are at 0 degrees.
+const ARM_JOINTS: [DhParameters; ARM_JOINT_COUNT] = [
+    // Base: turns the arm around the vertical axis, on top of the 105 mm high base.
+    DhParameters::new(0_f64, 90_f64, 105_f64, 0_f64),
+    // Shoulder: tilts the 105 mm upper arm.
+    DhParameters::new(105_f64, 0_f64, 0_f64, 90_f64),
+    // Elbow: tilts the forearm, which is turned upright by the offset.
+    DhParameters::new(0_f64, 90_f64, 0_f64, 90_f64),
+    // Forearm roll: turns the 98 mm forearm around its own axis.
+    DhParameters::new(0_f64, -90_f64, 98_f64, 0_f64),
+    // Wrist pitch.
+    DhParameters::new(0_f64, 90_f64, 0_f64, 0_f64),
+    // Wrist roll: turns the gripper, whose tip is 65 mm beyond the wrist.
+    DhParameters::new(0_f64, 0_f64, 65_f64, 0_f64),
+];
+
+/// Represents a serial chain of revolute joints, described by Denavit–Hartenberg parameters.
+///
+/// Joint angles are in degrees, and are given in the order of the joints from the base to the end
+///  effector, like the angles of an `RpcPose`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KinematicChain {
+    convention: Convention,
+    joints: Vec<DhParameters>,
+    base: Transform,
+    tool: Transform,
+}
+
+impl KinematicChain {
+    /// Creates a new `KinematicChain` without any joints.
+    ///
+    /// # Arguments
+    ///
+    /// * `convention` - The convention the DH parameters of the joints are expressed in.
+    ///
+    /// # Returns
+    ///
+    /// A new `KinematicChain` instance.
+    pub fn new(convention: Convention) -> Self {
+        Self {
+            convention,
+            joints: Vec::new(),
+            base: Transform::IDENTITY,
+            tool: Transform::IDENTITY,
+        }
+    }
+
+    /// Creates the `KinematicChain` of the arm, with its base at the origin of the world frame and
+    ///  the tip of the gripper as the end effector.
+    ///
+    /// # Returns
+    ///
+    /// A new `KinematicChain` instance with `ARM_JOINT_COUNT` joints, whose angles are those of an
+    ///  `RpcPose`.
+    pub fn arm() -> Self {
+        ARM_JOINTS
+            .into_iter()
+            .fold(Self::new(Convention::Standard), Self::with_joint)
+    }
+
+    /// Appends a joint to the end of the chain and returns the modified `KinematicChain` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters` - The DH parameters of the joint.
+    ///
+    /// # Returns
+    ///
+    /// The modified `KinematicChain` instance.
+    pub fn with_joint(mut self, parameters: DhParameters) -> Self {
+        self.joints.push(parameters);
+        self
+    }
+
+    /// Sets the pose of the base of the chain in the world frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The transformation from the world frame to the base frame.
+    ///
+    /// # Returns
+    ///
+    /// The modified `KinematicChain` instance.
+    pub fn with_base(mut self, base: Transform) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Sets the pose of the tool (such as a gripper) relative to the frame of the last joint.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - The transformation from the frame of the last joint to the tool frame.
+    ///
+    /// # Returns
+    ///
+    /// The modified `KinematicChain` instance.
+    pub fn with_tool(mut self, tool: Transform) -> Self {
+        self.tool = tool;
+        self
+    }
+
+    /// Gets the convention the DH parameters of the joints are expressed in.
+    pub fn convention(&self) -> Convention {
+        self.convention
+    }
+
+    /// Gets the DH parameters of the joints.
+    pub fn joints(&self) -> &[DhParameters] {
+        &self.joints
+    }
+
+    /// Gets the number of joints in the chain.
+    pub fn joint_count(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// Computes the frame of every joint for the given joint angles.
+    ///
+    /// # Arguments
+    ///
+    /// * `angles` - The angle of every joint in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The pose of the frame of every joint in the world frame, or an `Error` if the number of
+    ///  angles does not match the number of joints.
+    pub fn joint_frames(&self, angles: &[f64]) -> Result<Vec<Transform>, Error> {
+        self.check_joint_count(angles)?;
+
+        let mut frame = self.base;
+
+        let frames = self
+            .joints
+            .iter()
+            .zip(angles.iter())
+            .map(|(joint, &angle)| {
+                frame = frame * joint.transform(self.convention, angle);
+                frame
+            })
+            .collect();
+
+        Ok(frames)
+    }
+
+    /// Computes the pose of the end effector for the given joint angles.
+    ///
+    /// # Arguments
+    ///
+    /// * `angles` - The angle of every joint in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The pose of the tool frame in the world frame, or an `Error` if the number of angles does
+    ///  not match the number of joints.
+    pub fn end_effector(&self, angles: &[f64]) -> Result<Transform, Error> {
+        let frames = self.joint_frames(angles)?;
+        let last = frames.last().copied().unwrap_or(self.base);
+
+        Ok(last * self.tool)
+    }
+
+    /// Computes the positions of the joints for the given joint angles, such as for drawing the arm.
+    ///
+    /// # Arguments
+    ///
+    /// * `angles` - The angle of every joint in degrees.
+    ///
+    /// # Returns
+    ///
+    /// The position of the base, followed by the origin of every joint frame. When a tool is set,
+    ///  the position of the tool is appended, so the last position always is the end effector.
+    pub fn joint_positions(&self, angles: &[f64]) -> Result<Vec<[f64; 3]>, Error> {
+        let frames = self.joint_frames(angles)?;
+
+        let mut positions = Vec::with_capacity(frames.len() + 2);
+        positions.push(self.base.position());
+        positions.extend(frames.iter().map(Transform::position));
+
+        if self.tool != Transform::IDENTITY {
+            let last = frames.last().copied().unwrap_or(self.base);
+            positions.push((last * self.tool).position());
+        }
+
+        Ok(positions)
+    }
+
+    /// Computes the pose of the end effector of the arm for the angles of a pose.
+    ///
+    /// # Arguments
+    ///
+    /// * `angles` - The angles of the servos in degrees, in the order of an `RpcPose`.
+    ///
+    /// # Returns
+    ///
+    /// The pose of the tip of the gripper in the world frame, in millimeters.
+    pub fn arm_end_effector(angles: [f64; ARM_JOINT_COUNT]) -> Transform {
+        Self::arm()
+            .end_effector(&angles)
+            .expect("the arm has a joint for every angle")
+    }
+
+    /// Computes the positions of the joints of the arm for the angles of a pose.
+    ///
+    /// # Arguments
+    ///
+    /// * `angles` - The angles of the servos in degrees, in the order of an `RpcPose`.
+    ///
+    /// # Returns
+    ///
+    /// The position of the base, followed by the origin of every joint frame, in millimeters.
+    pub fn arm_joint_positions(angles: [f64; ARM_JOINT_COUNT]) -> Vec<[f64; 3]> {
+        Self::arm()
+            .joint_positions(&angles)
+            .expect("the arm has a joint for every angle")
+    }
+
+    /// Checks whether the number of joint angles matches the number of joints.
+    fn check_joint_count(&self, angles: &[f64]) -> Result<(), Error> {
+        if angles.len() != self.joints.len() {
+            return Err(Error::JointCountMismatch {
+                expected: self.joints.len(),
+                actual: angles.len(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f64; 3], b: [f64; 3]) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+
+    /// A planar arm with two links of length 10 and 5.
+    fn planar_arm(convention: Convention) -> KinematicChain {
+        match convention {
+            Convention::Standard => KinematicChain::new(convention)
+                .with_joint(DhParameters::new(10_f64, 0_f64, 0_f64, 0_f64))
+                .with_joint(DhParameters::new(5_f64, 0_f64, 0_f64, 0_f64)),
+            Convention::Modified => KinematicChain::new(convention)
+                .with_joint(DhParameters::new(0_f64, 0_f64, 0_f64, 0_f64))
+                .with_joint(DhParameters::new(10_f64, 0_f64, 0_f64, 0_f64))
+                .with_tool(Transform::from_translation([5_f64, 0_f64, 0_f64])),
+        }
+    }
+
+    #[test]
+    fn test_planar_arm() {
+        for convention in [Convention::Standard, Convention::Modified] {
+            let chain = planar_arm(convention);
+
+            let end_effector = chain.end_effector(&[90_f64, -90_f64]).unwrap();
+            assert_close(end_effector.position(), [5_f64, 10_f64, 0_f64]);
+            assert_close(end_effector.x_axis(), [1_f64, 0_f64, 0_f64]);
+
+            let end_effector = chain.end_effector(&[30_f64, 60_f64]).unwrap();
+            let (s, c) = 30_f64.to_radians().sin_cos();
+            assert_close(
+                end_effector.position(),
+                [10_f64 * c, 10_f64 * s + 5_f64, 0_f64],
+            );
+        }
+    }
+
+    #[test]
+    fn test_joint_positions() {
+        let chain = KinematicChain::new(Convention::Standard)
+            .with_base(Transform::from_translation([0_f64, 0_f64, 2_f64]))
+            .with_joint(DhParameters::new(0_f64, 90_f64, 3_f64, 0_f64))
+            .with_joint(DhParameters::new(4_f64, 0_f64, 0_f64, 0_f64));
+
+        let positions = chain.joint_positions(&[0_f64, 90_f64]).unwrap();
+
+        assert_eq!(positions.len(), 3);
+        assert_close(positions[0], [0_f64, 0_f64, 2_f64]);
+        assert_close(positions[1], [0_f64, 0_f64, 5_f64]);
+        assert_close(positions[2], [0_f64, 0_f64, 9_f64]);
+    }
+
+    #[test]
+    fn test_arm() {
+        let chain = KinematicChain::arm();
+        assert_eq!(chain.joint_count(), ARM_JOINT_COUNT);
+
+        let end_effector = KinematicChain::arm_end_effector([0_f64; ARM_JOINT_COUNT]);
+        assert_close(end_effector.position(), [0_f64, 0_f64, 373_f64]);
+        assert_close(end_effector.z_axis(), [0_f64, 0_f64, 1_f64]);
+
+        let positions =
+            KinematicChain::arm_joint_positions([0_f64, 90_f64, 0_f64, 0_f64, 0_f64, 0_f64]);
+        assert_eq!(positions.len(), ARM_JOINT_COUNT + 1);
+        assert_close(positions[1], [0_f64, 0_f64, 105_f64]);
+        assert_close(positions[ARM_JOINT_COUNT], [-268_f64, 0_f64, 105_f64]);
+
+        let end_effector =
+            KinematicChain::arm_end_effector([90_f64, 0_f64, 90_f64, 0_f64, 0_f64, 0_f64]);
+        assert_close(end_effector.position(), [0_f64, -163_f64, 210_f64]);
+    }
+
+    #[test]
+    fn test_joint_count_mismatch() {
+        let chain = planar_arm(Convention::Standard);
+
+        assert!(matches!(
+            chain.end_effector(&[0_f64; 3]),
+            Err(Error::JointCountMismatch {
+                expected: 2,
+                actual: 3
+            })
+        ));
+    }
+}
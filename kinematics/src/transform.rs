@@ -0,0 +1,260 @@
+use std::ops::Mul;
+
+/// Represents a rigid transformation in 3D space, stored as a homogeneous 4x4 matrix.
+///
+/// A transformation describes the pose of a frame: its position is the translation part and its
+///  orientation the rotation part of the matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    matrix: [[f64; 4]; 4],
+}
+
+impl Transform {
+    /// The identity transformation.
+    pub const IDENTITY: Transform = Transform {
+        matrix: [
+            [1_f64, 0_f64, 0_f64, 0_f64],
+            [0_f64, 1_f64, 0_f64, 0_f64],
+            [0_f64, 0_f64, 1_f64, 0_f64],
+            [0_f64, 0_f64, 0_f64, 1_f64],
+        ],
+    };
+
+    /// Creates a new `Transform` from a homogeneous 4x4 matrix (row major).
+    ///
+    /// # Arguments
+    ///
+    /// * `matrix` - The matrix, of which the last row must be `[0, 0, 0, 1]`.
+    ///
+    /// # Returns
+    ///
+    /// A new `Transform` instance.
+    pub fn from_matrix(matrix: [[f64; 4]; 4]) -> Self {
+        Self { matrix }
+    }
+
+    /// Creates a new `Transform` that only translates.
+    ///
+    /// # Arguments
+    ///
+    /// * `translation` - The translation along the x, y and z axis.
+    ///
+    /// # Returns
+    ///
+    /// A new `Transform` instance.
+    pub fn from_translation([x, y, z]: [f64; 3]) -> Self {
+        let mut transform = Self::IDENTITY;
+
+        transform.matrix[0][3] = x;
+        transform.matrix[1][3] = y;
+        transform.matrix[2][3] = z;
+
+        transform
+    }
+
+    /// Creates a new `Transform` that only rotates.
+    ///
+    /// # Arguments
+    ///
+    /// * `rotation` - The 3x3 rotation matrix (row major).
+    ///
+    /// # Returns
+    ///
+    /// A new `Transform` instance.
+    pub fn from_rotation(rotation: [[f64; 3]; 3]) -> Self {
+        let mut transform = Self::IDENTITY;
+
+        for (row, values) in rotation.iter().enumerate() {
+            transform.matrix[row][..3].copy_from_slice(values);
+        }
+
+        transform
+    }
+
+    /// Creates a new `Transform` that rotates around the x, y and z axis of the fixed frame, in
+    ///  that order.
+    ///
+    /// # Arguments
+    ///
+    /// * `roll` - The rotation around the x axis in degrees.
+    /// * `pitch` - The rotation around the y axis in degrees.
+    /// * `yaw` - The rotation around the z axis in degrees.
+    ///
+    /// # Returns
+    ///
+    /// A new `Transform` instance.
+    pub fn from_roll_pitch_yaw(roll: f64, pitch: f64, yaw: f64) -> Self {
+        let (sr, cr) = roll.to_radians().sin_cos();
+        let (sp, cp) = pitch.to_radians().sin_cos();
+        let (sy, cy) = yaw.to_radians().sin_cos();
+
+        Self::from_rotation([
+            [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+            [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+            [-sp, cp * sr, cp * cr],
+        ])
+    }
+
+    /// Gets the homogeneous 4x4 matrix (row major).
+    pub fn matrix(&self) -> [[f64; 4]; 4] {
+        self.matrix
+    }
+
+    /// Gets the position of the frame, which is the translation part of the transformation.
+    pub fn position(&self) -> [f64; 3] {
+        [self.matrix[0][3], self.matrix[1][3], self.matrix[2][3]]
+    }
+
+    /// Gets the orientation of the frame, which is the rotation part of the transformation.
+    pub fn rotation(&self) -> [[f64; 3]; 3] {
+        let mut rotation = [[0_f64; 3]; 3];
+
+        for (row, values) in rotation.iter_mut().enumerate() {
+            values.copy_from_slice(&self.matrix[row][..3]);
+        }
+
+        rotation
+    }
+
+    /// Gets the direction of the x axis of the frame.
+    pub fn x_axis(&self) -> [f64; 3] {
+        self.column(0)
+    }
+
+    /// Gets the direction of the y axis of the frame.
+    pub fn y_axis(&self) -> [f64; 3] {
+        self.column(1)
+    }
+
+    /// Gets the direction of the z axis of the frame, which is the rotation axis of a DH joint.
+    pub fn z_axis(&self) -> [f64; 3] {
+        self.column(2)
+    }
+
+    /// Computes the roll, pitch and yaw angles of the orientation of the frame.
+    ///
+    /// # Returns
+    ///
+    /// The rotation around the x, y and z axis in degrees, as used by `from_roll_pitch_yaw`.
+    pub fn roll_pitch_yaw(&self) -> [f64; 3] {
+        let [[r00, _, _], [r10, _, _], [r20, r21, r22]] = self.rotation();
+
+        let roll = r21.atan2(r22);
+        let pitch = (-r20).atan2((r00 * r00 + r10 * r10).sqrt());
+        let yaw = r10.atan2(r00);
+
+        [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()]
+    }
+
+    /// Applies the transformation to a point.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - The point to transform.
+    ///
+    /// # Returns
+    ///
+    /// The transformed point.
+    pub fn transform_point(&self, point: [f64; 3]) -> [f64; 3] {
+        let mut result = [0_f64; 3];
+
+        for (row, value) in result.iter_mut().enumerate() {
+            *value = (0..3)
+                .map(|column| self.matrix[row][column] * point[column])
+                .sum::<f64>()
+                + self.matrix[row][3];
+        }
+
+        result
+    }
+
+    /// Computes the inverse of the transformation.
+    ///
+    /// # Returns
+    ///
+    /// The transformation that undoes this transformation.
+    pub fn inverse(&self) -> Self {
+        let rotation = self.rotation();
+
+        // The inverse of a rotation is its transpose.
+        let mut transposed = [[0_f64; 3]; 3];
+
+        for (row, values) in transposed.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = rotation[column][row];
+            }
+        }
+
+        let mut inverse = Self::from_rotation(transposed);
+        let [x, y, z] = inverse.transform_point(self.position());
+
+        inverse.matrix[0][3] = -x;
+        inverse.matrix[1][3] = -y;
+        inverse.matrix[2][3] = -z;
+
+        inverse
+    }
+
+    /// Gets the first three elements of the given column of the matrix.
+    fn column(&self, column: usize) -> [f64; 3] {
+        [
+            self.matrix[0][column],
+            self.matrix[1][column],
+            self.matrix[2][column],
+        ]
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Self::Output {
+        let mut matrix = [[0_f64; 4]; 4];
+
+        for (row, values) in matrix.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = (0..4)
+                    .map(|i| self.matrix[row][i] * rhs.matrix[i][column])
+                    .sum();
+            }
+        }
+
+        Self { matrix }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f64; 3], b: [f64; 3]) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_roll_pitch_yaw_round_trip() {
+        let transform = Transform::from_roll_pitch_yaw(10_f64, -20_f64, 30_f64);
+
+        assert_close(transform.roll_pitch_yaw(), [10_f64, -20_f64, 30_f64]);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Transform::from_translation([1_f64, 2_f64, 3_f64])
+            * Transform::from_roll_pitch_yaw(45_f64, 10_f64, -60_f64);
+
+        let point = [4_f64, -5_f64, 6_f64];
+        let transformed = transform.transform_point(point);
+
+        assert_close(transform.inverse().transform_point(transformed), point);
+        assert_close((transform * transform.inverse()).position(), [0_f64; 3]);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    device::GENERAL_CALL_ADDRESS,
+    memory::{
+        ALLCALLADR_DEFAULT, MODE1_SUB1_BIT, MODE1_SUB2_BIT, MODE1_SUB3_BIT, SUBADR1_ADDR,
+        SUBADR1_DEFAULT, SUBADR2_ADDR, SUBADR2_DEFAULT, SUBADR3_ADDR, SUBADR3_DEFAULT,
+    },
+    Error,
+};
+
+/// The I2C address of a PCA9685 with all of the A0 to A5 pins tied to ground.
+pub const BASE_ADDRESS: u8 = 0b100_0000_u8;
+
+/// The LED All Call address the PCA9685 responds to after power-up.
+pub const DEFAULT_ALL_CALL_ADDRESS: u8 = ALLCALLADR_DEFAULT >> 1_u8;
+
+/// The number of hardware address pins (A0 to A5).
+const ADDRESS_PIN_COUNT: u8 = 6_u8;
+
+/// Computes the I2C address of a PCA9685 from the levels of its hardware address pins (as
+///  described in section "7.1.1").
+///
+/// # Arguments
+///
+/// * `pins` - The levels of the A0 to A5 pins, with A0 in the least significant bit.
+///
+/// # Returns
+///
+/// Returns the I2C address, or an `Error` if a bit other than A0 to A5 is set.
+pub fn from_pins(pins: u8) -> Result<u8, Error> {
+    if pins >> ADDRESS_PIN_COUNT != 0 {
+        return Err(Error::AddressError(pins));
+    }
+
+    Ok(BASE_ADDRESS | pins)
+}
+
+/// Checks that the given value is an I2C address a PCA9685 can respond to.
+///
+/// # Arguments
+///
+/// * `address` - The 7-bit I2C address.
+///
+/// # Returns
+///
+/// Returns the address, or an `Error` if it is not a 7-bit address or it is the general call
+///  address.
+pub(crate) fn validate(address: u8) -> Result<u8, Error> {
+    if address > 0x7F_u8 || address == GENERAL_CALL_ADDRESS {
+        return Err(Error::AddressError(address));
+    }
+
+    Ok(address)
+}
+
+/// One of the three programmable sub-addresses of the PCA9685, which allow a group of chips to
+///  be written to at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubAddress {
+    Sub1,
+    Sub2,
+    Sub3,
+}
+
+impl SubAddress {
+    /// All the sub-addresses, in order.
+    pub const ALL: [SubAddress; 3] = [SubAddress::Sub1, SubAddress::Sub2, SubAddress::Sub3];
+
+    /// Gets the address the PCA9685 assigns to the sub-address after power-up.
+    pub fn default_address(&self) -> u8 {
+        match self {
+            SubAddress::Sub1 => SUBADR1_DEFAULT >> 1_u8,
+            SubAddress::Sub2 => SUBADR2_DEFAULT >> 1_u8,
+            SubAddress::Sub3 => SUBADR3_DEFAULT >> 1_u8,
+        }
+    }
+
+    /// Gets the index of the sub-address, from 0 to 2.
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            SubAddress::Sub1 => 0,
+            SubAddress::Sub2 => 1,
+            SubAddress::Sub3 => 2,
+        }
+    }
+
+    /// Gets the register that holds the sub-address.
+    pub(crate) fn register(&self) -> u8 {
+        match self {
+            SubAddress::Sub1 => SUBADR1_ADDR,
+            SubAddress::Sub2 => SUBADR2_ADDR,
+            SubAddress::Sub3 => SUBADR3_ADDR,
+        }
+    }
+
+    /// Gets the bit of the MODE1 register that makes the chip respond to the sub-address.
+    pub(crate) fn mode1_bit(&self) -> u8 {
+        match self {
+            SubAddress::Sub1 => MODE1_SUB1_BIT,
+            SubAddress::Sub2 => MODE1_SUB2_BIT,
+            SubAddress::Sub3 => MODE1_SUB3_BIT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pins() {
+        assert_eq!(from_pins(0b00_0000).unwrap(), 0x40_u8);
+        assert_eq!(from_pins(0b00_0101).unwrap(), 0x45_u8);
+        assert_eq!(from_pins(0b11_1111).unwrap(), 0x7F_u8);
+        assert!(matches!(from_pins(0b100_0000), Err(Error::AddressError(_))));
+
+        assert_eq!(DEFAULT_ALL_CALL_ADDRESS, 0x70_u8);
+        assert_eq!(SubAddress::Sub2.default_address(), 0x72_u8);
+    }
+}
@@ -0,0 +1,420 @@
+//! Decodes a recording of the I2C transactions sent to PCA9685 chips, as made with
+//!  `pca9685::recording::Recorder`, into register operations, and optionally replays it against
+//!  simulated chips.
+//!
+//! Usage: `pca9685-replay <RECORDING> [--osc-clock <HZ>] [--replay] [--realtime]
+//!  [--chip <ADDRESS>]...`
+
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::BufReader,
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use embedded_hal::i2c::{I2c, Operation};
+use pca9685::{
+    recording::{Decoder, Reader, Record, RecordKind},
+    sim::{Bus, Pca9685, OSCILLATOR_SETTLE_TIME},
+    INTERNAL_OSC_CLOCK,
+};
+
+/// The usage of the command line.
+const USAGE: &str = "pca9685-replay <RECORDING> [--osc-clock <HZ>] [--replay] [--realtime] \
+    [--chip <ADDRESS>]...";
+
+/// The options of the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    path: String,
+    osc_clock: u32,
+    replay: bool,
+    realtime: bool,
+    chips: Vec<u8>,
+}
+
+impl Options {
+    /// Parses the options from the command line arguments.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut path = None;
+        let mut osc_clock = INTERNAL_OSC_CLOCK;
+        let mut replay = false;
+        let mut realtime = false;
+        let mut chips = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--osc-clock" => {
+                    let value = args.next().ok_or("--osc-clock requires a frequency")?;
+                    osc_clock = value
+                        .parse()
+                        .map_err(|_| format!("invalid oscillator clock {}", value))?;
+                }
+                "--replay" => replay = true,
+                "--realtime" => realtime = true,
+                "--chip" => {
+                    let value = args.next().ok_or("--chip requires an address")?;
+                    chips.push(parse_address(&value)?);
+                }
+                _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(Self {
+            path: path.ok_or("a recording must be provided")?,
+            osc_clock,
+            replay,
+            realtime,
+            chips,
+        })
+    }
+}
+
+/// Parses an I2C address, in hexadecimal with a `0x` prefix or in decimal.
+fn parse_address(value: &str) -> Result<u8, String> {
+    let address = match value.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    address.map_err(|_| format!("invalid address {}", value))
+}
+
+/// Prints the register operations of every record.
+fn decode(records: &[Record], osc_clock: u32) {
+    let mut decoder = Decoder::new(osc_clock);
+
+    for record in records {
+        let failed = if record.failed { " (failed)" } else { "" };
+
+        for operation in decoder.decode(record) {
+            println!(
+                "{:>12.6} {:#04x} {}{}",
+                record.timestamp.as_secs_f64(),
+                record.address,
+                operation,
+                failed
+            );
+        }
+    }
+}
+
+/// Replays the transactions against simulated chips, reporting the reads that return other
+///  values than recorded, and prints the final state of the chips.
+///
+/// The chips behave differently when they are accessed before their oscillator settled, so a
+///  transaction after one that woke a chip up is replayed no sooner after it than recorded, up to
+///  the time the oscillator needs to settle. Other than that, the transactions are replayed as
+///  fast as possible, or with their recorded timing if `realtime` is set.
+///
+/// # Returns
+///
+/// The number of transactions that failed or read other values than recorded.
+fn replay(records: &[Record], chips: &[u8], realtime: bool) -> usize {
+    let bus = Bus::new();
+
+    chips
+        .iter()
+        .for_each(|&address| bus.attach(Pca9685::new(address)));
+
+    let started_at = Instant::now();
+    let mut decoder = Decoder::new(INTERNAL_OSC_CLOCK);
+    let mut wakes: Vec<(Duration, Instant)> = Vec::new();
+    let mut differences = 0_usize;
+    let mut start = 0_usize;
+
+    while start < records.len() {
+        // Collect the operations of the transaction.
+        let end = records[start + 1..]
+            .iter()
+            .position(|record| !record.continued)
+            .map_or(records.len(), |position| start + 1 + position);
+
+        let transaction = &records[start..end];
+        let first = &transaction[0];
+
+        if realtime {
+            thread::sleep(first.timestamp.saturating_sub(started_at.elapsed()));
+        } else {
+            // Wait for the oscillators as long as they got in the recording.
+            for &(recorded_at, replayed_at) in wakes.iter() {
+                let wait = first
+                    .timestamp
+                    .saturating_sub(recorded_at)
+                    .min(OSCILLATOR_SETTLE_TIME);
+
+                thread::sleep(wait.saturating_sub(replayed_at.elapsed()));
+            }
+
+            wakes.retain(|&(recorded_at, _)| {
+                first.timestamp.saturating_sub(recorded_at) < OSCILLATOR_SETTLE_TIME
+            });
+        }
+
+        let mut buffers: Vec<Vec<u8>> = transaction
+            .iter()
+            .map(|record| vec![0_u8; record.data.len()])
+            .collect();
+
+        let mut operations: Vec<Operation> = transaction
+            .iter()
+            .zip(buffers.iter_mut())
+            .map(|(record, buffer)| match record.kind {
+                RecordKind::Write => Operation::Write(&record.data),
+                RecordKind::Read => Operation::Read(buffer),
+            })
+            .collect();
+
+        let result = bus.clone().transaction(first.address, &mut operations);
+        drop(operations);
+
+        let woke = transaction
+            .iter()
+            .flat_map(|record| decoder.decode(record))
+            .any(|operation| operation.wakes());
+
+        if woke && !realtime {
+            wakes.push((first.timestamp, Instant::now()));
+        }
+
+        match result {
+            Err(error) if !first.failed => {
+                println!(
+                    "{:>12.6} {:#04x} transaction failed: {}",
+                    first.timestamp.as_secs_f64(),
+                    first.address,
+                    error
+                );
+                differences += 1;
+            }
+            _ => {}
+        }
+
+        for (record, buffer) in transaction.iter().zip(buffers.iter()) {
+            if record.kind == RecordKind::Read && !record.failed && &record.data != buffer {
+                println!(
+                    "{:>12.6} {:#04x} read {:02x?}, recorded {:02x?}",
+                    record.timestamp.as_secs_f64(),
+                    record.address,
+                    buffer,
+                    record.data
+                );
+                differences += 1;
+            }
+        }
+
+        start = end;
+    }
+
+    for &address in chips {
+        let Some(chip) = bus.chip(address) else {
+            continue;
+        };
+
+        println!(
+            "PCA9685 {:#04x}: MODE1 {:#04x}, MODE2 {:#04x}, PRE_SCALE {:#04x}",
+            address,
+            chip.mode1(),
+            chip.mode2(),
+            chip.prescale()
+        );
+
+        for channel in 0..16_u8 {
+            let state = chip.channel(channel);
+
+            println!(
+                "  LED{:<2} on {:>4} off {:>4} full on {:<5} full off {:<5} duty cycle {:.4}",
+                channel,
+                state.on,
+                state.off,
+                state.full_on,
+                state.full_off,
+                state.duty_cycle()
+            );
+        }
+    }
+
+    differences
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("usage: {}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let records = File::open(&options.path)
+        .map_err(pca9685::recording::Error::from)
+        .and_then(|file| Reader::new(BufReader::new(file)))
+        .and_then(|reader| reader.collect::<Result<Vec<_>, _>>());
+
+    let records = match records {
+        Ok(records) => records,
+        Err(error) => {
+            eprintln!("{}: {}", options.path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    decode(&records, options.osc_clock);
+
+    if !options.replay {
+        return ExitCode::SUCCESS;
+    }
+
+    // Without explicit chips, simulate every address that was read from, since the LED All Call
+    //  and sub-addresses can only be written to.
+    let chips = if options.chips.is_empty() {
+        records
+            .iter()
+            .filter(|record| record.kind == RecordKind::Read && !record.failed)
+            .map(|record| record.address)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    } else {
+        options.chips
+    };
+
+    match replay(&records, &chips, options.realtime) {
+        0 => ExitCode::SUCCESS,
+        differences => {
+            eprintln!("{} transactions differ from the recording", differences);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use pca9685::{
+        device::Device,
+        recording::{Recorder, SharedWriter},
+        Driver,
+    };
+
+    use super::*;
+
+    const ADDRESS: u8 = 0x40;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Records a driver that restarts a chip, and reads it back after a pause of 200 ms.
+    async fn record() -> Vec<Record> {
+        let recorder = Recorder::new(Bus::with_chip(ADDRESS));
+        let writer = SharedWriter::new();
+        recorder.start(writer.clone()).unwrap();
+
+        let mut driver = Driver::builder(Device::new(recorder.clone(), ADDRESS))
+            .build()
+            .await
+            .unwrap();
+
+        driver.wake().await.unwrap();
+        driver.write_channel(0, 0, 307).await.unwrap();
+        driver.sleep().await.unwrap();
+        driver.restart().await.unwrap();
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(driver.check().await.unwrap(), None);
+
+        recorder.stop().unwrap();
+
+        let bytes = writer.bytes();
+
+        Reader::new(Cursor::new(bytes))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&["recording.bin"]).unwrap();
+
+        assert_eq!(
+            options,
+            Options {
+                path: "recording.bin".to_string(),
+                osc_clock: INTERNAL_OSC_CLOCK,
+                replay: false,
+                realtime: false,
+                chips: Vec::new(),
+            }
+        );
+
+        let options = parse(&[
+            "--osc-clock",
+            "26600000",
+            "recording.bin",
+            "--replay",
+            "--realtime",
+            "--chip",
+            "0x40",
+            "--chip",
+            "65",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            options,
+            Options {
+                path: "recording.bin".to_string(),
+                osc_clock: 26_600_000,
+                replay: true,
+                realtime: true,
+                chips: vec![0x40, 0x41],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&[]).unwrap_err(), "a recording must be provided");
+        assert_eq!(
+            parse(&["recording.bin", "--osc-clock"]).unwrap_err(),
+            "--osc-clock requires a frequency"
+        );
+        assert_eq!(
+            parse(&["recording.bin", "--osc-clock", "fast"]).unwrap_err(),
+            "invalid oscillator clock fast"
+        );
+        assert_eq!(
+            parse(&["recording.bin", "--chip", "0x4g"]).unwrap_err(),
+            "invalid address 0x4g"
+        );
+        assert_eq!(
+            parse(&["recording.bin", "other.bin"]).unwrap_err(),
+            "unexpected argument other.bin"
+        );
+        assert_eq!(
+            parse(&["recording.bin", "--fast"]).unwrap_err(),
+            "unexpected argument --fast"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let records = record().await;
+
+        // Only the oscillator is waited for, so the restart still takes effect.
+        let started_at = Instant::now();
+        assert_eq!(replay(&records, &[ADDRESS], false), 0);
+        assert!(started_at.elapsed() < Duration::from_millis(100));
+
+        let started_at = Instant::now();
+        assert_eq!(replay(&records, &[ADDRESS], true), 0);
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::{Arc, Mutex};
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation};
+
+/// Represents an I2C bus shared by several PCA9685 devices.
+///
+/// The bus can be cloned, all the clones use the same underlying bus, so every `Device` on the
+///  bus (and the LED All Call and sub-address writes of a `DriverSet`) can get its own clone.
+///  Every transaction holds the bus for its whole duration, so the transactions of different
+///  devices never interleave.
+pub struct SharedBus<I2C> {
+    i2c: Arc<Mutex<I2C>>,
+}
+
+impl<I2C> SharedBus<I2C> {
+    /// Creates a new `SharedBus` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `i2c` - The I2C bus to share.
+    ///
+    /// # Returns
+    ///
+    /// A new `SharedBus` instance.
+    pub fn new(i2c: I2C) -> Self {
+        Self {
+            i2c: Arc::new(Mutex::new(i2c)),
+        }
+    }
+}
+
+impl<I2C> Clone for SharedBus<I2C> {
+    fn clone(&self) -> Self {
+        Self {
+            i2c: self.i2c.clone(),
+        }
+    }
+}
+
+impl<I2C: I2c> ErrorType for SharedBus<I2C> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: I2c> I2c for SharedBus<I2C> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        // A panic while holding the bus does not leave it in an invalid state, so keep using it.
+        let mut i2c = self
+            .i2c
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        i2c.transaction(address, operations)
+    }
+}
@@ -1,35 +1,335 @@
-use rppal::i2c::I2c;
+use std::time::Duration;
+
+use embedded_hal::i2c::{ErrorKind, I2c};
 use thiserror::Error;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, warn};
+
+/// The I2C general call address, used to address all devices on the bus at once.
+pub(crate) const GENERAL_CALL_ADDRESS: u8 = 0x00_u8;
+
+/// The software reset command, sent to the general call address (as described in section "7.6").
+pub(crate) const SWRST_COMMAND: u8 = 0x06_u8;
 
 /// Represents the possible errors that can occur during I2C communication with the PCA9685 device.
 #[derive(Debug, Error)]
 pub enum Error {
     /// Represents an I2C error.
     #[error("I2C Error: {0}")]
-    I2CError(#[from] rppal::i2c::Error),
+    I2CError(ErrorKind),
+    /// Represents an I2C error that persisted until no retry could start before the deadline of
+    ///  the transaction.
+    #[error("Deadline Error: {0}")]
+    DeadlineError(ErrorKind),
+}
+
+impl Error {
+    /// Converts an error of the underlying I2C bus into an `Error`.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error returned by the I2C bus.
+    ///
+    /// # Returns
+    ///
+    /// The `Error` describing the kind of bus error that occurred.
+    pub(crate) fn from_bus<E: embedded_hal::i2c::Error>(error: E) -> Self {
+        Self::I2CError(error.kind())
+    }
+
+    /// Gets the kind of bus error that occurred.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::I2CError(kind) | Error::DeadlineError(kind) => kind,
+        }
+    }
+}
+
+/// Represents the policy for retrying I2C transactions that failed, such as because of a NAK on
+///  a noisy bus.
+///
+/// A failed transaction is retried until it succeeded, it was attempted `attempts` times, or the
+///  next attempt would start after the deadline. The delay before the first retry is `backoff`,
+///  and it doubles for every next retry.
+///
+/// The deadline bounds when the attempts start, not how long they take: a retry is only started
+///  if its backoff ends before the deadline, so a transaction that fails takes at most the
+///  deadline plus the duration of its last attempt, which depends on the timeout of the bus.
+///
+/// The backoff is awaited, so the runtime keeps running other tasks while a transaction waits to
+///  be retried, see `Device::transaction`. Only the tasks waiting for the same device, such as
+///  for the mutex of its `Driver`, wait for the retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+    deadline: Duration,
+}
+
+impl RetryPolicy {
+    /// The default number of attempts of a transaction.
+    pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+    /// The default delay before the first retry.
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_micros(500);
+
+    /// The default time after the start of the first attempt after which no more retries are
+    ///  started.
+    pub const DEFAULT_DEADLINE: Duration = Duration::from_millis(10);
+
+    /// Creates a new `RetryPolicy` instance with the default values.
+    ///
+    /// # Returns
+    ///
+    /// A new `RetryPolicy` instance.
+    pub fn new() -> Self {
+        Self {
+            attempts: Self::DEFAULT_ATTEMPTS,
+            backoff: Self::DEFAULT_BACKOFF,
+            deadline: Self::DEFAULT_DEADLINE,
+        }
+    }
+
+    /// Creates a new `RetryPolicy` instance that never retries.
+    ///
+    /// # Returns
+    ///
+    /// A new `RetryPolicy` instance.
+    pub fn none() -> Self {
+        Self::new().with_attempts(1)
+    }
+
+    /// Sets the maximum number of attempts of a transaction, including the first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempts` - The maximum number of attempts, at least 1.
+    ///
+    /// # Returns
+    ///
+    /// The modified `RetryPolicy` instance.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles for every next retry.
+    ///
+    /// # Arguments
+    ///
+    /// * `backoff` - The delay before the first retry.
+    ///
+    /// # Returns
+    ///
+    /// The modified `RetryPolicy` instance.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the time after the start of the first attempt of a transaction after which no more
+    ///  retries are started. The attempt that runs at the deadline is not interrupted.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The deadline relative to the first attempt.
+    ///
+    /// # Returns
+    ///
+    /// The modified `RetryPolicy` instance.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Gets the maximum number of attempts of a transaction.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Gets the delay before the first retry.
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    /// Gets the deadline of a transaction relative to its first attempt.
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Represents the counts of the I2C transactions of a `Device` and their errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ErrorCounters {
+    /// The number of transactions, not counting the retries.
+    pub transactions: u64,
+    /// The number of failed attempts, including the ones that were retried successfully.
+    pub errors: u64,
+    /// The number of retries.
+    pub retries: u64,
+    /// The number of transactions that failed after all their attempts.
+    pub failures: u64,
 }
 
 /// Represents a PCA9685 device.
-pub struct Device {
-    i2c: I2c,
-    address: u16,
+///
+/// The device is generic over the I2C bus it is attached to, any implementation of the
+/// `embedded_hal::i2c::I2c` trait can be used (such as `rppal::i2c::I2c` with the `rppal` feature).
+pub struct Device<I2C> {
+    i2c: I2C,
+    address: u8,
+    retry_policy: RetryPolicy,
+    error_counters: ErrorCounters,
 }
 
-impl Device {
-    /// Creates a new `Device` instance with the specified I2C context.
+impl<I2C> Device<I2C> {
+    /// Creates a new `Device` instance with the specified I2C bus and the default retry policy.
     ///
     /// # Arguments
     ///
-    /// * `ctx` - The I2C context to use for communication with the device.
+    /// * `i2c` - The I2C bus to use for communication with the device.
     /// * `address` - The I2C address of the device.
     ///
     /// # Returns
     ///
     /// Returns a new `Device` instance.
-    #[allow(unused)]
-    pub fn new(i2c: I2c, address: u16) -> Self {
-        Self { i2c, address }
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Self {
+            i2c,
+            address,
+            retry_policy: RetryPolicy::new(),
+            error_counters: ErrorCounters::default(),
+        }
+    }
+
+    /// Sets the policy for retrying failed I2C transactions.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_policy` - The retry policy.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Device` instance.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Gets the policy for retrying failed I2C transactions.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Gets the counts of the I2C transactions and their errors.
+    pub fn error_counters(&self) -> ErrorCounters {
+        self.error_counters
+    }
+
+    /// Resets the counts of the I2C transactions and their errors to zero.
+    pub fn reset_error_counters(&mut self) {
+        self.error_counters = ErrorCounters::default();
+    }
+
+    /// Gets the I2C address of the device.
+    ///
+    /// # Returns
+    ///
+    /// The I2C address of the device.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Consumes the device, returning the underlying I2C bus.
+    ///
+    /// # Returns
+    ///
+    /// The I2C bus the device was created with.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl Device<rppal::i2c::I2c> {
+    /// Creates a new `Device` instance on the primary I2C bus of the Raspberry Pi.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The I2C address of the device.
+    ///
+    /// # Returns
+    ///
+    /// Returns a new `Device` instance, or an `Error` if the I2C bus could not be opened.
+    pub fn rppal(address: u8) -> Result<Self, Error> {
+        let i2c = rppal::i2c::I2c::new().map_err(Error::from_bus)?;
+
+        Ok(Self::new(i2c, address))
+    }
+}
+
+impl<I2C: I2c> Device<I2C> {
+    /// Performs an I2C transaction, retrying it according to the retry policy.
+    ///
+    /// The backoff between the attempts is awaited with `tokio::time::sleep`, so it does not block
+    ///  the runtime. The caller keeps the device borrowed for the whole transaction though, so no
+    ///  other transaction to the device can come in between the attempts.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction` - The function that performs the transaction on the bus.
+    ///
+    /// # Returns
+    ///
+    /// Returns the result of the transaction, or an `Error` if all the attempts failed.
+    async fn transaction<T>(
+        &mut self,
+        mut transaction: impl FnMut(&mut I2C) -> Result<T, I2C::Error>,
+    ) -> Result<T, Error> {
+        let started = Instant::now();
+        let mut backoff = self.retry_policy.backoff;
+        let mut attempt = 1_u32;
+
+        self.error_counters.transactions += 1;
+
+        loop {
+            let error = match transaction(&mut self.i2c) {
+                Ok(value) => return Ok(value),
+                Err(error) => Error::from_bus(error),
+            };
+
+            self.error_counters.errors += 1;
+
+            if attempt >= self.retry_policy.attempts {
+                self.error_counters.failures += 1;
+                warn!(address = self.address, attempt, %error, "I2C transaction failed");
+                return Err(error);
+            }
+
+            if started.elapsed() + backoff > self.retry_policy.deadline {
+                self.error_counters.failures += 1;
+                warn!(
+                    address = self.address,
+                    attempt,
+                    %error,
+                    "I2C transaction missed its deadline"
+                );
+                return Err(Error::DeadlineError(error.kind()));
+            }
+
+            debug!(address = self.address, attempt, ?backoff, %error, "retrying I2C transaction");
+            sleep(backoff).await;
+
+            self.error_counters.retries += 1;
+            backoff *= 2;
+            attempt += 1;
+        }
     }
 
     /// Reads a single byte from the device at the specified address.
@@ -42,11 +342,13 @@ impl Device {
     ///
     /// Returns the read byte on success, or an `Error` if the read operation fails.
     #[allow(unused)]
-    pub(crate) fn read_byte(&mut self, address: u8) -> Result<u8, Error> {
+    pub(crate) async fn read_byte(&mut self, address: u8) -> Result<u8, Error> {
+        let device_address = self.address;
         let write_buffer = [address];
         let mut read_buffer = [0_u8];
 
-        self.i2c.write_read(&write_buffer, &mut read_buffer)?;
+        self.transaction(|i2c| i2c.write_read(device_address, &write_buffer, &mut read_buffer))
+            .await?;
 
         Ok(read_buffer[0])
     }
@@ -62,10 +364,16 @@ impl Device {
     ///
     /// Returns `Ok(())` on success, or an `Error` if the read operation fails.
     #[allow(unused)]
-    pub(crate) fn read_bytes(&mut self, address: u8, read_buffer: &mut [u8]) -> Result<(), Error> {
+    pub(crate) async fn read_bytes(
+        &mut self,
+        address: u8,
+        read_buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let device_address = self.address;
         let write_buffer = [address];
 
-        self.i2c.write_read(&write_buffer, read_buffer)?;
+        self.transaction(|i2c| i2c.write_read(device_address, &write_buffer, read_buffer))
+            .await?;
 
         Ok(())
     }
@@ -81,10 +389,12 @@ impl Device {
     ///
     /// Returns `Ok(())` on success, or an `Error` if the write operation fails.
     #[allow(unused)]
-    pub(crate) fn write_byte(&mut self, address: u8, value: u8) -> Result<(), Error> {
+    pub(crate) async fn write_byte(&mut self, address: u8, value: u8) -> Result<(), Error> {
+        let device_address = self.address;
         let buffer = &[address, value];
 
-        self.i2c.write(buffer)?;
+        self.transaction(|i2c| i2c.write(device_address, buffer))
+            .await?;
 
         Ok(())
     }
@@ -99,16 +409,11 @@ impl Device {
     /// Returns `Ok(())` on success, or an `Error` if the reset operation fails.
     pub async fn software_reset(&mut self) -> Result<(), Error> {
         // Create the buffer that will contain the reset command (as described in section "7.6").
-        let buffer = [0x06];
+        let buffer = [SWRST_COMMAND];
 
-        // Set the slave address to the general call address (as described in section "7.6").
-        self.i2c.set_slave_address(0x00)?;
-
-        // Write the reset command to the device.
-        self.i2c.write(&buffer)?;
-
-        // Set the slave address back to the device address.
-        self.i2c.set_slave_address(self.address)?;
+        // Write the reset command to the general call address (as described in section "7.6").
+        self.transaction(|i2c| i2c.write(GENERAL_CALL_ADDRESS, &buffer))
+            .await?;
 
         // Wait for the reset to complete.
         sleep(std::time::Duration::from_millis(100)).await;
@@ -128,13 +433,16 @@ impl Device {
     ///
     /// Returns `Ok(())` on success, or an `Error` if the write operation fails.
     #[allow(unused)]
-    pub(crate) fn write_bytes(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
+    pub(crate) async fn write_bytes(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
         let mut write_buffer = Vec::with_capacity(buffer.len() + 1);
 
         write_buffer.push(address);
         write_buffer.extend_from_slice(buffer);
 
-        self.i2c.write(&write_buffer)?;
+        let device_address = self.address;
+
+        self.transaction(|i2c| i2c.write(device_address, &write_buffer))
+            .await?;
 
         Ok(())
     }
@@ -150,12 +458,12 @@ impl Device {
     ///
     /// Returns `Ok(())` on success, or an `Error` if the write operation fails.
     #[allow(unused)]
-    pub(crate) fn set_bit_mask(&mut self, address: u8, mask: u8) -> Result<(), Error> {
-        let mut value = self.read_byte(address)?;
+    pub(crate) async fn set_bit_mask(&mut self, address: u8, mask: u8) -> Result<(), Error> {
+        let mut value = self.read_byte(address).await?;
 
         value |= mask;
 
-        self.write_byte(address, value)?;
+        self.write_byte(address, value).await?;
 
         Ok(())
     }
@@ -171,13 +479,170 @@ impl Device {
     ///
     /// Returns `Ok(())` on success, or an `Error` if the write operation fails.
     #[allow(unused)]
-    pub(crate) fn clear_bit_mask(&mut self, address: u8, mask: u8) -> Result<(), Error> {
-        let mut value = self.read_byte(address)?;
+    pub(crate) async fn clear_bit_mask(&mut self, address: u8, mask: u8) -> Result<(), Error> {
+        let mut value = self.read_byte(address).await?;
 
         value &= !mask;
 
-        self.write_byte(address, value)?;
+        self.write_byte(address, value).await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::{NoAcknowledgeSource, Operation};
+
+    use super::*;
+    use crate::sim::Bus;
+
+    const ADDRESS: u8 = 0x40;
+
+    /// A bus that does not acknowledge the given number of transactions before working.
+    struct FlakyBus {
+        bus: Bus,
+        failures: usize,
+    }
+
+    impl embedded_hal::i2c::ErrorType for FlakyBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for FlakyBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+            }
+
+            self.bus.transaction(address, operations)
+        }
+    }
+
+    fn build_device(failures: usize) -> Device<FlakyBus> {
+        let bus = FlakyBus {
+            bus: Bus::with_chip(ADDRESS),
+            failures,
+        };
+
+        Device::new(bus, ADDRESS)
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let mut device = build_device(2);
+
+        device.write_byte(0x06, 0x12).await.unwrap();
+        assert_eq!(device.read_byte(0x06).await.unwrap(), 0x12);
+
+        assert_eq!(
+            device.error_counters(),
+            ErrorCounters {
+                transactions: 2,
+                errors: 2,
+                retries: 2,
+                failures: 0,
+            }
+        );
+
+        device.reset_error_counters();
+        assert_eq!(device.error_counters(), ErrorCounters::default());
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts_exhausted() {
+        let mut device = build_device(2).with_retry_policy(RetryPolicy::none());
+
+        assert!(matches!(
+            device.write_byte(0x06, 0x12).await,
+            Err(Error::I2CError(ErrorKind::NoAcknowledge(_)))
+        ));
+        assert_eq!(device.error_counters().failures, 1);
+        assert_eq!(device.error_counters().retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_deadline() {
+        let retry_policy = RetryPolicy::new()
+            .with_attempts(10)
+            .with_backoff(Duration::from_millis(2))
+            .with_deadline(Duration::from_millis(5));
+
+        let mut device = build_device(10).with_retry_policy(retry_policy);
+
+        // The first retry after 2ms fits in the deadline, the next one after another 4ms does not.
+        assert!(matches!(
+            device.write_byte(0x06, 0x12).await,
+            Err(Error::DeadlineError(ErrorKind::NoAcknowledge(_)))
+        ));
+        assert_eq!(device.error_counters().retries, 1);
+    }
+
+    /// A bus that fails its first transaction, and only performs the next one after another task
+    ///  made progress.
+    struct HandshakeBus {
+        bus: Bus,
+        failed: Option<tokio::sync::oneshot::Sender<()>>,
+        progressed: std::sync::mpsc::Receiver<()>,
+    }
+
+    impl embedded_hal::i2c::ErrorType for HandshakeBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for HandshakeBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if let Some(failed) = self.failed.take() {
+                failed.send(()).unwrap();
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data));
+            }
+
+            // Only succeeds if the other task ran during the backoff, otherwise it cannot run until
+            //  this one finishes.
+            self.progressed
+                .recv_timeout(Duration::from_secs(10))
+                .map_err(|_| ErrorKind::Other)?;
+
+            self.bus.transaction(address, operations)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_does_not_block_runtime() {
+        let (failed_sender, failed_receiver) = tokio::sync::oneshot::channel();
+        let (progressed_sender, progressed_receiver) = std::sync::mpsc::channel();
+
+        let bus = HandshakeBus {
+            bus: Bus::with_chip(ADDRESS),
+            failed: Some(failed_sender),
+            progressed: progressed_receiver,
+        };
+
+        let retry_policy = RetryPolicy::new()
+            .with_attempts(2)
+            .with_deadline(Duration::from_secs(1));
+
+        let mut device = Device::new(bus, ADDRESS).with_retry_policy(retry_policy);
+
+        // The other task makes progress on the current thread once the transaction failed, while
+        //  the transaction backs off and retries.
+        let other = tokio::spawn(async move {
+            failed_receiver.await.unwrap();
+            progressed_sender.send(()).unwrap();
+        });
+
+        let retrying = tokio::spawn(async move { device.write_byte(0x06, 0x12).await });
+
+        retrying.await.unwrap().unwrap();
+        other.await.unwrap();
+    }
+}
@@ -0,0 +1,734 @@
+use std::sync::Arc;
+
+use embedded_hal::i2c::I2c;
+use tokio::sync::Mutex;
+
+use crate::{
+    address,
+    device::{Device, ErrorCounters, RetryPolicy},
+    memory::{led_on_l_addr, ALL_LED_ON_L_ADDR, LED_COUNT},
+    Channel, Driver, DriverBuilder, Error, Output,
+};
+
+/// Identifies a channel of a `DriverSet` by the number of its chip and its channel on that chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChannelAddress {
+    /// The number of the chip, in the order the chips were added to the `DriverSet`.
+    pub chip: u8,
+    /// The channel number on the chip (0-15).
+    pub channel: u8,
+}
+
+impl ChannelAddress {
+    /// Creates a new `ChannelAddress` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - The number of the chip.
+    /// * `channel` - The channel number on the chip.
+    ///
+    /// # Returns
+    ///
+    /// A new `ChannelAddress` instance.
+    pub fn new(chip: u8, channel: u8) -> Self {
+        Self { chip, channel }
+    }
+
+    /// Creates the `ChannelAddress` of the channel with the given global index, counting the 16
+    ///  channels of the first chip first, then those of the second chip, and so on.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The global index of the channel.
+    ///
+    /// # Returns
+    ///
+    /// The `ChannelAddress` of the channel.
+    pub fn from_index(index: u16) -> Self {
+        Self {
+            chip: (index / LED_COUNT as u16) as u8,
+            channel: (index % LED_COUNT as u16) as u8,
+        }
+    }
+
+    /// Gets the global index of the channel, see `from_index`.
+    pub fn index(&self) -> u16 {
+        self.chip as u16 * LED_COUNT as u16 + self.channel as u16
+    }
+}
+
+/// Builder for creating a `DriverSet` instance.
+pub struct DriverSetBuilder<I2C> {
+    bus: I2C,
+    chips: Vec<DriverBuilder<I2C>>,
+    all_call_address: Option<u8>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl<I2C: I2c + Clone> DriverSetBuilder<I2C> {
+    /// Creates a new instance of the `DriverSetBuilder` struct without any chips.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The I2C bus all the chips are attached to, such as a `bus::SharedBus`.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `DriverSetBuilder` struct.
+    pub fn new(bus: I2C) -> Self {
+        Self {
+            bus,
+            chips: Vec::new(),
+            all_call_address: None,
+            retry_policy: None,
+        }
+    }
+
+    /// Adds a chip to the `DriverSetBuilder`, which gets the next chip number.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - The builder of the driver of the chip, with a `Device` on a clone of the bus.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverSetBuilder` instance.
+    pub fn with_chip(mut self, chip: DriverBuilder<I2C>) -> Self {
+        self.chips.push(chip);
+        self
+    }
+
+    /// Makes all the chips respond to the given LED All Call address for the `DriverSetBuilder`,
+    ///  so they can be written to at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The 7-bit LED All Call address, such as `address::DEFAULT_ALL_CALL_ADDRESS`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverSetBuilder` instance.
+    pub fn with_all_call_address(mut self, address: u8) -> Self {
+        self.all_call_address = Some(address);
+        self
+    }
+
+    /// Sets the policy for retrying failed writes to the call addresses for the
+    ///  `DriverSetBuilder`. Without it, the retry policy of the device of the first chip is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_policy` - The retry policy.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverSetBuilder` instance.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Builds the `DriverSet` instance.
+    ///
+    /// This checks that no address is used for more than one purpose on the bus, and then builds
+    ///  the driver of every chip, see `DriverBuilder::build`.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the `DriverSet` instance if the build operation is
+    /// successful, otherwise returns an `Error`.
+    pub async fn build(self) -> Result<DriverSet<I2C>, Error> {
+        if self.chips.len() > u8::MAX as usize {
+            return Err(Error::ChipError(u8::MAX));
+        }
+
+        // The chip addresses must be unique, and may not be used as a call address.
+        let mut chip_addresses = Vec::with_capacity(self.chips.len());
+
+        for chip in self.chips.iter() {
+            let address = address::validate(chip.device.address())?;
+
+            if chip_addresses.contains(&address) {
+                return Err(Error::AddressError(address));
+            }
+
+            chip_addresses.push(address);
+        }
+
+        let mut call_addresses: Vec<u8> = self
+            .chips
+            .iter()
+            .flat_map(|chip| chip.sub_addresses.iter().flatten().copied())
+            .chain(self.all_call_address)
+            .collect();
+
+        call_addresses.sort_unstable();
+        call_addresses.dedup();
+
+        if let Some(&address) = call_addresses
+            .iter()
+            .find(|address| chip_addresses.contains(address))
+        {
+            return Err(Error::AddressError(address));
+        }
+
+        // The LED All Call address may not be used as a sub-address, or it would only reach a
+        //  group of the chips.
+        if let Some(all_call_address) = self.all_call_address {
+            let used_as_sub_address = self
+                .chips
+                .iter()
+                .any(|chip| chip.sub_addresses.contains(&Some(all_call_address)));
+
+            if used_as_sub_address {
+                return Err(Error::AddressError(all_call_address));
+            }
+        }
+
+        // Create a device for every call address, which retries like the devices of the chips.
+        let retry_policy = self.retry_policy.unwrap_or_else(|| {
+            self.chips
+                .first()
+                .map(|chip| chip.device.retry_policy())
+                .unwrap_or_default()
+        });
+
+        let call_devices = call_addresses
+            .iter()
+            .map(|&address| {
+                Mutex::new(Device::new(self.bus.clone(), address).with_retry_policy(retry_policy))
+            })
+            .collect();
+
+        // Build the driver of every chip.
+        let mut drivers = Vec::with_capacity(self.chips.len());
+
+        for chip in self.chips {
+            let chip = match self.all_call_address {
+                Some(address) => chip.with_all_call_address(address),
+                None => chip,
+            };
+
+            drivers.push(Arc::new(Mutex::new(chip.build().await?)));
+        }
+
+        Ok(DriverSet {
+            drivers,
+            chip_addresses,
+            call_addresses,
+            call_devices,
+            all_call_address: self.all_call_address,
+        })
+    }
+}
+
+/// Represents a set of PCA9685 drivers on the same I2C bus, addressing their channels with
+///  `ChannelAddress`es.
+///
+/// Next to the drivers of the individual chips, the set can write to all the chips at once
+///  through the LED All Call address, or to a group of chips through a sub-address. Such a write
+///  is a single I2C transaction, so the outputs of all the chips change in the same PWM period.
+pub struct DriverSet<I2C> {
+    drivers: Vec<Arc<Mutex<Driver<I2C>>>>,
+    chip_addresses: Vec<u8>,
+    call_addresses: Vec<u8>,
+    call_devices: Vec<Mutex<Device<I2C>>>,
+    all_call_address: Option<u8>,
+}
+
+impl<I2C: I2c + Clone> DriverSet<I2C> {
+    /// Creates a new instance of the `DriverSetBuilder` struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The I2C bus all the chips are attached to.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `DriverSetBuilder` struct.
+    pub fn builder(bus: I2C) -> DriverSetBuilder<I2C> {
+        DriverSetBuilder::new(bus)
+    }
+
+    /// Gets the number of chips in the set.
+    pub fn len(&self) -> usize {
+        self.drivers.len()
+    }
+
+    /// Gets whether the set has no chips.
+    pub fn is_empty(&self) -> bool {
+        self.drivers.is_empty()
+    }
+
+    /// Gets the LED All Call address all the chips respond to, if any.
+    pub fn all_call_address(&self) -> Option<u8> {
+        self.all_call_address
+    }
+
+    /// Gets the counts of the I2C transactions sent to the given call address and their errors,
+    ///  which are not counted by the drivers of the chips that respond to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `call_address` - The LED All Call address or a sub-address of the set.
+    ///
+    /// # Returns
+    ///
+    /// Returns the counts, or an `Error` if the call address is not used by the set.
+    pub async fn call_error_counters(&self, call_address: u8) -> Result<ErrorCounters, Error> {
+        Ok(self
+            .call_device(call_address)?
+            .lock()
+            .await
+            .error_counters())
+    }
+
+    /// Gets the I2C address of the specified chip.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - The number of the chip.
+    ///
+    /// # Returns
+    ///
+    /// Returns the I2C address, or an `Error` if the chip number is out of range.
+    pub fn address(&self, chip: u8) -> Result<u8, Error> {
+        self.chip_addresses
+            .get(chip as usize)
+            .copied()
+            .ok_or(Error::ChipError(chip))
+    }
+
+    /// Gets the driver of the specified chip.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - The number of the chip.
+    ///
+    /// # Returns
+    ///
+    /// Returns the driver, or an `Error` if the chip number is out of range.
+    pub fn driver(&self, chip: u8) -> Result<&Arc<Mutex<Driver<I2C>>>, Error> {
+        self.drivers
+            .get(chip as usize)
+            .ok_or(Error::ChipError(chip))
+    }
+
+    /// Gets the specified channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the channel.
+    ///
+    /// # Returns
+    ///
+    /// Returns the channel, or an `Error` if the chip or channel number is out of range.
+    pub fn channel(&self, address: ChannelAddress) -> Result<Channel<I2C>, Error> {
+        if address.channel >= LED_COUNT {
+            return Err(Error::ChannelError(address.channel));
+        }
+
+        Ok(Channel::new(
+            self.driver(address.chip)?.clone(),
+            address.channel,
+        ))
+    }
+
+    /// Writes the duty cycles to multiple channels of the set.
+    ///
+    /// The channels of every chip are written at once, see `Driver::write_channels_duty_cycle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - The address and duty cycle of every channel to write, in any order.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channels_duty_cycle(
+        &self,
+        channels: &[(ChannelAddress, f64)],
+    ) -> Result<(), Error> {
+        // Check all the chips before writing any of them.
+        for (address, _) in channels {
+            self.driver(address.chip)?;
+        }
+
+        for (chip, driver) in self.drivers.iter().enumerate() {
+            let chip_channels: Vec<(u8, f64)> = channels
+                .iter()
+                .filter(|(address, _)| address.chip as usize == chip)
+                .map(|&(address, duty_cycle)| (address.channel, duty_cycle))
+                .collect();
+
+            if !chip_channels.is_empty() {
+                driver
+                    .lock()
+                    .await
+                    .write_channels_duty_cycle(&chip_channels)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes an output to the specified channel of all the chips that respond to the given call
+    ///  address, in a single I2C transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `call_address` - The LED All Call address or a sub-address of the set.
+    /// * `channel` - The channel number to write the output to.
+    /// * `output` - The output to write.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_call_channel_output(
+        &self,
+        call_address: u8,
+        channel: u8,
+        output: Output,
+    ) -> Result<(), Error> {
+        if channel >= LED_COUNT {
+            return Err(Error::ChannelError(channel));
+        }
+
+        self.write_call(call_address, Some(channel), output).await
+    }
+
+    /// Writes an output to all the channels of all the chips that respond to the given call
+    ///  address, in a single I2C transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `call_address` - The LED All Call address or a sub-address of the set.
+    /// * `output` - The output to write.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_call_all_channels_output(
+        &self,
+        call_address: u8,
+        output: Output,
+    ) -> Result<(), Error> {
+        self.write_call(call_address, None, output).await
+    }
+
+    /// Turns all the channels of all the chips fully off.
+    ///
+    /// With a LED All Call address this takes a single I2C transaction, otherwise every chip is
+    ///  written to in turn.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn all_channels_off(&self) -> Result<(), Error> {
+        if let Some(all_call_address) = self.all_call_address {
+            return self
+                .write_call_all_channels_output(all_call_address, Output::FullOff)
+                .await;
+        }
+
+        for driver in self.drivers.iter() {
+            driver.lock().await.all_channels_off().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes an output to a channel, or to all the channels if `None`, of all the chips that
+    ///  respond to the given call address.
+    ///
+    /// Once the write succeeded, the drivers of those chips remember the output, so they restore
+    ///  it rather than the output written before when they recover from a reset, see
+    ///  `Driver::check`. They are locked until the write is done, so their own writes cannot
+    ///  interleave with it.
+    async fn write_call(
+        &self,
+        call_address: u8,
+        channel: Option<u8>,
+        output: Output,
+    ) -> Result<(), Error> {
+        let call_device = self.call_device(call_address)?;
+
+        output.validate()?;
+
+        let mut drivers = Vec::with_capacity(self.drivers.len());
+
+        for driver in self.drivers.iter() {
+            let driver = driver.lock().await;
+
+            if driver.responds_to(call_address) {
+                drivers.push(driver);
+            }
+        }
+
+        let register = match channel {
+            Some(channel) => led_on_l_addr(channel),
+            None => ALL_LED_ON_L_ADDR,
+        };
+
+        call_device
+            .lock()
+            .await
+            .write_bytes(register, &output.led_bytes())
+            .await?;
+
+        for driver in drivers.iter_mut() {
+            driver.remember_output(channel, output);
+        }
+
+        Ok(())
+    }
+
+    /// Gets the device that writes to the given call address.
+    fn call_device(&self, call_address: u8) -> Result<&Mutex<Device<I2C>>, Error> {
+        self.call_addresses
+            .iter()
+            .position(|&address| address == call_address)
+            .map(|index| &self.call_devices[index])
+            .ok_or(Error::AddressError(call_address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        address::{SubAddress, DEFAULT_ALL_CALL_ADDRESS},
+        sim, PhaseStrategy,
+    };
+
+    /// Creates a simulated bus with a chip at each of the given addresses.
+    fn build_bus(addresses: &[u8]) -> sim::Bus {
+        let bus = sim::Bus::new();
+
+        for &address in addresses {
+            bus.attach(sim::Pca9685::new(address));
+        }
+
+        bus
+    }
+
+    #[test]
+    fn test_channel_address() {
+        assert_eq!(ChannelAddress::from_index(17), ChannelAddress::new(1, 1));
+        assert_eq!(ChannelAddress::new(2, 15).index(), 47);
+    }
+
+    #[tokio::test]
+    async fn test_build() {
+        let first = address::from_pins(0b00_0000).unwrap();
+        let second = address::from_pins(0b00_0001).unwrap();
+        let bus = build_bus(&[first, second]);
+
+        let set = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), first)))
+            .with_chip(
+                Driver::builder(Device::new(bus.clone(), second))
+                    .with_phase_strategy(PhaseStrategy::Staggered)
+                    .with_sub_address(SubAddress::Sub1, 0x71),
+            )
+            .with_all_call_address(DEFAULT_ALL_CALL_ADDRESS)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.address(1).unwrap(), second);
+        assert!(matches!(set.address(2), Err(Error::ChipError(2))));
+
+        let chip = bus.chip(second).unwrap();
+        assert_eq!(chip.register(SubAddress::Sub1.register()), 0x71 << 1);
+
+        // Both chips respond to the LED All Call address, only the second to its sub-address.
+        set.write_call_channel_output(DEFAULT_ALL_CALL_ADDRESS, 3, Output::FullOn)
+            .await
+            .unwrap();
+        set.write_call_channel_output(0x71, 4, Output::FullOn)
+            .await
+            .unwrap();
+
+        assert!(bus.chip(first).unwrap().channel(3).full_on);
+        assert!(bus.chip(second).unwrap().channel(3).full_on);
+        assert!(!bus.chip(first).unwrap().channel(4).full_on);
+        assert!(bus.chip(second).unwrap().channel(4).full_on);
+
+        // Addresses that are not configured are refused.
+        assert!(matches!(
+            set.write_call_channel_output(0x72, 0, Output::FullOn).await,
+            Err(Error::AddressError(0x72))
+        ));
+
+        set.all_channels_off().await.unwrap();
+
+        assert!(bus.chip(first).unwrap().channel(3).full_off);
+        assert!(bus.chip(second).unwrap().channel(4).full_off);
+    }
+
+    #[tokio::test]
+    async fn test_build_conflicting_addresses() {
+        let bus = build_bus(&[0x40, 0x41]);
+
+        let duplicate = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .build()
+            .await;
+
+        assert!(matches!(duplicate, Err(Error::AddressError(0x40))));
+
+        let call_address_of_chip = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x41)))
+            .with_all_call_address(0x41)
+            .build()
+            .await;
+
+        assert!(matches!(
+            call_address_of_chip,
+            Err(Error::AddressError(0x41))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_channels_duty_cycle() {
+        let bus = build_bus(&[0x40, 0x41]);
+
+        let set = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x41)))
+            .build()
+            .await
+            .unwrap();
+
+        set.write_channels_duty_cycle(&[
+            (ChannelAddress::from_index(0), 0.25),
+            (ChannelAddress::from_index(17), 0.5),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(bus.chip(0x40).unwrap().channel(0).off, 1024);
+        assert_eq!(bus.chip(0x41).unwrap().channel(1).off, 2048);
+
+        // A channel of a chip that is not in the set is refused before anything is written.
+        assert!(matches!(
+            set.write_channels_duty_cycle(&[
+                (ChannelAddress::new(0, 0), 0.5),
+                (ChannelAddress::new(2, 0), 0.5)
+            ])
+            .await,
+            Err(Error::ChipError(2))
+        ));
+        assert_eq!(bus.chip(0x40).unwrap().channel(0).off, 1024);
+
+        let mut channel = set.channel(ChannelAddress::new(1, 2)).unwrap();
+        channel.write_duty_cycle(0.5).await.unwrap();
+
+        assert_eq!(bus.chip(0x41).unwrap().channel(2).off, 2048);
+    }
+
+    #[tokio::test]
+    async fn test_call_outputs_survive_reset() {
+        let bus = build_bus(&[0x40, 0x41]);
+
+        let set = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .with_chip(
+                Driver::builder(Device::new(bus.clone(), 0x41))
+                    .with_sub_address(SubAddress::Sub1, 0x71),
+            )
+            .with_all_call_address(DEFAULT_ALL_CALL_ADDRESS)
+            .build()
+            .await
+            .unwrap();
+
+        for chip in 0..2 {
+            let mut driver = set.driver(chip).unwrap().lock().await;
+            driver.wake().await.unwrap();
+            driver
+                .write_channels(&[(0, 0, 300), (1, 0, 300)])
+                .await
+                .unwrap();
+        }
+
+        // The drivers restore the outputs written through the call addresses after a reset,
+        //  rather than the outputs they wrote themselves.
+        set.all_channels_off().await.unwrap();
+        set.write_call_channel_output(0x71, 1, Output::FullOn)
+            .await
+            .unwrap();
+
+        for address in [0x40, 0x41] {
+            bus.chip_mut(address, |chip| chip.reset()).unwrap();
+        }
+
+        for chip in 0..2 {
+            let mut driver = set.driver(chip).unwrap().lock().await;
+            assert!(driver.check().await.unwrap().is_some());
+        }
+
+        let first = bus.chip(0x40).unwrap();
+        assert!(first.channel(0).full_off);
+        assert!(first.channel(1).full_off);
+
+        let second = bus.chip(0x41).unwrap();
+        assert!(second.channel(0).full_off);
+        assert!(second.channel(1).full_on);
+    }
+
+    #[tokio::test]
+    async fn test_failed_call_write() {
+        let bus = build_bus(&[0x40, 0x41]);
+
+        let set = DriverSet::builder(bus.clone())
+            .with_chip(Driver::builder(Device::new(bus.clone(), 0x40)))
+            .with_chip(
+                Driver::builder(Device::new(bus.clone(), 0x41))
+                    .with_sub_address(SubAddress::Sub1, 0x71),
+            )
+            .with_retry_policy(RetryPolicy::new().with_attempts(2))
+            .build()
+            .await
+            .unwrap();
+
+        set.driver(1).unwrap().lock().await.wake().await.unwrap();
+
+        set.write_call_channel_output(0x71, 1, Output::FullOn)
+            .await
+            .unwrap();
+
+        // After a reset the chip does not respond to its sub-address anymore, so the write fails
+        //  after being retried.
+        bus.chip_mut(0x41, |chip| chip.reset()).unwrap();
+
+        assert!(set
+            .write_call_channel_output(0x71, 1, Output::FullOff)
+            .await
+            .is_err());
+
+        assert_eq!(
+            set.call_error_counters(0x71).await.unwrap(),
+            ErrorCounters {
+                transactions: 2,
+                errors: 2,
+                retries: 1,
+                failures: 1,
+            }
+        );
+        assert!(matches!(
+            set.call_error_counters(0x72).await,
+            Err(Error::AddressError(0x72))
+        ));
+
+        // The driver restores the output of the last write that succeeded.
+        let mut driver = set.driver(1).unwrap().lock().await;
+        assert!(driver.check().await.unwrap().is_some());
+        assert!(bus.chip(0x41).unwrap().channel(1).full_on);
+    }
+}
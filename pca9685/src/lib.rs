@@ -1,19 +1,45 @@
 use std::{sync::Arc, time::Duration};
 
+use address::SubAddress;
 use device::Device;
-use math::{compute_on_off_time, compute_prescale};
+use embedded_hal::{
+    digital::OutputPin,
+    i2c::{ErrorKind, I2c},
+};
+use math::{
+    compute_on_off_time, compute_osc_clock, compute_prescale, compute_update_rate,
+    COUNTS_PER_PERIOD,
+};
 use memory::{
-    led_on_l_addr, MODE1_ADDR, MODE1_ALLCALL_BIT, MODE1_RESTART_BIT, MODE1_SLEEP_BIT, MODE2_ADDR, MODE2_IVRT_BIT, PRE_SCALE_ADDR
+    led_on_l_addr, ALLCALLADR_ADDR, ALL_LED_ON_L_ADDR, LED_BASE_ADDR, LED_COUNT, LED_COUNT_H_MASK,
+    LED_FULL_BIT, LED_LAST_ADDR, MODE1_ADDR, MODE1_ALLCALL_BIT, MODE1_EXTCLK_BIT,
+    MODE1_RESTART_BIT, MODE1_SLEEP_BIT, MODE1_SUB1_BIT, MODE1_SUB2_BIT, MODE1_SUB3_BIT, MODE2_ADDR,
+    PRE_SCALE_ADDR, PRE_SCALE_DEFAULT,
 };
-use rppal::gpio::{Level, OutputPin};
+use mode2::Mode2Config;
+use recovery::{RecoveryEvent, RecoveryReason};
+use register_dump::RegisterDump;
 use thiserror::Error;
-use tokio::{sync::Mutex, time::sleep};
+use tokio::{
+    sync::{broadcast, Mutex},
+    time::sleep,
+};
+use tracing::{debug, instrument, trace};
 
 use crate::memory::MODE1_AI_BIT;
 
+pub mod address;
+pub mod bus;
 pub mod device;
-pub(crate) mod math;
+pub mod driver_set;
+pub mod math;
+#[allow(unused)]
 pub(crate) mod memory;
+pub mod mode2;
+pub mod recording;
+pub mod recovery;
+pub mod register_dump;
+pub mod sim;
 
 /// Represents the possible errors that can occur in the PCA9685 driver.
 #[derive(Debug, Error)]
@@ -27,36 +53,160 @@ pub enum Error {
     /// Restart error: an error occurred during the restart operation of the PCA9685 device.
     #[error("Restart error")]
     RestartError,
+    /// Output enable error: an error occurred while driving the Output Enable pin.
+    #[error("Output enable error: {0}")]
+    OutputEnableError(embedded_hal::digital::ErrorKind),
+    /// Channel error: a channel number is out of range, or was given more than once.
+    #[error("Channel error: invalid or duplicate channel {0}")]
+    ChannelError(u8),
+    /// MODE2 error: the MODE2 register did not read back the configuration that was written.
+    #[error("MODE2 error: wrote {expected:#04x}, read back {actual:#04x}")]
+    Mode2Error { expected: u8, actual: u8 },
+    /// Missing output enable error: the outputs cannot be disabled without an Output Enable pin.
+    #[error("Missing output enable error: the driver has no Output Enable pin")]
+    MissingOutputEnableError,
+    /// Address error: an I2C address is invalid, or is used for more than one purpose on the bus.
+    #[error("Address error: invalid or conflicting address {0:#04x}")]
+    AddressError(u8),
+    /// Chip error: a chip number is out of range of a `DriverSet`.
+    #[error("Chip error: invalid chip {0}")]
+    ChipError(u8),
+    /// External clock error: the device did not switch to the external clock.
+    #[error("External clock error: the EXTCLK bit could not be set")]
+    ExternalClockError,
+    /// Verify error: a register did not read back the value that was written, such as after the
+    ///  device silently reset.
+    #[error(
+        "Verify error: register {register:#04x} reads {actual:#04x}, expected {expected:#04x}"
+    )]
+    VerifyError {
+        register: u8,
+        expected: u8,
+        actual: u8,
+    },
+    /// Count error: an on or off count of a channel is out of range of 0 to 4095.
+    #[error("Count error: count {0} out of bounds of 0 to 4095")]
+    CountError(u16),
+}
+
+/// The number of recovery events a subscriber can lag behind before it misses events.
+const RECOVERY_EVENT_CAPACITY: usize = 16;
+
+/// The frequency of the internal oscillator of the PCA9685 in Hz (as described in section "7.3.5").
+pub const INTERNAL_OSC_CLOCK: u32 = 25_000_000_u32;
+
+/// The maximum frequency of an external clock on the EXTCLK pin in Hz (as described in section
+///  "7.3.1").
+pub const MAX_EXTERNAL_CLOCK: u32 = 50_000_000_u32;
+
+/// The number of PWM channels of the PCA9685 (LED0 to LED15).
+pub const CHANNEL_COUNT: u8 = LED_COUNT;
+
+/// The strategy for choosing the phase offset of every channel, which is the count at which its
+///  pulse starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum PhaseStrategy {
+    /// Starts the pulses of all the channels at count 0.
+    #[default]
+    Aligned,
+    /// Spreads the start of the pulses evenly over the period, so the loads attached to the
+    ///  channels do not all draw current at the same instant.
+    Staggered,
+}
+
+impl PhaseStrategy {
+    /// Computes the phase offset of the given channel.
+    fn phase_offset(&self, channel: u8) -> u16 {
+        match self {
+            PhaseStrategy::Aligned => 0_u16,
+            PhaseStrategy::Staggered => channel as u16 * (4096_u16 / LED_COUNT as u16),
+        }
+    }
+}
+
+/// An Output Enable pin, with the type of the pin and its error erased, so `Driver` does not need
+///  a type parameter for it.
+trait OutputEnable: Send {
+    /// Drives the (active low) pin to enable or disable the outputs.
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error>;
+}
+
+impl<OE: OutputPin + Send> OutputEnable for OE {
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        let result = if enabled {
+            self.set_low()
+        } else {
+            self.set_high()
+        };
+
+        result.map_err(|error| Error::OutputEnableError(embedded_hal::digital::Error::kind(&error)))
+    }
 }
 
 /// Builder for creating a `Driver` instance with custom configuration.
-pub struct DriverBuilder {
-    device: Device,
-    oe: OutputPin,
+pub struct DriverBuilder<I2C> {
+    device: Device<I2C>,
+    oe: Option<Box<dyn OutputEnable>>,
     osc_clock: u32,
+    external_clock: bool,
     update_rate: u16,
+    phase_strategy: PhaseStrategy,
+    mode2: Mode2Config,
+    all_call_address: Option<u8>,
+    sub_addresses: [Option<u8>; 3],
+    verify_writes: bool,
 }
 
-impl DriverBuilder {
+impl<I2C: I2c> DriverBuilder<I2C> {
     /// Creates a new instance of the `DriverBuilder` struct with default values for the oscillator clock and update rate.
     ///
     /// # Arguments
     ///
     /// * `device` - The `Device` instance used for communication with the PCA9685 device.
-    /// * `oe` - The `OutputPin` instance used for controlling the Output Enable pin of the PCA9685 device.
     ///
     /// # Returns
     ///
     /// A new instance of the `DriverBuilder` struct with default values for the oscillator clock (50,000,000) and update rate (50).
-    pub fn new(device: Device, oe: OutputPin) -> Self {
+    pub fn new(device: Device<I2C>) -> Self {
         Self {
             device,
-            oe,
+            oe: None,
             osc_clock: 50_000_000_u32,
+            external_clock: false,
             update_rate: 50_u16,
+            phase_strategy: PhaseStrategy::Aligned,
+            mode2: Mode2Config::new(),
+            all_call_address: None,
+            sub_addresses: [None; 3],
+            verify_writes: false,
         }
     }
 
+    /// Sets the pin connected to the Output Enable pin of the PCA9685 device for the
+    ///  `DriverBuilder`.
+    ///
+    /// Without it, the Output Enable pin is assumed to be tied to ground, so the outputs are
+    ///  always enabled. With it, `build` disables the outputs before configuring the device, and
+    ///  they stay disabled until `Driver::enable_outputs` is called, such as once every channel
+    ///  was written.
+    ///
+    /// # Arguments
+    ///
+    /// * `oe` - The `OutputPin` instance used for controlling the Output Enable pin of the PCA9685 device.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_oe_pin<OE: OutputPin + Send + 'static>(mut self, oe: OE) -> Self {
+        self.oe = Some(Box::new(oe));
+        self
+    }
+
     /// Sets the oscillator clock value for the `DriverBuilder`.
     ///
     /// This function allows you to customize the oscillator clock value used by the `DriverBuilder`.
@@ -73,6 +223,25 @@ impl DriverBuilder {
         self
     }
 
+    /// Makes the device use the clock on its EXTCLK pin instead of the internal oscillator for
+    ///  the `DriverBuilder`.
+    ///
+    /// Once set, the device keeps using the external clock until it is reset (as described in
+    ///  section "7.3.1").
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The frequency of the external clock in Hz, at most `MAX_EXTERNAL_CLOCK`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_external_clock(mut self, frequency: u32) -> Self {
+        self.osc_clock = frequency;
+        self.external_clock = true;
+        self
+    }
+
     /// Sets the update rate value for the `DriverBuilder`.
     ///
     /// This function allows you to customize the update rate value used by the `DriverBuilder`.
@@ -89,58 +258,251 @@ impl DriverBuilder {
         self
     }
 
+    /// Sets the strategy for choosing the phase offset of every channel for the `DriverBuilder`.
+    ///
+    /// The phase offsets can be changed per channel later on, see `Driver::set_phase_offset`.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase_strategy` - The phase strategy to set.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_phase_strategy(mut self, phase_strategy: PhaseStrategy) -> Self {
+        self.phase_strategy = phase_strategy;
+        self
+    }
+
+    /// Sets the configuration of the MODE2 register for the `DriverBuilder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode2` - The configuration of the output driver, inversion, output change timing and
+    ///   the state of the outputs while they are disabled.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_mode2(mut self, mode2: Mode2Config) -> Self {
+        self.mode2 = mode2;
+        self
+    }
+
+    /// Makes the device respond to the given LED All Call address for the `DriverBuilder`.
+    ///
+    /// Without it, the device does not respond to "LED All Calls".
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The 7-bit LED All Call address, such as `address::DEFAULT_ALL_CALL_ADDRESS`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_all_call_address(mut self, address: u8) -> Self {
+        self.all_call_address = Some(address);
+        self
+    }
+
+    /// Makes the device respond to the given sub-address for the `DriverBuilder`.
+    ///
+    /// Without it, the device does not respond to the sub-address.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub_address` - The sub-address to program.
+    /// * `address` - The 7-bit address to respond to.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_sub_address(mut self, sub_address: SubAddress, address: u8) -> Self {
+        self.sub_addresses[sub_address.index()] = Some(address);
+        self
+    }
+
+    /// Sets whether the `Driver` reads back the LED registers after writing them for the
+    ///  `DriverBuilder`, see `Driver::set_verify_writes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `verify_writes` - Whether to verify the writes.
+    ///
+    /// # Returns
+    ///
+    /// Returns the modified `DriverBuilder` instance.
+    pub fn with_verify_writes(mut self, verify_writes: bool) -> Self {
+        self.verify_writes = verify_writes;
+        self
+    }
+
     /// Builds the `Driver` instance.
     ///
     /// This function finalizes the configuration of the `DriverBuilder` and creates a new
     /// instance of the `Driver` struct. It performs the following steps:
-    /// 1. Clears the "LED All Calls" bit in the MODE1 register.
-    /// 2. Computes the prescale value based on the oscillator clock and update rate.
-    /// 3. Writes the prescale value to the PRE_SCALE register.
+    /// 0. Disables the outputs, if an Output Enable pin is configured.
+    /// 1. Programs the LED All Call address and the sub-addresses, and sets or clears their bits
+    ///    in the MODE1 register.
+    /// 2. Writes the MODE2 configuration and reads it back to verify it.
+    /// 3. Switches to the external clock, if configured.
+    /// 4. Computes the prescale value based on the oscillator clock and update rate.
+    /// 5. Writes the prescale value to the PRE_SCALE register.
     ///
     /// # Returns
     ///
     /// Returns a `Result` containing the `Driver` instance if the build operation is successful,
     /// otherwise returns an `Error`.
-    pub fn build(mut self) -> Result<Driver, Error> {
-        // Write high to oe.
-        self.oe.set_low();
-
-        // Do not listen to "LED All Calls".
-        self.device.clear_bit_mask(MODE1_ADDR, MODE1_ALLCALL_BIT)?;
+    pub async fn build(mut self) -> Result<Driver<I2C>, Error> {
+        // Pull the (active low) output enable pin high to disable the outputs, so they stay
+        //  disabled if the device cannot be configured.
+        if let Some(oe) = self.oe.as_mut() {
+            oe.set_enabled(false)?;
+        }
 
-        // Set the auto increment bit.
-        self.device.set_bit_mask(MODE1_ADDR, MODE1_AI_BIT)?;
+        // Check the addresses to listen to.
+        if let Some(address) = self.all_call_address {
+            self.validate_call_address(address)?;
+        }
 
-        // Set thge invert bit.
-        // self.device.set_bit_mask(MODE2_ADDR, MODE2_IVRT_BIT)?;
+        for address in self.sub_addresses.iter().flatten() {
+            self.validate_call_address(*address)?;
+        }
 
         // Compute the prescale value.
         let prescale: u8 = compute_prescale(self.osc_clock, self.update_rate)?;
 
-        // Write the prescale value to the device.
-        self.device.write_byte(PRE_SCALE_ADDR, prescale)?;
+        // Create the driver instance with the configuration, the output enable pin and the
+        //  phase offsets of the strategy.
+        let mut driver = Driver::new(self.device);
+        driver.outputs_enabled = self.oe.is_none();
+        driver.oe = self.oe;
+        driver.osc_clock = self.osc_clock;
+        driver.verify_writes = self.verify_writes;
+        driver.configuration = Configuration {
+            all_call_address: self.all_call_address,
+            sub_addresses: self.sub_addresses,
+            external_clock: self.external_clock,
+            mode2: self.mode2,
+            prescale,
+        };
+
+        // Write the configuration to the device.
+        driver.configure().await?;
+
+        for (channel, phase_offset) in driver.phase_offsets.iter_mut().enumerate() {
+            *phase_offset = self.phase_strategy.phase_offset(channel as u8);
+        }
 
         // Return the driver instance.
-        Ok(Driver::new(self.device))
+        Ok(driver)
+    }
+
+    /// Checks that the given address can be used as the LED All Call address or a sub-address.
+    fn validate_call_address(&self, address: u8) -> Result<u8, Error> {
+        if address::validate(address)? == self.device.address() {
+            return Err(Error::AddressError(address));
+        }
+
+        Ok(address)
+    }
+}
+
+/// The configuration a `Driver` writes to the device, which it keeps to restore the device after
+///  it reset.
+#[derive(Debug, Clone, Copy)]
+struct Configuration {
+    all_call_address: Option<u8>,
+    sub_addresses: [Option<u8>; 3],
+    external_clock: bool,
+    mode2: Mode2Config,
+    prescale: u8,
+}
+
+impl Configuration {
+    /// The bits of the MODE1 register that are part of the configuration.
+    const MODE1_MASK: u8 = MODE1_AI_BIT
+        | MODE1_EXTCLK_BIT
+        | MODE1_ALLCALL_BIT
+        | MODE1_SUB1_BIT
+        | MODE1_SUB2_BIT
+        | MODE1_SUB3_BIT;
+
+    /// Gets the configured bits of the MODE1 register, see `MODE1_MASK`.
+    fn mode1_bits(&self) -> u8 {
+        let mut bits = MODE1_AI_BIT;
+
+        if self.external_clock {
+            bits |= MODE1_EXTCLK_BIT;
+        }
+
+        if self.all_call_address.is_some() {
+            bits |= MODE1_ALLCALL_BIT;
+        }
+
+        for sub_address in SubAddress::ALL {
+            if self.sub_addresses[sub_address.index()].is_some() {
+                bits |= sub_address.mode1_bit();
+            }
+        }
+
+        bits
+    }
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            all_call_address: None,
+            sub_addresses: [None; 3],
+            external_clock: false,
+            mode2: Mode2Config::new(),
+            prescale: PRE_SCALE_DEFAULT,
+        }
     }
 }
 /// Represents a driver for the PCA9685 device.
-pub struct Driver {
-    device: Device,
+pub struct Driver<I2C> {
+    device: Device<I2C>,
+    phase_offsets: [u16; LED_COUNT as usize],
+    oe: Option<Box<dyn OutputEnable>>,
+    outputs_enabled: bool,
+    osc_clock: u32,
+    configuration: Configuration,
+    verify_writes: bool,
+    awake: bool,
+    channel_outputs: [Option<Output>; LED_COUNT as usize],
+    bus_error: Option<ErrorKind>,
+    recovery_events: broadcast::Sender<RecoveryEvent>,
 }
 
-impl Driver {
-    /// Creates a new instance of the `Driver` struct.
+impl<I2C: I2c> Driver<I2C> {
+    /// Creates a new instance of the `Driver` struct, without an Output Enable pin.
+    ///
+    /// The driver assumes the device runs on the internal oscillator with the power-up prescale
+    ///  value, use `builder` to configure the device instead.
     ///
     /// # Arguments
     ///
-    /// * `device` - The I2c context used for communication with the PCA9685 device.
+    /// * `device` - The `Device` instance used for communication with the PCA9685 device.
     ///
     /// # Returns
     ///
     /// A new instance of the `Driver` struct.
-    pub fn new(device: Device) -> Self {
-        Self { device }
+    pub fn new(device: Device<I2C>) -> Self {
+        Self {
+            device,
+            phase_offsets: [0_u16; LED_COUNT as usize],
+            oe: None,
+            outputs_enabled: true,
+            osc_clock: INTERNAL_OSC_CLOCK,
+            configuration: Configuration::default(),
+            verify_writes: false,
+            awake: false,
+            channel_outputs: [None; LED_COUNT as usize],
+            bus_error: None,
+            recovery_events: broadcast::channel(RECOVERY_EVENT_CAPACITY).0,
+        }
     }
 
     /// Creates a new instance of the `DriverBuilder` struct.
@@ -148,13 +510,136 @@ impl Driver {
     /// # Arguments
     ///
     /// * `device` - The `Device` instance used for communication with the PCA9685 device.
-    /// * `oe` - The `OutputPin` instance used for controlling the Output Enable pin of the PCA9685 device.
     ///
     /// # Returns
     ///
     /// A new instance of the `DriverBuilder` struct.
-    pub fn builder(device: Device, oe: OutputPin) -> DriverBuilder {
-        DriverBuilder::new(device, oe)
+    pub fn builder(device: Device<I2C>) -> DriverBuilder<I2C> {
+        DriverBuilder::new(device)
+    }
+
+    /// Enables the outputs by pulling the Output Enable pin low.
+    ///
+    /// Without an Output Enable pin the outputs are always enabled, so this does nothing.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the outputs are enabled, otherwise returns an `Error`.
+    pub fn enable_outputs(&mut self) -> Result<(), Error> {
+        if let Some(oe) = self.oe.as_mut() {
+            oe.set_enabled(true)?;
+        }
+
+        self.outputs_enabled = true;
+
+        Ok(())
+    }
+
+    /// Disables all the outputs at once by pulling the Output Enable pin high.
+    ///
+    /// This does not involve the I2C bus, so it is the fastest way to cut the pulses to all the
+    ///  channels. The state of the outputs while they are disabled is configured by the OUTNE
+    ///  bits, see `Mode2Config::with_output_not_enabled`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the outputs are disabled, otherwise returns an `Error`, such as when
+    ///  the driver has no Output Enable pin.
+    pub fn disable_outputs(&mut self) -> Result<(), Error> {
+        let oe = self.oe.as_mut().ok_or(Error::MissingOutputEnableError)?;
+        oe.set_enabled(false)?;
+
+        self.outputs_enabled = false;
+
+        Ok(())
+    }
+
+    /// Gets whether the outputs are enabled.
+    pub fn outputs_enabled(&self) -> bool {
+        self.outputs_enabled
+    }
+
+    /// Gets the I2C address of the PCA9685 device.
+    pub fn address(&self) -> u8 {
+        self.device.address()
+    }
+
+    /// Gets whether the driver has an Output Enable pin.
+    pub fn has_oe_pin(&self) -> bool {
+        self.oe.is_some()
+    }
+
+    /// Gets the counts of the I2C transactions with the device and their errors, see
+    ///  `Device::error_counters`.
+    pub fn error_counters(&self) -> device::ErrorCounters {
+        self.device.error_counters()
+    }
+
+    /// Gets the oscillator clock frequency the driver assumes, in Hz.
+    pub fn osc_clock(&self) -> u32 {
+        self.osc_clock
+    }
+
+    /// Gets whether the device uses the clock on its EXTCLK pin.
+    pub fn external_clock(&self) -> bool {
+        self.configuration.external_clock
+    }
+
+    /// Gets the prescale value written to the device.
+    pub fn prescale(&self) -> u8 {
+        self.configuration.prescale
+    }
+
+    /// Gets the update rate the device actually runs at, which differs slightly from the
+    ///  requested update rate because the prescale value is an integer.
+    ///
+    /// # Returns
+    ///
+    /// The update rate in Hz.
+    pub fn update_rate(&self) -> f64 {
+        compute_update_rate(self.osc_clock, self.configuration.prescale)
+    }
+
+    /// Gets the PWM period the device actually runs at.
+    ///
+    /// # Returns
+    ///
+    /// The duration of a PWM period.
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f64(1_f64 / self.update_rate())
+    }
+
+    /// Gets the duration of a single count of the PWM period, which is the resolution of the
+    ///  pulse widths.
+    ///
+    /// # Returns
+    ///
+    /// The duration of a count in microseconds.
+    pub fn tick_resolution(&self) -> f64 {
+        1e6_f64 / (self.update_rate() * COUNTS_PER_PERIOD as f64)
+    }
+
+    /// Computes the actual oscillator clock frequency of the device from the measured width of a
+    ///  pulse, such as measured with an oscilloscope.
+    ///
+    /// The result can be passed to `DriverBuilder::with_osc_clock`, so the update rate and pulse
+    ///  widths of the device match the requested ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `counts` - The width of the measured pulse in counts, such as `off - on` of a `PWM`
+    ///   output, ranging from 1 to 4096.
+    /// * `pulse_width` - The measured width of the pulse in microseconds.
+    ///
+    /// # Returns
+    ///
+    /// Returns the oscillator clock frequency in Hz, or an `Error` if the pulse width is invalid.
+    pub fn calibrate_osc_clock(&self, counts: u16, pulse_width: f64) -> Result<u32, Error> {
+        Ok(compute_osc_clock(
+            self.configuration.prescale,
+            counts,
+            pulse_width,
+        )?)
     }
 
     /// Puts the PCA9685 device into sleep mode.
@@ -164,8 +649,13 @@ impl Driver {
     /// # Returns
     ///
     /// Returns `Ok(())` if the sleep operation is successful, otherwise returns an `Error`.
-    pub fn sleep(&mut self) -> Result<(), Error> {
-        self.device.set_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)?;
+    pub async fn sleep(&mut self) -> Result<(), Error> {
+        self.device
+            .set_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)
+            .await?;
+        self.awake = false;
+
+        debug!(address = self.address(), "sleeping");
 
         Ok(())
     }
@@ -179,7 +669,12 @@ impl Driver {
     ///
     /// Returns `Ok(())` if the wake operation is successful, otherwise returns an `Error`.
     pub async fn wake(&mut self) -> Result<(), Error> {
-        self.device.clear_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)?;
+        self.device
+            .clear_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)
+            .await?;
+        self.awake = true;
+
+        debug!(address = self.address(), "woken up");
 
         sleep(Duration::from_micros(500_u64)).await;
 
@@ -200,24 +695,31 @@ impl Driver {
     pub async fn restart(&mut self) -> Result<(), Error> {
         // If the restart bit is still zero, give some time for the restart bit to be set.
         //  this is talked about in the "remark" in section "7.3.1.1".
-        if self.device.read_byte(MODE1_ADDR)? & MODE1_RESTART_BIT == 0 {
+        if self.device.read_byte(MODE1_ADDR).await? & MODE1_RESTART_BIT == 0 {
             sleep(Duration::from_micros(500_u64)).await;
         }
 
         // If the restart bit still is not set, return an error, because we cannot restart
         //  the channels.
-        if self.device.read_byte(MODE1_ADDR)? & MODE1_RESTART_BIT == 0 {
+        if self.device.read_byte(MODE1_ADDR).await? & MODE1_RESTART_BIT == 0 {
             return Err(Error::RestartError);
         }
 
         // Clear the sleep bit to awaken the device, give some time for the oscilator to
         //  settle as specified in step 2 of the restart sequence in section "7.3.1.1".
-        self.device.clear_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)?;
+        self.device
+            .clear_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)
+            .await?;
         sleep(Duration::from_micros(500_u64)).await;
 
         // Write a logic 1 to the restart bit to clear it and restart all the channels,
         //  as specified in step 3 of the restart sequence in section "7.3.1.1.".
-        self.device.set_bit_mask(MODE1_ADDR, MODE1_RESTART_BIT)?;
+        self.device
+            .set_bit_mask(MODE1_ADDR, MODE1_RESTART_BIT)
+            .await?;
+        self.awake = true;
+
+        debug!(address = self.address(), "restarted");
 
         // Return success.
         Ok(())
@@ -234,28 +736,85 @@ impl Driver {
     /// # Returns
     ///
     /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
-    pub fn write_channel(&mut self, channel: u8, on: u16, off: u16) -> Result<(), Error> {
-        assert!(on <= 4095_u16);
-        assert!(off <= 4095_u16);
+    pub async fn write_channel(&mut self, channel: u8, on: u16, off: u16) -> Result<(), Error> {
+        if channel >= LED_COUNT {
+            return Err(Error::ChannelError(channel));
+        }
 
-        // Get the base address of the registers for the given channel.
-        let address: u8 = led_on_l_addr(channel);
+        Output::Pwm { on, off }.validate()?;
 
-        println!("{}, {}, {}, {:#x}", channel, on, off, address);
+        trace!(address = self.address(), channel, on, off, "writing channel");
 
-        // // Split the on value into two bytes.
-        let on_l_val: u8 = ((on & 0x00FF_u16) >> 0_u16) as u8;
-        let on_h_val: u8 = ((on & 0xFF00_u16) >> 8_u16) as u8;
+        // Write the values to the registers.
+        self.write_led_registers(channel, &led_bytes(on, off))
+            .await?;
 
-        // // Split the off value into two bytes.
-        let off_l_val: u8 = ((off & 0x00FF_u16) >> 0_u16) as u8;
-        let off_h_val: u8 = ((off & 0xFF00_u16) >> 8_u16) as u8;
+        // Return success.
+        Ok(())
+    }
 
-        // Create a buffer with the values to write.
-        let buffer: [u8; 4] = [on_l_val, on_h_val, off_l_val, off_h_val];
+    /// Writes the on and off values to multiple channels of the PCA9685 device at once.
+    ///
+    /// The registers of consecutive channels are consecutive too, so with auto-increment enabled
+    ///  (as done by `DriverBuilder::build`) every run of consecutive channels is written in a
+    ///  single I2C transaction. The outputs of a run change at the same time, since the PCA9685
+    ///  only applies new values at the end of the PWM period.
+    ///
+    /// Channels that are not consecutive are written in separate transactions, one per run, so
+    ///  the end of a PWM period can fall between them. Only the channels of a single run are
+    ///  guaranteed to change in the same period.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - The channel number, on value and off value of every channel to write, in
+    ///   any order.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channels(&mut self, channels: &[(u8, u16, u16)]) -> Result<(), Error> {
+        // Sort the channels, so consecutive channels end up next to each other.
+        let mut channels = channels.to_vec();
+        channels.sort_by_key(|&(channel, _, _)| channel);
 
-        // Write the values to the registers.
-        self.device.write_bytes(address, &buffer)?;
+        // Check all the channels before writing any of them.
+        for (index, &(channel, on, off)) in channels.iter().enumerate() {
+            let duplicate = index > 0 && channels[index - 1].0 == channel;
+
+            if channel >= LED_COUNT || duplicate {
+                return Err(Error::ChannelError(channel));
+            }
+
+            Output::Pwm { on, off }.validate()?;
+        }
+
+        let mut run: Option<(u8, u8)> = None;
+        let mut buffer = Vec::with_capacity(channels.len() * 4);
+
+        for (channel, on, off) in channels {
+            trace!(address = self.address(), channel, on, off, "writing channel");
+
+            match run {
+                // Continue the run.
+                Some((first, last)) if last + 1 == channel => run = Some((first, channel)),
+                // Start a new run, writing the previous one.
+                _ => {
+                    if let Some((first, _)) = run {
+                        self.write_led_registers(first, &buffer).await?;
+                        buffer.clear();
+                    }
+
+                    run = Some((channel, channel));
+                }
+            }
+
+            buffer.extend_from_slice(&led_bytes(on, off));
+        }
+
+        // Write the last run.
+        if let Some((first, _)) = run {
+            self.write_led_registers(first, &buffer).await?;
+        }
 
         // Return success.
         Ok(())
@@ -275,71 +834,1448 @@ impl Driver {
     /// # Returns
     ///
     /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
-    pub fn write_channel_duty_cycle(&mut self, channel: u8, duty_cycle: f64) -> Result<(), Error> {
-        println!("Duty cycle: {}", duty_cycle);
-        
-        // Compute the on and off values based on the duty cycle.
-        let (on, off) = compute_on_off_time(duty_cycle)?;
+    pub async fn write_channel_duty_cycle(
+        &mut self,
+        channel: u8,
+        duty_cycle: f64,
+    ) -> Result<(), Error> {
+        trace!(address = self.address(), channel, duty_cycle, "writing duty cycle");
+
+        // Compute the on and off values based on the duty cycle and the phase offset.
+        let (on, off) = compute_on_off_time(duty_cycle, self.phase_offset(channel)?)?;
 
         // Write the on and off values to the register.
-        self.write_channel(channel, on, off)?;
+        self.write_channel(channel, on, off).await?;
 
         // Return success.
         Ok(())
     }
-}
-
-/// Represents a channel of a PCA9685 driver.
-pub struct Channel {
-    driver: Arc<Mutex<Driver>>,
-    channel: u8,
-}
 
-impl Channel {
-    /// Creates a new `Channel` instance.
+    /// Writes the duty cycles to multiple channels of the PCA9685 device at once.
+    ///
+    /// See `write_channels` for how the channels are written.
     ///
     /// # Arguments
     ///
-    /// * `driver` - The driver for the PCA9685.
-    /// * `channel` - The channel number.
+    /// * `channels` - The channel number and duty cycle of every channel to write, in any order.
     ///
     /// # Returns
     ///
-    /// A new `Channel` instance.
-    pub fn new(driver: Arc<Mutex<Driver>>, channel: u8) -> Self {
-        Self { driver, channel }
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channels_duty_cycle(&mut self, channels: &[(u8, f64)]) -> Result<(), Error> {
+        // Compute the on and off values based on the duty cycles and the phase offsets.
+        let channels = channels
+            .iter()
+            .map(|&(channel, duty_cycle)| {
+                trace!(address = self.address(), channel, duty_cycle, "writing duty cycle");
+
+                let (on, off) = compute_on_off_time(duty_cycle, self.phase_offset(channel)?)?;
+                Ok((channel, on, off))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Write the on and off values to the registers.
+        self.write_channels(&channels).await
     }
 
-    /// Writes the on and off values to the channel.
+    /// Writes the width of the pulses to the specified channel of the PCA9685 device.
+    ///
+    /// The pulse width is converted into a duty cycle using the actual period of the device (see
+    ///  `period`), so the pulses have the same width at any update rate.
     ///
     /// # Arguments
     ///
-    /// * `on` - The on value.
-    /// * `off` - The off value.
+    /// * `channel` - The channel number to write the pulse width to.
+    /// * `pulse_width` - The width of the pulses in microseconds, ranging from 0 to the period.
     ///
     /// # Returns
     ///
-    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
-    pub async fn write(&mut self, on: u16, off: u16) -> Result<(), Error> {
-        self.driver
-            .lock()
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channel_pulse_width(
+        &mut self,
+        channel: u8,
+        pulse_width: f64,
+    ) -> Result<(), Error> {
+        self.write_channel_duty_cycle(channel, self.pulse_width_duty_cycle(pulse_width))
             .await
-            .write_channel(self.channel, on, off)
     }
 
-    /// Writes the duty cycle to the channel.
+    /// Writes the widths of the pulses to multiple channels of the PCA9685 device at once.
+    ///
+    /// See `write_channel_pulse_width` for how the pulse widths are converted, and
+    ///  `write_channels` for how the channels are written.
     ///
     /// # Arguments
     ///
-    /// * `duty_cycle` - The duty cycle value.
+    /// * `channels` - The channel number and pulse width in microseconds of every channel to
+    ///   write, in any order.
     ///
     /// # Returns
     ///
-    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
-    pub async fn write_duty_cycle(&mut self, duty_cycle: f64) -> Result<(), Error> {
-        self.driver
-            .lock()
-            .await
-            .write_channel_duty_cycle(self.channel, duty_cycle)
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channels_pulse_width(
+        &mut self,
+        channels: &[(u8, f64)],
+    ) -> Result<(), Error> {
+        let channels: Vec<_> = channels
+            .iter()
+            .map(|&(channel, pulse_width)| (channel, self.pulse_width_duty_cycle(pulse_width)))
+            .collect();
+
+        self.write_channels_duty_cycle(&channels).await
+    }
+
+    /// Reads the configuration of the MODE2 register from the PCA9685 device.
+    ///
+    /// # Returns
+    ///
+    /// Returns the configuration, or an `Error` if the read operation fails.
+    pub async fn mode2(&mut self) -> Result<Mode2Config, Error> {
+        let value = self.device.read_byte(MODE2_ADDR).await?;
+
+        Ok(Mode2Config::from_register(value))
+    }
+
+    /// Gets the phase offset of the specified channel, which is the count at which its pulse
+    ///  starts when writing a duty cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number to get the phase offset of.
+    ///
+    /// # Returns
+    ///
+    /// Returns the phase offset, or an `Error` if the channel number is out of range.
+    pub fn phase_offset(&self, channel: u8) -> Result<u16, Error> {
+        self.phase_offsets
+            .get(channel as usize)
+            .copied()
+            .ok_or(Error::ChannelError(channel))
+    }
+
+    /// Sets the phase offset of the specified channel.
+    ///
+    /// The phase offset is applied by the next duty cycle written to the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number to set the phase offset of.
+    /// * `phase_offset` - The count at which the pulse starts, ranging from 0 to 4095.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the phase offset is valid, otherwise returns an `Error`.
+    pub fn set_phase_offset(&mut self, channel: u8, phase_offset: u16) -> Result<(), Error> {
+        if phase_offset > 4095_u16 {
+            return Err(math::Error::PhaseOffsetOutOfBounds(phase_offset).into());
+        }
+
+        *self
+            .phase_offsets
+            .get_mut(channel as usize)
+            .ok_or(Error::ChannelError(channel))? = phase_offset;
+
+        Ok(())
+    }
+
+    /// Writes an output to the specified channel of the PCA9685 device.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number to write the output to.
+    /// * `output` - The output to write.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_channel_output(&mut self, channel: u8, output: Output) -> Result<(), Error> {
+        if channel >= LED_COUNT {
+            return Err(Error::ChannelError(channel));
+        }
+
+        output.validate()?;
+
+        // Write the values to the registers.
+        self.write_led_registers(channel, &output.led_bytes())
+            .await?;
+
+        // Return success.
+        Ok(())
+    }
+
+    /// Writes an output to all the channels of the PCA9685 device at once, using the ALL_LED
+    ///  registers.
+    ///
+    /// This takes a single I2C transaction, such as for turning all the servos off instantly.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The output to write.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn write_all_channels_output(&mut self, output: Output) -> Result<(), Error> {
+        output.validate()?;
+
+        // Remember the output, so it can be restored after the device reset.
+        self.channel_outputs = [Some(output); LED_COUNT as usize];
+
+        // Write the values to the registers.
+        let result = self
+            .device
+            .write_bytes(ALL_LED_ON_L_ADDR, &output.led_bytes())
+            .await;
+        self.track(result)?;
+
+        // The ALL_LED registers always read back as zero, so read back the registers of every
+        //  channel instead, if enabled.
+        if self.verify_writes {
+            let expected = output.led_bytes().repeat(LED_COUNT as usize);
+            self.verify_registers(LED_BASE_ADDR, &expected).await?;
+        }
+
+        // Return success.
+        Ok(())
+    }
+
+    /// Turns all the channels of the PCA9685 device fully off at once.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the write operation is successful, otherwise returns an `Error`.
+    pub async fn all_channels_off(&mut self) -> Result<(), Error> {
+        self.write_all_channels_output(Output::FullOff).await
+    }
+
+    /// Checks whether the device responds to the given LED All Call address or sub-address.
+    pub(crate) fn responds_to(&self, call_address: u8) -> bool {
+        self.configuration.all_call_address == Some(call_address)
+            || self.configuration.sub_addresses.contains(&Some(call_address))
+    }
+
+    /// Remembers an output written to the device through a call address, so it is restored after
+    ///  the device reset like the outputs written by the driver itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel the output was written to, or `None` for all the channels.
+    /// * `output` - The output that was written.
+    pub(crate) fn remember_output(&mut self, channel: Option<u8>, output: Output) {
+        match channel {
+            Some(channel) => {
+                if let Some(slot) = self.channel_outputs.get_mut(channel as usize) {
+                    *slot = Some(output);
+                }
+            }
+            None => self.channel_outputs = [Some(output); LED_COUNT as usize],
+        }
+    }
+
+    /// Gets whether the LED registers are read back after writing them.
+    pub fn verify_writes(&self) -> bool {
+        self.verify_writes
+    }
+
+    /// Sets whether the LED registers are read back after writing them.
+    ///
+    /// When enabled, every write of the channels is followed by a read of the same registers, and
+    ///  a register that does not hold the written value results in an `Error::VerifyError`. This
+    ///  doubles the I2C traffic, but catches a device that silently reset, such as after a
+    ///  brownout.
+    ///
+    /// # Arguments
+    ///
+    /// * `verify_writes` - Whether to verify the writes.
+    pub fn set_verify_writes(&mut self, verify_writes: bool) {
+        self.verify_writes = verify_writes;
+    }
+
+    /// Reads the output of the specified channel from the PCA9685 device.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number to read the output of.
+    ///
+    /// # Returns
+    ///
+    /// Returns the output, or an `Error` if the read operation fails.
+    pub async fn read_channel(&mut self, channel: u8) -> Result<Output, Error> {
+        if channel >= LED_COUNT {
+            return Err(Error::ChannelError(channel));
+        }
+
+        let mut bytes = [0_u8; 4];
+        self.device
+            .read_bytes(led_on_l_addr(channel), &mut bytes)
+            .await?;
+
+        Ok(Output::from_led_bytes(bytes))
+    }
+
+    /// Reads the prescale value from the PCA9685 device.
+    ///
+    /// # Returns
+    ///
+    /// Returns the prescale value, or an `Error` if the read operation fails.
+    pub async fn read_prescale(&mut self) -> Result<u8, Error> {
+        Ok(self.device.read_byte(PRE_SCALE_ADDR).await?)
+    }
+
+    /// Reads all the registers of the PCA9685 device, such as for diagnostics.
+    ///
+    /// # Returns
+    ///
+    /// Returns the values of the registers, or an `Error` if the read operation fails.
+    pub async fn read_registers(&mut self) -> Result<RegisterDump, Error> {
+        let mut registers = [0_u8; LED_LAST_ADDR as usize + 1];
+
+        // With auto-increment enabled the registers can be read in a single transaction,
+        //  otherwise every register is read on its own.
+        registers[0] = self.device.read_byte(MODE1_ADDR).await?;
+
+        if registers[0] & MODE1_AI_BIT != 0 {
+            self.device.read_bytes(MODE1_ADDR, &mut registers).await?;
+        } else {
+            for (address, value) in registers.iter_mut().enumerate().skip(1) {
+                *value = self.device.read_byte(address as u8).await?;
+            }
+        }
+
+        let prescale = self.read_prescale().await?;
+
+        Ok(RegisterDump::new(registers, prescale))
+    }
+
+    /// Writes the given bytes to the LED registers, starting at the ON_L register of the given
+    ///  channel, and reads them back if enabled.
+    async fn write_led_registers(&mut self, channel: u8, bytes: &[u8]) -> Result<(), Error> {
+        if channel as usize + bytes.len() / 4 > LED_COUNT as usize {
+            return Err(Error::ChannelError(channel));
+        }
+
+        let address = led_on_l_addr(channel);
+
+        // Remember the outputs, so they can be restored after the device reset.
+        for (offset, output_bytes) in bytes.chunks_exact(4).enumerate() {
+            let output = Output::from_led_bytes(output_bytes.try_into().unwrap());
+            self.channel_outputs[channel as usize + offset] = Some(output);
+        }
+
+        let result = self.device.write_bytes(address, bytes).await;
+        self.track(result)?;
+
+        if self.verify_writes {
+            self.verify_registers(address, bytes).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the PCA9685 device lost its configuration, such as after a power loss, or
+    ///  whether an I2C transaction failed since the last check, and recovers the device if so.
+    ///
+    /// The device is considered reset when the MODE1 or PRE_SCALE register does not hold the
+    ///  configuration of the `DriverBuilder`, or when it sleeps while it should be awake. Then
+    ///  the configuration is written again, the last commanded output of every channel is
+    ///  restored, and the device is woken up or restarted. After a failed I2C transaction only the
+    ///  outputs are restored, since the failed write may not have reached the device.
+    ///
+    /// This is meant to be called periodically. Every recovery is also sent to the subscribers of
+    ///  `recovery_events`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the recovery event if the device was recovered, `None` if the device is fine, or an
+    ///  `Error` if the device could not be checked or recovered (it is checked again next time).
+    #[instrument(level = "debug", skip(self), fields(address = self.address()))]
+    pub async fn check(&mut self) -> Result<Option<RecoveryEvent>, Error> {
+        let bus_error = self.bus_error.take();
+
+        let result = self.device.read_byte(MODE1_ADDR).await;
+        let mode1 = self.track(result)?;
+
+        let result = self.device.read_byte(PRE_SCALE_ADDR).await;
+        let prescale = self.track(result)?;
+
+        let configuration = self.configuration;
+        let reset = prescale != configuration.prescale
+            || mode1 & Configuration::MODE1_MASK != configuration.mode1_bits()
+            || (self.awake && mode1 & MODE1_SLEEP_BIT != 0);
+
+        let reason = match bus_error {
+            _ if reset => RecoveryReason::Reset { mode1, prescale },
+            Some(kind) => RecoveryReason::BusError(kind),
+            None => return Ok(None),
+        };
+
+        debug!(mode1, prescale, reset, ?bus_error, "recovering");
+
+        if let Err(error) = self.recover(reset).await {
+            // Try again on the next check.
+            self.bus_error = self.bus_error.or(bus_error);
+            return Err(error);
+        }
+
+        let event = RecoveryEvent {
+            address: self.address(),
+            reason,
+        };
+
+        // Having no subscribers is fine.
+        let _ = self.recovery_events.send(event);
+
+        Ok(Some(event))
+    }
+
+    /// Subscribes to the recovery events of the driver, see `check`.
+    ///
+    /// # Returns
+    ///
+    /// The receiver of the recovery events.
+    pub fn recovery_events(&self) -> broadcast::Receiver<RecoveryEvent> {
+        self.recovery_events.subscribe()
+    }
+
+    /// Writes the configuration to the device.
+    ///
+    /// The PRE_SCALE register can only be written while the device sleeps, which it does after
+    ///  a reset.
+    async fn configure(&mut self) -> Result<(), Error> {
+        let configuration = self.configuration;
+
+        debug!(
+            address = self.address(),
+            mode1 = configuration.mode1_bits(),
+            mode2 = configuration.mode2.to_register(),
+            prescale = configuration.prescale,
+            "configuring"
+        );
+
+        // Program the addresses to listen to, which are stored in the upper seven bits of their
+        //  registers.
+        if let Some(address) = configuration.all_call_address {
+            self.device
+                .write_byte(ALLCALLADR_ADDR, address << 1_u8)
+                .await?;
+        }
+
+        for sub_address in SubAddress::ALL {
+            if let Some(address) = configuration.sub_addresses[sub_address.index()] {
+                self.device
+                    .write_byte(sub_address.register(), address << 1_u8)
+                    .await?;
+            }
+        }
+
+        // Only listen to the configured "LED All Calls" and sub-addresses.
+        let address_bits = MODE1_ALLCALL_BIT | MODE1_SUB1_BIT | MODE1_SUB2_BIT | MODE1_SUB3_BIT;
+        let mode1_bits = configuration.mode1_bits() & !MODE1_EXTCLK_BIT;
+
+        self.device
+            .clear_bit_mask(MODE1_ADDR, address_bits & !mode1_bits)
+            .await?;
+
+        // Set the auto increment bit, and the bits of the configured addresses.
+        self.device.set_bit_mask(MODE1_ADDR, mode1_bits).await?;
+
+        // Write the MODE2 configuration, and read it back to verify it.
+        let expected = configuration.mode2.to_register();
+        self.device.write_byte(MODE2_ADDR, expected).await?;
+
+        let actual = self.device.read_byte(MODE2_ADDR).await?;
+
+        if actual != expected {
+            return Err(Error::Mode2Error { expected, actual });
+        }
+
+        // Switch to the external clock, which requires setting the SLEEP bit first, and then
+        //  setting the EXTCLK bit with the SLEEP bit still set (as described in section "7.3.1").
+        if configuration.external_clock {
+            self.device
+                .set_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)
+                .await?;
+            self.device
+                .set_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT | MODE1_EXTCLK_BIT)
+                .await?;
+
+            if self.device.read_byte(MODE1_ADDR).await? & MODE1_EXTCLK_BIT == 0 {
+                return Err(Error::ExternalClockError);
+            }
+        }
+
+        // Write the prescale value to the device.
+        self.device
+            .write_byte(PRE_SCALE_ADDR, configuration.prescale)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Recovers the device, see `check`.
+    async fn recover(&mut self, reset: bool) -> Result<(), Error> {
+        if reset {
+            // Some registers may have survived, so make sure the device sleeps before writing the
+            //  configuration.
+            self.device
+                .set_bit_mask(MODE1_ADDR, MODE1_SLEEP_BIT)
+                .await?;
+            self.configure().await?;
+        }
+
+        // Restore the last commanded output of every channel.
+        for (channel, output) in self.channel_outputs.into_iter().enumerate() {
+            if let Some(output) = output {
+                self.write_led_registers(channel as u8, &output.led_bytes())
+                    .await?;
+            }
+        }
+
+        // Run the restart sequence if the channels were active before the device went to sleep,
+        //  otherwise just wake the device up.
+        if reset && self.awake {
+            if self.device.read_byte(MODE1_ADDR).await? & MODE1_RESTART_BIT != 0 {
+                self.restart().await?;
+            } else {
+                self.wake().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts a pulse width in microseconds into the duty cycle at the actual update rate.
+    fn pulse_width_duty_cycle(&self, pulse_width: f64) -> f64 {
+        pulse_width * self.update_rate() / 1e6_f64
+    }
+
+    /// Remembers the kind of a failed I2C transaction, so the next `check` recovers the device.
+    fn track<T>(&mut self, result: Result<T, device::Error>) -> Result<T, device::Error> {
+        if let Err(error) = &result {
+            self.bus_error = Some(error.kind());
+        }
+
+        result
+    }
+
+    /// Reads the registers starting at the given address back, and checks that they hold the
+    ///  expected values.
+    async fn verify_registers(&mut self, address: u8, expected: &[u8]) -> Result<(), Error> {
+        let mut actual = vec![0_u8; expected.len()];
+        self.device.read_bytes(address, &mut actual).await?;
+
+        let mismatch = expected
+            .iter()
+            .zip(actual.iter())
+            .enumerate()
+            .find(|(_, (expected, actual))| expected != actual);
+
+        if let Some((offset, (&expected, &actual))) = mismatch {
+            return Err(Error::VerifyError {
+                register: address + offset as u8,
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents the output of a PWM channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// A PWM signal that turns on and off at the given counts (0 to 4095) of every period.
+    Pwm { on: u16, off: u16 },
+    /// Always on, using the full ON bit of the LEDn_ON_H register.
+    FullOn,
+    /// Always off, using the full OFF bit of the LEDn_OFF_H register. This cuts the pulse to a
+    ///  servo, so it stops holding its position.
+    FullOff,
+}
+
+impl Output {
+    /// Creates the output for the given duty cycle, with the pulse starting at count 0.
+    ///
+    /// Unlike `Driver::write_channel_duty_cycle`, a duty cycle of 0.0 or 1.0 results in an output
+    ///  that is fully off or on, instead of a PWM signal that is one count short.
+    ///
+    /// # Arguments
+    ///
+    /// * `duty_cycle` - The duty cycle, ranging from 0.0 to 1.0.
+    ///
+    /// # Returns
+    ///
+    /// The output for the duty cycle, or an `Error` if the duty cycle is out of range.
+    pub fn from_duty_cycle(duty_cycle: f64) -> Result<Self, Error> {
+        if !(0_f64..=1_f64).contains(&duty_cycle) {
+            return Err(math::Error::DutyCycleOutOfBounds(duty_cycle).into());
+        }
+
+        if duty_cycle == 0_f64 {
+            return Ok(Output::FullOff);
+        }
+
+        if duty_cycle == 1_f64 {
+            return Ok(Output::FullOn);
+        }
+
+        let (on, off) = compute_on_off_time(duty_cycle, 0_u16)?;
+
+        Ok(Output::Pwm { on, off })
+    }
+
+    /// Decodes the output from the values of the ON_L, ON_H, OFF_L and OFF_H registers.
+    ///
+    /// The full off bit takes precedence over the full on bit (as described in section "7.3.3").
+    pub(crate) fn from_led_bytes(bytes: [u8; 4]) -> Self {
+        let [on_l, on_h, off_l, off_h] = bytes;
+
+        if off_h & LED_FULL_BIT != 0 {
+            return Output::FullOff;
+        }
+
+        if on_h & LED_FULL_BIT != 0 {
+            return Output::FullOn;
+        }
+
+        Output::Pwm {
+            on: u16::from_le_bytes([on_l, on_h & LED_COUNT_H_MASK]),
+            off: u16::from_le_bytes([off_l, off_h & LED_COUNT_H_MASK]),
+        }
+    }
+
+    /// Checks that the on and off counts of a PWM signal fit in the 12-bit counter.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the output can be written, otherwise returns an `Error`.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        match *self {
+            Output::Pwm { on, .. } if on > 4095_u16 => Err(Error::CountError(on)),
+            Output::Pwm { off, .. } if off > 4095_u16 => Err(Error::CountError(off)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the values of the ON_L, ON_H, OFF_L and OFF_H registers for the output, which must
+    ///  have been checked with `validate`.
+    fn led_bytes(&self) -> [u8; 4] {
+        match *self {
+            Output::Pwm { on, off } => led_bytes(on, off),
+            Output::FullOn => [0x00_u8, LED_FULL_BIT, 0x00_u8, 0x00_u8],
+            Output::FullOff => [0x00_u8, 0x00_u8, 0x00_u8, LED_FULL_BIT],
+        }
+    }
+}
+
+/// Splits the on and off values into the bytes of the ON_L, ON_H, OFF_L and OFF_H registers.
+fn led_bytes(on: u16, off: u16) -> [u8; 4] {
+    // Split the on value into two bytes.
+    let on_l_val: u8 = (on & 0x00FF_u16) as u8;
+    let on_h_val: u8 = ((on & 0xFF00_u16) >> 8_u16) as u8;
+
+    // Split the off value into two bytes.
+    let off_l_val: u8 = (off & 0x00FF_u16) as u8;
+    let off_h_val: u8 = ((off & 0xFF00_u16) >> 8_u16) as u8;
+
+    [on_l_val, on_h_val, off_l_val, off_h_val]
+}
+
+/// Represents a channel of a PCA9685 driver.
+pub struct Channel<I2C> {
+    driver: Arc<Mutex<Driver<I2C>>>,
+    channel: u8,
+}
+
+impl<I2C: I2c> Channel<I2C> {
+    /// Creates a new `Channel` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `driver` - The driver for the PCA9685.
+    /// * `channel` - The channel number.
+    ///
+    /// # Returns
+    ///
+    /// A new `Channel` instance.
+    pub fn new(driver: Arc<Mutex<Driver<I2C>>>, channel: u8) -> Self {
+        Self { driver, channel }
+    }
+
+    /// Gets the driver of the channel, such as for writing multiple channels at once.
+    pub fn driver(&self) -> &Arc<Mutex<Driver<I2C>>> {
+        &self.driver
+    }
+
+    /// Gets the channel number.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Writes the on and off values to the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `on` - The on value.
+    /// * `off` - The off value.
+    ///
+    /// # Returns
+    ///
+    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
+    pub async fn write(&mut self, on: u16, off: u16) -> Result<(), Error> {
+        self.driver
+            .lock()
+            .await
+            .write_channel(self.channel, on, off)
+            .await
+    }
+
+    /// Writes the duty cycle to the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `duty_cycle` - The duty cycle value.
+    ///
+    /// # Returns
+    ///
+    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
+    pub async fn write_duty_cycle(&mut self, duty_cycle: f64) -> Result<(), Error> {
+        self.driver
+            .lock()
+            .await
+            .write_channel_duty_cycle(self.channel, duty_cycle)
+            .await
+    }
+
+    /// Writes the width of the pulses to the channel, see `Driver::write_channel_pulse_width`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pulse_width` - The width of the pulses in microseconds.
+    ///
+    /// # Returns
+    ///
+    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
+    pub async fn write_pulse_width(&mut self, pulse_width: f64) -> Result<(), Error> {
+        self.driver
+            .lock()
+            .await
+            .write_channel_pulse_width(self.channel, pulse_width)
+            .await
+    }
+
+    /// Sets the phase offset of the channel, see `Driver::set_phase_offset`.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase_offset` - The count at which the pulse starts, ranging from 0 to 4095.
+    ///
+    /// # Returns
+    ///
+    /// An `Ok` result if the phase offset is valid, otherwise an `Err` containing the error.
+    pub async fn set_phase_offset(&mut self, phase_offset: u16) -> Result<(), Error> {
+        self.driver
+            .lock()
+            .await
+            .set_phase_offset(self.channel, phase_offset)
+    }
+
+    /// Writes an output to the channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - The output, such as `Output::FullOff` to cut the pulse.
+    ///
+    /// # Returns
+    ///
+    /// An `Ok` result if the write operation is successful, otherwise an `Err` containing the error.
+    pub async fn write_output(&mut self, output: Output) -> Result<(), Error> {
+        self.driver
+            .lock()
+            .await
+            .write_channel_output(self.channel, output)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        memory::{led_base_addr, MODE1_DEFAULT, MODE2_DEFAULT},
+        mode2::{OutputDriver, OutputNotEnabled},
+        sim::{Bus, Pin},
+    };
+
+    const ADDRESS: u8 = 0x40_u8;
+
+    async fn build_driver(bus: &Bus) -> Driver<Bus> {
+        let mut device = Device::new(bus.clone(), ADDRESS);
+        device.software_reset().await.unwrap();
+
+        Driver::builder(device)
+            .with_oe_pin(Pin::new())
+            .with_osc_clock(25_000_000)
+            .with_update_rate(50)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build() {
+        let bus = Bus::with_chip(ADDRESS);
+        let oe = Pin::new();
+
+        let mut device = Device::new(bus.clone(), ADDRESS);
+        device.software_reset().await.unwrap();
+
+        Driver::builder(device)
+            .with_oe_pin(oe.clone())
+            .with_osc_clock(25_000_000)
+            .with_update_rate(50)
+            .build()
+            .await
+            .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert!(oe.is_high());
+        assert_eq!(chip.prescale(), 121);
+        assert_eq!(chip.mode1(), MODE1_SLEEP_BIT | MODE1_AI_BIT);
+    }
+
+    #[tokio::test]
+    async fn test_frequency() {
+        let bus = Bus::with_chip(ADDRESS);
+        let driver = build_driver(&bus).await;
+
+        // The prescale value of 121 gives slightly more than the requested 50 Hz.
+        assert_eq!(driver.prescale(), 121);
+        assert!((driver.update_rate() - 25_000_000.0 / (4096.0 * 122.0)).abs() < 1e-9);
+        assert!((driver.period().as_secs_f64() - 1.0 / driver.update_rate()).abs() < 1e-9);
+        assert!((driver.tick_resolution() - 4.88).abs() < 1e-9);
+
+        // A pulse of 300 counts measured at 1500us means the oscillator is slower than assumed.
+        let osc_clock = driver.calibrate_osc_clock(300, 1500.0).unwrap();
+        assert_eq!(osc_clock, 24_400_000);
+    }
+
+    #[tokio::test]
+    async fn test_write_channel_pulse_width() {
+        for update_rate in [50, 60, 330] {
+            let bus = Bus::with_chip(ADDRESS);
+
+            let mut driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+                .with_osc_clock(25_000_000)
+                .with_update_rate(update_rate)
+                .build()
+                .await
+                .unwrap();
+
+            driver.write_channel_pulse_width(0, 1500.0).await.unwrap();
+            driver
+                .write_channels_pulse_width(&[(1, 500.0), (2, 2500.0)])
+                .await
+                .unwrap();
+
+            // The pulses are as wide as requested, to within the rounding of the counts.
+            let chip = bus.chip(ADDRESS).unwrap();
+
+            for (channel, pulse_width) in [(0, 1500.0), (1, 500.0), (2, 2500.0)] {
+                let state = chip.channel(channel);
+                let width = (state.off - state.on) as f64 * driver.tick_resolution();
+
+                assert!((width - pulse_width).abs() <= 2.0 * driver.tick_resolution());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_external_clock() {
+        let bus = Bus::with_chip(ADDRESS);
+
+        let driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_external_clock(10_000_000)
+            .with_update_rate(50)
+            .build()
+            .await
+            .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_ne!(chip.mode1() & MODE1_EXTCLK_BIT, 0);
+        assert_eq!(chip.prescale(), 48);
+        assert!(driver.external_clock());
+        assert_eq!(driver.osc_clock(), 10_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_read_channel() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver.write_channel(2, 100, 2100).await.unwrap();
+        driver
+            .write_channel_output(3, Output::FullOn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            driver.read_channel(2).await.unwrap(),
+            Output::Pwm { on: 100, off: 2100 }
+        );
+        assert_eq!(driver.read_channel(3).await.unwrap(), Output::FullOn);
+        assert!(matches!(
+            driver.read_channel(16).await,
+            Err(Error::ChannelError(16))
+        ));
+        assert_eq!(driver.read_prescale().await.unwrap(), 121);
+    }
+
+    #[tokio::test]
+    async fn test_read_registers() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver.write_channel(15, 0, 4000).await.unwrap();
+
+        let dump = driver.read_registers().await.unwrap();
+        assert_eq!(dump.mode1(), MODE1_SLEEP_BIT | MODE1_AI_BIT);
+        assert_eq!(dump.mode2(), Mode2Config::new());
+        assert_eq!(dump.prescale(), 121);
+        assert_eq!(dump.channel(15), Some(Output::Pwm { on: 0, off: 4000 }));
+
+        // Without auto-increment every register is read on its own.
+        Device::new(bus.clone(), ADDRESS)
+            .software_reset()
+            .await
+            .unwrap();
+
+        let dump = driver.read_registers().await.unwrap();
+        assert_eq!(dump.mode1(), MODE1_DEFAULT);
+        assert_eq!(dump.all_call_address(), address::DEFAULT_ALL_CALL_ADDRESS);
+        assert_eq!(dump.channel(15), Some(Output::FullOff));
+    }
+
+    #[tokio::test]
+    async fn test_verify_writes() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+        driver.set_verify_writes(true);
+
+        driver
+            .write_channels(&[(0, 0, 100), (1, 0, 200)])
+            .await
+            .unwrap();
+        driver
+            .write_all_channels_output(Output::FullOff)
+            .await
+            .unwrap();
+
+        // After a silent reset the auto-increment bit is cleared, so the registers of a channel
+        //  cannot be written in one go anymore.
+        bus.chip_mut(ADDRESS, |chip| chip.reset()).unwrap();
+
+        assert!(matches!(
+            driver.write_channel(0, 0, 300).await,
+            Err(Error::VerifyError { .. })
+        ));
+
+        // Without verification the failed write goes unnoticed.
+        driver.set_verify_writes(false);
+        driver.write_channel(0, 0, 300).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_recovers_from_reset() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+        let mut events = driver.recovery_events();
+
+        driver.wake().await.unwrap();
+        driver
+            .write_channels(&[(0, 0, 300), (1, 256, 556)])
+            .await
+            .unwrap();
+        driver
+            .write_channel_output(2, Output::FullOn)
+            .await
+            .unwrap();
+
+        // Nothing to recover.
+        assert_eq!(driver.check().await.unwrap(), None);
+
+        // Simulate a power loss.
+        bus.chip_mut(ADDRESS, |chip| chip.reset()).unwrap();
+
+        let event = driver.check().await.unwrap().unwrap();
+        assert_eq!(
+            event.reason,
+            RecoveryReason::Reset {
+                mode1: MODE1_DEFAULT,
+                prescale: PRE_SCALE_DEFAULT
+            }
+        );
+        assert_eq!(events.try_recv().unwrap(), event);
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_eq!(chip.mode1() & MODE1_AI_BIT, MODE1_AI_BIT);
+        assert_eq!(chip.prescale(), 121);
+        assert!(chip.outputs_active());
+        assert_eq!(chip.channel(0).off, 300);
+        assert_eq!(chip.channel(1).on, 256);
+        assert!(chip.channel(2).full_on);
+
+        assert_eq!(driver.check().await.unwrap(), None);
+    }
+
+    /// A bus that fails every transaction while `failing` is set.
+    struct FailingBus {
+        bus: Bus,
+        failing: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl embedded_hal::i2c::ErrorType for FailingBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for FailingBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [embedded_hal::i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(ErrorKind::Bus);
+            }
+
+            self.bus.transaction(address, operations)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_recovers_from_bus_error() {
+        let bus = Bus::with_chip(ADDRESS);
+        build_driver(&bus).await;
+
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let failing_bus = FailingBus {
+            bus: bus.clone(),
+            failing: failing.clone(),
+        };
+
+        let mut driver = Driver::new(Device::new(failing_bus, ADDRESS));
+        driver.configuration.prescale = 121;
+
+        failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(driver.write_channel(4, 0, 400).await.is_err());
+
+        // The device cannot be checked while the bus fails, so it is checked again next time.
+        assert!(driver.check().await.is_err());
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+
+        let event = driver.check().await.unwrap().unwrap();
+        assert_eq!(event.reason, RecoveryReason::BusError(ErrorKind::Bus));
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(4).off, 400);
+    }
+
+    #[tokio::test]
+    async fn test_software_reset_restores_defaults() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+        driver.wake().await.unwrap();
+
+        Device::new(bus.clone(), ADDRESS)
+            .software_reset()
+            .await
+            .unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode1(), MODE1_DEFAULT);
+    }
+
+    #[tokio::test]
+    async fn test_wake() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver.wake().await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert!(!chip.is_sleeping());
+        assert!(chip.outputs_active());
+    }
+
+    #[tokio::test]
+    async fn test_restart() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+        driver.wake().await.unwrap();
+        driver.write_channel(0, 0, 300).await.unwrap();
+
+        // Without active channels before sleeping, there is nothing to restart.
+        assert!(matches!(driver.restart().await, Err(Error::RestartError)));
+
+        driver.sleep().await.unwrap();
+        assert!(!bus.chip(ADDRESS).unwrap().outputs_active());
+
+        driver.restart().await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert!(chip.outputs_active());
+        assert_eq!(chip.channel(0).off, 300);
+    }
+
+    #[tokio::test]
+    async fn test_write_channel() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver.write_channel(3, 0x123, 0x456).await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_eq!(chip.register(led_base_addr(3)), 0x23);
+        assert_eq!(chip.channel(3).on, 0x123);
+        assert_eq!(chip.channel(3).off, 0x456);
+        assert!(!chip.channel(3).full_off);
+    }
+
+    #[tokio::test]
+    async fn test_write_channel_invalid() {
+        let bus = Bus::with_chip(ADDRESS);
+        let driver = Arc::new(Mutex::new(build_driver(&bus).await));
+
+        assert!(matches!(
+            driver.lock().await.write_channel(16, 0, 100).await,
+            Err(Error::ChannelError(16))
+        ));
+        assert!(matches!(
+            driver.lock().await.write_channel(0, 5000, 100).await,
+            Err(Error::CountError(5000))
+        ));
+        assert!(matches!(
+            Channel::new(driver.clone(), 16).write(0, 100).await,
+            Err(Error::ChannelError(16))
+        ));
+
+        // The remembered outputs are left as they were.
+        assert_eq!(driver.lock().await.channel_outputs, [None; 16]);
+    }
+
+    /// A bus that counts the transactions made through it.
+    struct CountingBus {
+        bus: Bus,
+        transactions: usize,
+    }
+
+    impl embedded_hal::i2c::ErrorType for CountingBus {
+        type Error = embedded_hal::i2c::ErrorKind;
+    }
+
+    impl I2c for CountingBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [embedded_hal::i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            self.transactions += 1;
+            self.bus.transaction(address, operations)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_channels() {
+        let bus = Bus::with_chip(ADDRESS);
+        build_driver(&bus).await;
+
+        let counting_bus = CountingBus {
+            bus: bus.clone(),
+            transactions: 0,
+        };
+
+        let mut driver = Driver::new(Device::new(counting_bus, ADDRESS));
+
+        driver
+            .write_channels(&[(2, 0, 300), (0, 0, 100), (1, 10, 200), (7, 0, 700)])
+            .await
+            .unwrap();
+
+        // Channels 0 to 2 are written in one burst, channel 7 in another.
+        assert_eq!(driver.device.release().transactions, 2);
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_eq!(chip.channel(0).off, 100);
+        assert_eq!(chip.channel(1).on, 10);
+        assert_eq!(chip.channel(1).off, 200);
+        assert_eq!(chip.channel(2).off, 300);
+        assert_eq!(chip.channel(3).off, 0);
+        assert_eq!(chip.channel(7).off, 700);
+    }
+
+    #[tokio::test]
+    async fn test_write_channels_invalid() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        assert!(matches!(
+            driver.write_channels(&[(1, 0, 100), (1, 0, 200)]).await,
+            Err(Error::ChannelError(1))
+        ));
+        assert!(matches!(
+            driver.write_channels_duty_cycle(&[(16, 0.5)]).await,
+            Err(Error::ChannelError(16))
+        ));
+
+        // Nothing is written if any of the counts is out of range.
+        assert!(matches!(
+            driver.write_channels(&[(0, 0, 100), (1, 0, 4096)]).await,
+            Err(Error::CountError(4096))
+        ));
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(0).off, 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_channel_output() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver
+            .write_channel_output(0, Output::FullOn)
+            .await
+            .unwrap();
+        driver
+            .write_channel_output(1, Output::FullOff)
+            .await
+            .unwrap();
+        driver
+            .write_channel_output(2, Output::from_duty_cycle(0.5).unwrap())
+            .await
+            .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_eq!(chip.channel(0).duty_cycle(), 1_f64);
+        assert_eq!(chip.channel(1).duty_cycle(), 0_f64);
+        assert!(!chip.channel(2).full_on && !chip.channel(2).full_off);
+        assert_eq!(chip.channel(2).off, 2048);
+
+        // Writing a PWM signal clears the full ON and OFF bits.
+        driver
+            .write_channel_output(0, Output::Pwm { on: 0, off: 100 })
+            .await
+            .unwrap();
+        assert!(!bus.chip(ADDRESS).unwrap().channel(0).full_on);
+
+        assert_eq!(Output::from_duty_cycle(0_f64).unwrap(), Output::FullOff);
+        assert_eq!(Output::from_duty_cycle(1_f64).unwrap(), Output::FullOn);
+
+        for duty_cycle in [-0.5_f64, 1.5_f64, f64::NAN] {
+            assert!(matches!(
+                Output::from_duty_cycle(duty_cycle),
+                Err(Error::MathError(math::Error::DutyCycleOutOfBounds(_)))
+            ));
+        }
+
+        // Counts that do not fit in the 12-bit counter are rejected without writing anything.
+        let before = bus.chip(ADDRESS).unwrap().channel(0);
+
+        assert!(matches!(
+            driver
+                .write_channel_output(0, Output::Pwm { on: 0, off: 5000 })
+                .await,
+            Err(Error::CountError(5000))
+        ));
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(0), before);
+    }
+
+    #[tokio::test]
+    async fn test_write_all_channels_output() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver
+            .write_all_channels_output(Output::Pwm { on: 0, off: 300 })
+            .await
+            .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert!((0..16).all(|channel| chip.channel(channel).off == 300));
+
+        driver.all_channels_off().await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert!((0..16).all(|channel| chip.channel(channel).full_off));
+
+        // An invalid output leaves the remembered outputs as they were.
+        assert!(matches!(
+            driver
+                .write_all_channels_output(Output::Pwm { on: 5000, off: 0 })
+                .await,
+            Err(Error::CountError(5000))
+        ));
+        assert_eq!(driver.channel_outputs, [Some(Output::FullOff); 16]);
+    }
+
+    #[tokio::test]
+    async fn test_outputs_enabled() {
+        let bus = Bus::with_chip(ADDRESS);
+        let oe = Pin::new();
+
+        let mut driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_oe_pin(oe.clone())
+            .build()
+            .await
+            .unwrap();
+
+        // The outputs stay disabled until they are enabled explicitly.
+        assert!(!driver.outputs_enabled());
+        assert!(oe.is_high());
+
+        driver.enable_outputs().unwrap();
+        assert!(driver.outputs_enabled());
+        assert!(oe.is_low());
+
+        driver.disable_outputs().unwrap();
+        assert!(!driver.outputs_enabled());
+        assert!(oe.is_high());
+
+        driver.enable_outputs().unwrap();
+        assert!(driver.outputs_enabled());
+        assert!(oe.is_low());
+    }
+
+    #[tokio::test]
+    async fn test_outputs_disabled_after_failed_build() {
+        let bus = Bus::with_chip(ADDRESS);
+        let oe = Pin::new();
+        oe.clone().set_low().unwrap();
+
+        // The prescale of 2000 Hz is out of range, so the device cannot be configured.
+        let result = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_oe_pin(oe.clone())
+            .with_osc_clock(25_000_000)
+            .with_update_rate(2000)
+            .build()
+            .await;
+
+        assert!(matches!(result, Err(Error::MathError(_))));
+        assert!(oe.is_high());
+    }
+
+    #[tokio::test]
+    async fn test_outputs_enabled_without_oe_pin() {
+        let bus = Bus::with_chip(ADDRESS);
+
+        let mut driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .build()
+            .await
+            .unwrap();
+
+        assert!(!driver.has_oe_pin());
+        assert!(driver.outputs_enabled());
+        assert!(matches!(
+            driver.disable_outputs(),
+            Err(Error::MissingOutputEnableError)
+        ));
+        assert!(driver.outputs_enabled());
+        driver.enable_outputs().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mode2() {
+        let bus = Bus::with_chip(ADDRESS);
+
+        let mode2 = Mode2Config::new()
+            .with_invert(true)
+            .with_output_driver(OutputDriver::OpenDrain)
+            .with_output_not_enabled(OutputNotEnabled::HighImpedance);
+
+        let mut driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_mode2(mode2)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode2(), 0b0001_0010);
+        assert_eq!(driver.mode2().await.unwrap(), mode2);
+
+        // The default configuration restores the power-up value.
+        Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode2(), MODE2_DEFAULT);
+    }
+
+    #[tokio::test]
+    async fn test_phase_offsets() {
+        let bus = Bus::with_chip(ADDRESS);
+
+        let mut driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_phase_strategy(PhaseStrategy::Staggered)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(driver.phase_offset(0).unwrap(), 0);
+        assert_eq!(driver.phase_offset(15).unwrap(), 3840);
+
+        driver.set_phase_offset(1, 4000).unwrap();
+        assert!(driver.set_phase_offset(1, 4096).is_err());
+        assert!(matches!(
+            driver.set_phase_offset(16, 0),
+            Err(Error::ChannelError(16))
+        ));
+
+        driver
+            .write_channels_duty_cycle(&[(1, 0.1), (3, 0.1)])
+            .await
+            .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+
+        // The pulse of channel 1 wraps around the end of the period.
+        assert_eq!(chip.channel(1).on, 4000);
+        assert_eq!(chip.channel(1).off, 314);
+        assert_eq!(chip.channel(3).on, 768);
+        assert_eq!(chip.channel(3).off, 1178);
+
+        for channel in [1, 3] {
+            assert!((chip.channel(channel).duty_cycle() - 0.1).abs() < 1e-3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_channel_duty_cycle() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut driver = build_driver(&bus).await;
+
+        driver.write_channel_duty_cycle(5, 0.5).await.unwrap();
+        driver.write_channel_duty_cycle(6, 1.0).await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        assert_eq!(chip.channel(5).off, 2048);
+        assert_eq!(chip.channel(6).off, 4095);
+
+        // Duty cycles out of range are rejected rather than clamped.
+        for duty_cycle in [2.0, -0.5, f64::NAN] {
+            assert!(matches!(
+                driver.write_channel_duty_cycle(7, duty_cycle).await,
+                Err(Error::MathError(math::Error::DutyCycleOutOfBounds(_)))
+            ));
+        }
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(7).off, 0);
+    }
+
+    #[tokio::test]
+    async fn test_channel() {
+        let bus = Bus::with_chip(ADDRESS);
+        let driver = Arc::new(Mutex::new(build_driver(&bus).await));
+        let mut channel = Channel::new(driver, 2);
+
+        channel.write_duty_cycle(0.25).await.unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(2).off, 1024);
     }
 }
@@ -1,7 +1,13 @@
 use thiserror::Error;
 
+use crate::memory::PRE_SCALE_MIN;
+
+/// The number of counts in a PWM period of the PCA9685.
+pub(crate) const COUNTS_PER_PERIOD: u16 = 4096_u16;
+
 /// Represents the possible errors that can occur during computation.
 #[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     /// Represents an error where the prescale value is out of bounds of u8.
     #[error("Prescale {0} out of bounds of u8")]
@@ -10,6 +16,14 @@ pub enum Error {
     /// Represents an error where the duty cycle is out of bounds of 0.0 to 1.0.
     #[error("Duty cycle {0} out of bounds of 0.0 to 1.0")]
     DutyCycleOutOfBounds(f64),
+
+    /// Represents an error where the phase offset is out of bounds of 0 to 4095.
+    #[error("Phase offset {0} out of bounds of 0 to 4095")]
+    PhaseOffsetOutOfBounds(u16),
+
+    /// Represents an error where a pulse width is not a positive number of counts or microseconds.
+    #[error("Pulse width {0} out of bounds")]
+    PulseWidthOutOfBounds(f64),
 }
 
 /// Computes the prescale value for a PCA9685 PWM controller based on the oscillator clock
@@ -24,12 +38,13 @@ pub enum Error {
 ///
 /// Returns a `Result` containing the prescale value as a `u8` if the computation is successful.
 ///  Otherwise, it returns an `Error` with a custom error message.
-pub(crate) fn compute_prescale(osc_clock: u32, update_rate: u16) -> Result<u8, Error> {
+pub fn compute_prescale(osc_clock: u32, update_rate: u16) -> Result<u8, Error> {
     // Compute the prescale value using the formula: (osc_clock / (4096 * update_rate)) - 1.
     let prescale_value = (osc_clock as f64 / (4096_f64 * update_rate as f64)).round() - 1_f64;
 
-    // Check if the prescale value is outside the bounds of u8.
-    if prescale_value < u8::MIN as f64 || prescale_value > u8::MAX as f64 {
+    // Check if the prescale value is outside the bounds of u8, or below the minimum the hardware
+    //  accepts.
+    if prescale_value < PRE_SCALE_MIN as f64 || prescale_value > u8::MAX as f64 {
         // Return an error with a custom error message.
         return Err(Error::PrescaleOutOfBounds(prescale_value));
     }
@@ -38,23 +53,135 @@ pub(crate) fn compute_prescale(osc_clock: u32, update_rate: u16) -> Result<u8, E
     Ok(prescale_value as u8)
 }
 
-/// Calculates the on time for a PWM signal based on the given duty cycle.
+/// Computes the update rate a PCA9685 PWM controller actually runs at for a prescale value, which
+///  differs from the desired update rate because the prescale value is rounded.
+///
+/// # Arguments
+///
+/// * `osc_clock` - The oscillator clock frequency in Hz.
+/// * `prescale` - The prescale value.
+///
+/// # Returns
+///
+/// The update rate in Hz.
+pub(crate) fn compute_update_rate(osc_clock: u32, prescale: u8) -> f64 {
+    osc_clock as f64 / (COUNTS_PER_PERIOD as f64 * (prescale as f64 + 1_f64))
+}
+
+/// Computes the actual oscillator clock frequency from the measured width of a pulse.
+///
+/// A pulse of `counts` counts lasts `counts * (prescale + 1) / osc_clock` seconds, so solving for
+///  the oscillator clock frequency gives the frequency the pulse was actually generated with.
+///
+/// # Arguments
+///
+/// * `prescale` - The prescale value the pulse was generated with.
+/// * `counts` - The width of the pulse in counts, ranging from 1 to 4096.
+/// * `pulse_width` - The measured width of the pulse in microseconds.
+///
+/// # Returns
+///
+/// The oscillator clock frequency in Hz.
+pub(crate) fn compute_osc_clock(prescale: u8, counts: u16, pulse_width: f64) -> Result<u32, Error> {
+    if counts == 0_u16 || counts > COUNTS_PER_PERIOD {
+        return Err(Error::PulseWidthOutOfBounds(counts as f64));
+    }
+
+    if !pulse_width.is_finite() || pulse_width <= 0_f64 {
+        return Err(Error::PulseWidthOutOfBounds(pulse_width));
+    }
+
+    let osc_clock = counts as f64 * (prescale as f64 + 1_f64) / (pulse_width * 1e-6_f64);
+
+    if osc_clock > u32::MAX as f64 {
+        return Err(Error::PulseWidthOutOfBounds(pulse_width));
+    }
+
+    Ok(osc_clock.round() as u32)
+}
+
+/// Calculates the on and off time for a PWM signal based on the given duty cycle.
+///
+/// The pulse starts at the phase offset. If it does not fit in the remainder of the period, the
+///  off time wraps around to the start of the period, which the PCA9685 handles by turning the
+///  output off in the next period (as described in section "7.3.3").
 ///
 /// # Arguments
 ///
 /// * `duty_cycle` - The duty cycle of the PWM signal, ranging from 0.0 to 1.0.
+/// * `phase_offset` - The count at which the pulse starts, ranging from 0 to 4095.
 ///
 /// # Returns
 ///
-/// The on time for the PWM signal, represented as a 16-bit unsigned integer.
-pub(crate) fn compute_on_off_time(mut duty_cycle: f64) -> Result<(u16, u16), Error> {
-    // Clamp the duty cycle.
-    duty_cycle = duty_cycle.clamp(0.0, 1.0);
+/// The on and off time for the PWM signal, represented as 12-bit counts, or an `Error` if the
+///  duty cycle or phase offset is out of range.
+pub(crate) fn compute_on_off_time(duty_cycle: f64, phase_offset: u16) -> Result<(u16, u16), Error> {
+    // Check if the phase offset is outside the bounds of the 12-bit counter.
+    if phase_offset > 4095_u16 {
+        return Err(Error::PhaseOffsetOutOfBounds(phase_offset));
+    }
+
+    // Check if the duty cycle is outside the bounds of 0.0 to 1.0, which includes NaN.
+    if !(0_f64..=1_f64).contains(&duty_cycle) {
+        return Err(Error::DutyCycleOutOfBounds(duty_cycle));
+    }
 
-    // Calculate the on and off time values.
-    let on_time = 0_u16;
-    let off_time = (duty_cycle * 4095_f64).round() as u16;
+    // Calculate the width of the pulse.
+    let width = (duty_cycle * 4095_f64).round() as u16;
+
+    // Calculate the on and off time values, wrapping the off time around the end of the period.
+    let on_time = phase_offset;
+    let off_time = (phase_offset + width) % 4096_u16;
 
     // Return the on time and off time as a tuple.
     Ok((on_time, off_time))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_prescale() {
+        // The example of section "7.3.5": 200 Hz with the internal 25 MHz oscillator.
+        assert_eq!(compute_prescale(25_000_000, 200).unwrap(), 0x1E);
+
+        let prescale = compute_prescale(25_000_000, 50).unwrap();
+        assert_eq!(prescale, 121);
+        assert!((compute_update_rate(25_000_000, prescale) - 50.0).abs() < 0.1);
+
+        assert!(matches!(
+            compute_prescale(25_000_000, 2000),
+            Err(Error::PrescaleOutOfBounds(_))
+        ));
+
+        // A pulse measured 1% longer than expected means the oscillator is 1% slower.
+        let osc_clock = compute_osc_clock(121, 300, 300.0 * 122.0 / 25.0 * 1.01).unwrap();
+        assert!((osc_clock as f64 - 25_000_000.0 / 1.01).abs() < 1.0);
+
+        assert!(compute_osc_clock(121, 0, 1500.0).is_err());
+        assert!(compute_osc_clock(121, 300, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_compute_on_off_time() {
+        assert_eq!(compute_on_off_time(0.5, 0).unwrap(), (0, 2048));
+        assert_eq!(compute_on_off_time(1.0, 0).unwrap(), (0, 4095));
+        assert_eq!(compute_on_off_time(0.1, 1000).unwrap(), (1000, 1410));
+
+        // The pulse wraps around the end of the period.
+        assert_eq!(compute_on_off_time(0.5, 3000).unwrap(), (3000, 952));
+
+        assert!(matches!(
+            compute_on_off_time(0.5, 4096),
+            Err(Error::PhaseOffsetOutOfBounds(4096))
+        ));
+
+        for duty_cycle in [-0.1, 2.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                compute_on_off_time(duty_cycle, 0),
+                Err(Error::DutyCycleOutOfBounds(_))
+            ));
+        }
+    }
+}
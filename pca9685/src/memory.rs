@@ -1,18 +1,56 @@
 pub(crate) const MODE1_ADDR: u8 = 0x00_u8;
 pub(crate) const MODE2_ADDR: u8 = 0x01_u8;
+pub(crate) const SUBADR1_ADDR: u8 = 0x02_u8;
+pub(crate) const SUBADR2_ADDR: u8 = 0x03_u8;
+pub(crate) const SUBADR3_ADDR: u8 = 0x04_u8;
+pub(crate) const ALLCALLADR_ADDR: u8 = 0x05_u8;
 pub(crate) const LED_BASE_ADDR: u8 = 0x06_u8;
+pub(crate) const LED_LAST_ADDR: u8 = 0x45_u8;
+pub(crate) const ALL_LED_ON_L_ADDR: u8 = 0xFA_u8;
+pub(crate) const ALL_LED_ON_H_ADDR: u8 = 0xFB_u8;
+pub(crate) const ALL_LED_OFF_L_ADDR: u8 = 0xFC_u8;
+pub(crate) const ALL_LED_OFF_H_ADDR: u8 = 0xFD_u8;
 pub(crate) const PRE_SCALE_ADDR: u8 = 0xFE_u8;
+pub(crate) const TEST_MODE_ADDR: u8 = 0xFF_u8;
 
+pub(crate) const LED_COUNT: u8 = 16_u8;
 pub(crate) const LED_BASE_OFFSET_MULTIPLIER: u8 = 0x04_u8;
 
 pub(crate) const LED_ON_L_BASE_OFFSET: u8 = 0x00_u8;
+pub(crate) const LED_ON_H_BASE_OFFSET: u8 = 0x01_u8;
+pub(crate) const LED_OFF_L_BASE_OFFSET: u8 = 0x02_u8;
+pub(crate) const LED_OFF_H_BASE_OFFSET: u8 = 0x03_u8;
 
 pub(crate) const MODE1_RESTART_BIT: u8 = 1_u8 << 7_u8;
+pub(crate) const MODE1_EXTCLK_BIT: u8 = 1_u8 << 6_u8;
 pub(crate) const MODE1_AI_BIT: u8 = 1_u8 << 5_u8;
 pub(crate) const MODE1_SLEEP_BIT: u8 = 1_u8 << 4_u8;
+pub(crate) const MODE1_SUB1_BIT: u8 = 1_u8 << 3_u8;
+pub(crate) const MODE1_SUB2_BIT: u8 = 1_u8 << 2_u8;
+pub(crate) const MODE1_SUB3_BIT: u8 = 1_u8 << 1_u8;
 pub(crate) const MODE1_ALLCALL_BIT: u8 = 1_u8 << 0_u8;
 
 pub(crate) const MODE2_IVRT_BIT: u8 = 1_u8 << 4_u8;
+pub(crate) const MODE2_OCH_BIT: u8 = 1_u8 << 3_u8;
+pub(crate) const MODE2_OUTDRV_BIT: u8 = 1_u8 << 2_u8;
+pub(crate) const MODE2_OUTNE_MASK: u8 = 0b11_u8;
+
+/// The bit in the LEDn_ON_H and LEDn_OFF_H registers that forces the output fully on or off.
+pub(crate) const LED_FULL_BIT: u8 = 1_u8 << 4_u8;
+/// The bits in the LEDn_ON_H and LEDn_OFF_H registers that contain the upper bits of the count.
+pub(crate) const LED_COUNT_H_MASK: u8 = 0x0F_u8;
+
+/// The register values after power-up or a software reset (as described in section "7.3").
+pub(crate) const MODE1_DEFAULT: u8 = MODE1_SLEEP_BIT | MODE1_ALLCALL_BIT;
+pub(crate) const MODE2_DEFAULT: u8 = MODE2_OUTDRV_BIT;
+pub(crate) const SUBADR1_DEFAULT: u8 = 0xE2_u8;
+pub(crate) const SUBADR2_DEFAULT: u8 = 0xE4_u8;
+pub(crate) const SUBADR3_DEFAULT: u8 = 0xE8_u8;
+pub(crate) const ALLCALLADR_DEFAULT: u8 = 0xE0_u8;
+pub(crate) const PRE_SCALE_DEFAULT: u8 = 0x1E_u8;
+
+/// The minimum value of the PRE_SCALE register, lower values are forced to this value by the hardware.
+pub(crate) const PRE_SCALE_MIN: u8 = 0x03_u8;
 
 /// Computes the base address of the LED register for the given channel.
 ///
@@ -0,0 +1,213 @@
+use crate::memory::{
+    MODE2_DEFAULT, MODE2_IVRT_BIT, MODE2_OCH_BIT, MODE2_OUTDRV_BIT, MODE2_OUTNE_MASK,
+};
+
+/// The way the outputs are driven (the OUTDRV bit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputDriver {
+    /// The outputs are configured with an open-drain structure.
+    OpenDrain,
+    /// The outputs are configured with a totem pole structure, as needed to drive servos.
+    #[default]
+    TotemPole,
+}
+
+/// The moment at which new PWM values are applied to the outputs (the OCH bit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputChange {
+    /// The outputs change on the I2C STOP condition.
+    #[default]
+    OnStop,
+    /// The outputs change on the I2C ACK of the last register of a channel.
+    OnAck,
+}
+
+/// The state of the outputs while the Output Enable pin is high (the OUTNE bits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputNotEnabled {
+    /// The outputs are low.
+    #[default]
+    Low,
+    /// The outputs are high with a totem pole driver, and high-impedance with an open-drain
+    ///  driver.
+    High,
+    /// The outputs are high-impedance.
+    HighImpedance,
+}
+
+/// Represents the configuration of the MODE2 register (as described in section "7.3.2").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mode2Config {
+    invert: bool,
+    output_change: OutputChange,
+    output_driver: OutputDriver,
+    output_not_enabled: OutputNotEnabled,
+}
+
+impl Mode2Config {
+    /// Creates a new `Mode2Config` instance with the power-up values of the PCA9685.
+    ///
+    /// # Returns
+    ///
+    /// The new `Mode2Config` instance.
+    pub fn new() -> Self {
+        Self::from_register(MODE2_DEFAULT)
+    }
+
+    /// Sets whether the output logic state is inverted (the INVRT bit), such as for LEDs driven
+    ///  through an external driver, and returns the modified `Mode2Config` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `invert`: Whether the output logic state is inverted.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Mode2Config` instance.
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Sets the moment at which new PWM values are applied to the outputs and returns the
+    ///  modified `Mode2Config` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_change`: The moment at which the outputs change.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Mode2Config` instance.
+    pub fn with_output_change(mut self, output_change: OutputChange) -> Self {
+        self.output_change = output_change;
+        self
+    }
+
+    /// Sets the way the outputs are driven and returns the modified `Mode2Config` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_driver`: The way the outputs are driven.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Mode2Config` instance.
+    pub fn with_output_driver(mut self, output_driver: OutputDriver) -> Self {
+        self.output_driver = output_driver;
+        self
+    }
+
+    /// Sets the state of the outputs while the Output Enable pin is high and returns the modified
+    ///  `Mode2Config` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_not_enabled`: The state of the outputs while they are disabled.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Mode2Config` instance.
+    pub fn with_output_not_enabled(mut self, output_not_enabled: OutputNotEnabled) -> Self {
+        self.output_not_enabled = output_not_enabled;
+        self
+    }
+
+    /// Gets whether the output logic state is inverted.
+    pub fn invert(&self) -> bool {
+        self.invert
+    }
+
+    /// Gets the moment at which new PWM values are applied to the outputs.
+    pub fn output_change(&self) -> OutputChange {
+        self.output_change
+    }
+
+    /// Gets the way the outputs are driven.
+    pub fn output_driver(&self) -> OutputDriver {
+        self.output_driver
+    }
+
+    /// Gets the state of the outputs while the Output Enable pin is high.
+    pub fn output_not_enabled(&self) -> OutputNotEnabled {
+        self.output_not_enabled
+    }
+
+    /// Decodes the configuration from the value of the MODE2 register.
+    pub(crate) fn from_register(value: u8) -> Self {
+        Self {
+            invert: value & MODE2_IVRT_BIT != 0,
+            output_change: if value & MODE2_OCH_BIT != 0 {
+                OutputChange::OnAck
+            } else {
+                OutputChange::OnStop
+            },
+            output_driver: if value & MODE2_OUTDRV_BIT != 0 {
+                OutputDriver::TotemPole
+            } else {
+                OutputDriver::OpenDrain
+            },
+            output_not_enabled: match value & MODE2_OUTNE_MASK {
+                0b00_u8 => OutputNotEnabled::Low,
+                0b01_u8 => OutputNotEnabled::High,
+                _ => OutputNotEnabled::HighImpedance,
+            },
+        }
+    }
+
+    /// Encodes the configuration into the value of the MODE2 register.
+    pub(crate) fn to_register(self) -> u8 {
+        let mut value = 0_u8;
+
+        if self.invert {
+            value |= MODE2_IVRT_BIT;
+        }
+
+        if self.output_change == OutputChange::OnAck {
+            value |= MODE2_OCH_BIT;
+        }
+
+        if self.output_driver == OutputDriver::TotemPole {
+            value |= MODE2_OUTDRV_BIT;
+        }
+
+        value |= match self.output_not_enabled {
+            OutputNotEnabled::Low => 0b00_u8,
+            OutputNotEnabled::High => 0b01_u8,
+            OutputNotEnabled::HighImpedance => 0b10_u8,
+        };
+
+        value
+    }
+}
+
+impl Default for Mode2Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_round_trip() {
+        assert_eq!(Mode2Config::new().to_register(), MODE2_DEFAULT);
+
+        let config = Mode2Config::new()
+            .with_invert(true)
+            .with_output_change(OutputChange::OnAck)
+            .with_output_driver(OutputDriver::OpenDrain)
+            .with_output_not_enabled(OutputNotEnabled::HighImpedance);
+
+        assert_eq!(config.to_register(), 0b0001_1010);
+        assert_eq!(Mode2Config::from_register(config.to_register()), config);
+
+        // Both values of the upper OUTNE bit mean high-impedance.
+        assert_eq!(
+            Mode2Config::from_register(0b11).output_not_enabled(),
+            OutputNotEnabled::HighImpedance
+        );
+    }
+}
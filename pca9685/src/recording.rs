@@ -0,0 +1,865 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation as I2cOperation};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    address::SubAddress,
+    device::{GENERAL_CALL_ADDRESS, SWRST_COMMAND},
+    math::{compute_update_rate, COUNTS_PER_PERIOD},
+    memory::{
+        led_base_addr, ALLCALLADR_ADDR, ALLCALLADR_DEFAULT, ALL_LED_OFF_H_ADDR, ALL_LED_ON_L_ADDR,
+        LED_BASE_ADDR, LED_COUNT, LED_FULL_BIT, LED_LAST_ADDR, LED_OFF_H_BASE_OFFSET, MODE1_ADDR,
+        MODE1_AI_BIT, MODE1_ALLCALL_BIT, MODE1_DEFAULT, MODE1_EXTCLK_BIT, MODE1_RESTART_BIT,
+        MODE1_SLEEP_BIT, MODE1_SUB1_BIT, MODE1_SUB2_BIT, MODE1_SUB3_BIT, MODE2_ADDR, MODE2_DEFAULT,
+        PRE_SCALE_ADDR, PRE_SCALE_DEFAULT, SUBADR1_ADDR, SUBADR1_DEFAULT, SUBADR2_ADDR,
+        SUBADR2_DEFAULT, SUBADR3_ADDR, SUBADR3_DEFAULT,
+    },
+    Output,
+};
+
+/// The bytes every recording starts with, followed by the version of the format.
+const MAGIC: &[u8; 7] = b"PCA9685";
+
+/// The version of the recording format.
+const VERSION: u8 = 1_u8;
+
+/// The flag of a record that marks a read, instead of a write.
+const READ_FLAG: u8 = 1_u8 << 0_u8;
+
+/// The flag of a record that marks an operation of a transaction that failed.
+const FAILED_FLAG: u8 = 1_u8 << 1_u8;
+
+/// The flag of a record that marks an operation following another operation of the same
+///  transaction, after a repeated start condition.
+const CONTINUED_FLAG: u8 = 1_u8 << 2_u8;
+
+/// The names of the bits of the MODE1 register, from the most significant bit down.
+const MODE1_BITS: [(u8, &str); 8] = [
+    (MODE1_RESTART_BIT, "RESTART"),
+    (MODE1_EXTCLK_BIT, "EXTCLK"),
+    (MODE1_AI_BIT, "AI"),
+    (MODE1_SLEEP_BIT, "SLEEP"),
+    (MODE1_SUB1_BIT, "SUB1"),
+    (MODE1_SUB2_BIT, "SUB2"),
+    (MODE1_SUB3_BIT, "SUB3"),
+    (MODE1_ALLCALL_BIT, "ALLCALL"),
+];
+
+/// Represents the possible errors that can occur while reading a recording.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Represents an error reading or writing the recording.
+    #[error("I/O Error: {0}")]
+    IoError(#[from] io::Error),
+    /// Represents a file that is not a recording.
+    #[error("Header Error: not a PCA9685 recording")]
+    HeaderError,
+    /// Represents a recording in a version of the format that is not supported.
+    #[error("Version Error: unsupported recording version {0}")]
+    VersionError(u8),
+    /// Represents a record with unknown flags.
+    #[error("Flags Error: unknown record flags {0:#04x}")]
+    FlagsError(u8),
+}
+
+/// The direction of a recorded I2C operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    /// Bytes written to the device.
+    Write,
+    /// Bytes read from the device.
+    Read,
+}
+
+/// Represents a single recorded I2C operation.
+///
+/// A transaction with several operations, such as setting the register pointer and reading the
+///  register, is recorded as one record per operation, the records after the first one being
+///  `continued`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The time since the start of the recording at which the transaction started.
+    pub timestamp: Duration,
+    /// The I2C address the transaction was sent to.
+    pub address: u8,
+    /// The direction of the operation.
+    pub kind: RecordKind,
+    /// Whether the operation continues the transaction of the previous record.
+    pub continued: bool,
+    /// Whether the transaction failed, in which case the bytes of a read are not meaningful.
+    pub failed: bool,
+    /// The bytes written or read.
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Writes the record in the recording format.
+    ///
+    /// Every record consists of the timestamp in microseconds (u64), the address (u8), the flags
+    ///  (u8), the number of bytes (u16) and the bytes themselves, all little-endian.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The writer to write the record to.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the record was written, otherwise returns an `io::Error`.
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut flags = 0_u8;
+
+        if self.kind == RecordKind::Read {
+            flags |= READ_FLAG;
+        }
+
+        if self.failed {
+            flags |= FAILED_FLAG;
+        }
+
+        if self.continued {
+            flags |= CONTINUED_FLAG;
+        }
+
+        let mut buffer = Vec::with_capacity(12 + self.data.len());
+        buffer.extend_from_slice(&(self.timestamp.as_micros() as u64).to_le_bytes());
+        buffer.push(self.address);
+        buffer.push(flags);
+        buffer.extend_from_slice(&(self.data.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(&self.data);
+
+        // Write the record at once, so a crash does not leave half a record behind.
+        writer.write_all(&buffer)
+    }
+
+    /// Reads a record in the recording format, see `write_to`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the record from.
+    ///
+    /// # Returns
+    ///
+    /// Returns the record, `None` at the end of the recording, or an `Error` if the record is
+    ///  invalid or truncated.
+    fn read_from(reader: &mut impl Read) -> Result<Option<Self>, Error> {
+        let mut timestamp = [0_u8; 8];
+
+        // The end of the recording may only occur between records.
+        if reader.read(&mut timestamp[..1])? == 0 {
+            return Ok(None);
+        }
+
+        reader.read_exact(&mut timestamp[1..])?;
+
+        let mut header = [0_u8; 4];
+        reader.read_exact(&mut header)?;
+
+        let [address, flags, length_l, length_h] = header;
+
+        if flags & !(READ_FLAG | FAILED_FLAG | CONTINUED_FLAG) != 0 {
+            return Err(Error::FlagsError(flags));
+        }
+
+        let mut data = vec![0_u8; u16::from_le_bytes([length_l, length_h]) as usize];
+        reader.read_exact(&mut data)?;
+
+        Ok(Some(Self {
+            timestamp: Duration::from_micros(u64::from_le_bytes(timestamp)),
+            address,
+            kind: if flags & READ_FLAG != 0 {
+                RecordKind::Read
+            } else {
+                RecordKind::Write
+            },
+            continued: flags & CONTINUED_FLAG != 0,
+            failed: flags & FAILED_FLAG != 0,
+            data,
+        }))
+    }
+}
+
+/// Represents a recording in progress.
+struct Recording {
+    writer: Box<dyn Write + Send>,
+    started_at: Instant,
+}
+
+/// Represents an I2C bus that can record every transaction sent over it, with timestamps, such as
+///  to capture exactly what the firmware sent to the PCA9685 chips of a misbehaving unit.
+///
+/// The bus passes the transactions through while it is not recording. It can be cloned, all the
+///  clones use the same underlying bus and the same recording, so the clones handed to several
+///  `Device`s end up in one recording.
+pub struct Recorder<I2C> {
+    i2c: I2C,
+    recording: Arc<Mutex<Option<Recording>>>,
+}
+
+impl<I2C> Recorder<I2C> {
+    /// Creates a new `Recorder` instance, which does not record until `start` is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `i2c` - The I2C bus to record the transactions of.
+    ///
+    /// # Returns
+    ///
+    /// A new `Recorder` instance.
+    pub fn new(i2c: I2C) -> Self {
+        Self {
+            i2c,
+            recording: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Starts recording the transactions, replacing the running recording if any.
+    ///
+    /// Every transaction is written as soon as it completes, so the writer should not buffer if the
+    ///  recording should survive a crash. When writing fails, a warning is logged and the recording
+    ///  stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The writer to write the recording to, such as a file.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the recording started, otherwise returns an `io::Error`.
+    pub fn start(&self, writer: impl Write + Send + 'static) -> io::Result<()> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        let recording = Recording {
+            writer,
+            started_at: Instant::now(),
+        };
+
+        if let Some(mut previous) = self.lock().replace(recording) {
+            previous.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops recording the transactions.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the recording was flushed or no recording was running, otherwise returns
+    ///  an `io::Error`.
+    pub fn stop(&self) -> io::Result<()> {
+        match self.lock().take() {
+            Some(mut recording) => recording.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks whether the transactions are being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Locks the recording, recovering it from a panic of another clone.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Recording>> {
+        self.recording
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<I2C: Clone> Clone for Recorder<I2C> {
+    fn clone(&self) -> Self {
+        Self {
+            i2c: self.i2c.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+impl<I2C: I2c> ErrorType for Recorder<I2C> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: I2c> I2c for Recorder<I2C> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [I2cOperation<'_>],
+    ) -> Result<(), Self::Error> {
+        // Hold the recording during the transaction, so the transactions of the clones are
+        //  recorded in the order they were sent.
+        let mut recording = self
+            .recording
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let Some(active) = recording.as_mut() else {
+            drop(recording);
+            return self.i2c.transaction(address, operations);
+        };
+
+        let timestamp = active.started_at.elapsed();
+        let result = self.i2c.transaction(address, operations);
+
+        for (index, operation) in operations.iter().enumerate() {
+            let (kind, data) = match operation {
+                I2cOperation::Write(bytes) => (RecordKind::Write, bytes.to_vec()),
+                I2cOperation::Read(buffer) => (RecordKind::Read, buffer.to_vec()),
+            };
+
+            let record = Record {
+                timestamp,
+                address,
+                kind,
+                continued: index > 0,
+                failed: result.is_err(),
+                data,
+            };
+
+            if let Err(error) = record.write_to(&mut active.writer) {
+                warn!(%error, "I2C recording stopped");
+                *recording = None;
+                break;
+            }
+        }
+
+        result
+    }
+}
+
+/// Represents a writer that keeps a recording in memory, such as to inspect what a `Recorder`
+///  recorded in a simulation or a test.
+///
+/// The writer can be cloned, all the clones write to the same bytes, so a clone can be handed to
+///  `Recorder::start` while the original is kept to read the recording.
+#[derive(Debug, Clone, Default)]
+pub struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+impl SharedWriter {
+    /// Creates a new, empty `SharedWriter` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a copy of the bytes written so far.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.lock().clone()
+    }
+
+    /// Locks the bytes, recovering them from a panic of another clone.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<u8>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.lock().extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the records of a recording.
+pub struct Reader<R> {
+    reader: R,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new `Reader` instance, checking the header of the recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read the recording from, such as a buffered file.
+    ///
+    /// # Returns
+    ///
+    /// A new `Reader` instance, or an `Error` if the recording is invalid.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut header = [0_u8; MAGIC.len() + 1];
+
+        reader
+            .read_exact(&mut header)
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::UnexpectedEof => Error::HeaderError,
+                _ => Error::IoError(error),
+            })?;
+
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(Error::HeaderError);
+        }
+
+        if header[MAGIC.len()] != VERSION {
+            return Err(Error::VersionError(header[MAGIC.len()]));
+        }
+
+        Ok(Self { reader })
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Record::read_from(&mut self.reader).transpose()
+    }
+}
+
+/// Represents a register operation decoded from a record, see `Decoder`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// The software reset sent to the general call address.
+    SoftwareReset,
+    /// A write to the MODE1 register.
+    Mode1 { previous: u8, value: u8 },
+    /// A write to the MODE2 register.
+    Mode2 { value: u8 },
+    /// A write to one of the sub-address registers, as a 7-bit address.
+    SubAddress {
+        sub_address: SubAddress,
+        address: u8,
+    },
+    /// A write to the LED All Call address register, as a 7-bit address.
+    AllCallAddress { address: u8 },
+    /// A write to the PRE_SCALE register, with the update rate it results in.
+    Prescale { value: u8, update_rate: f64 },
+    /// A write to the LEDn registers of a channel, with the resulting width of the pulses in
+    ///  microseconds.
+    Channel {
+        channel: u8,
+        output: Output,
+        pulse_width: f64,
+    },
+    /// A write to the ALL_LED registers, with the resulting width of the pulses in microseconds.
+    AllChannels { output: Output, pulse_width: f64 },
+    /// A write to any other register.
+    Register { register: u8, value: u8 },
+    /// A read of consecutive registers, starting at the given register.
+    Read { register: u8, data: Vec<u8> },
+}
+
+impl Operation {
+    /// Checks whether the operation wakes the chip up by clearing the SLEEP bit, after which its
+    ///  oscillator needs time to settle.
+    pub fn wakes(&self) -> bool {
+        matches!(
+            self,
+            Operation::Mode1 { previous, value }
+                if previous & MODE1_SLEEP_BIT != 0 && value & MODE1_SLEEP_BIT == 0
+        )
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::SoftwareReset => write!(f, "software reset"),
+            Operation::Mode1 { previous, value } => {
+                write!(f, "MODE1 {:#04x} -> {:#04x} (", previous, value)?;
+
+                let names: Vec<_> = MODE1_BITS
+                    .iter()
+                    .filter(|(bit, _)| value & bit != 0)
+                    .map(|(_, name)| *name)
+                    .collect();
+
+                write!(f, "{})", names.join(" "))
+            }
+            Operation::Mode2 { value } => write!(f, "MODE2 {:#04x}", value),
+            Operation::SubAddress {
+                sub_address,
+                address,
+            } => write!(f, "SUBADR{} {:#04x}", sub_address.index() + 1, address),
+            Operation::AllCallAddress { address } => write!(f, "ALLCALLADR {:#04x}", address),
+            Operation::Prescale { value, update_rate } => {
+                write!(f, "PRE_SCALE {:#04x} ({:.3} Hz)", value, update_rate)
+            }
+            Operation::Channel {
+                channel,
+                output,
+                pulse_width,
+            } => write!(f, "LED{} {:?} ({:.1} us)", channel, output, pulse_width),
+            Operation::AllChannels {
+                output,
+                pulse_width,
+            } => write!(f, "ALL_LED {:?} ({:.1} us)", output, pulse_width),
+            Operation::Register { register, value } => {
+                write!(f, "register {:#04x} <- {:#04x}", register, value)
+            }
+            Operation::Read { register, data } => {
+                write!(f, "read {:#04x}: {:02x?}", register, data)
+            }
+        }
+    }
+}
+
+/// Represents the registers of a chip, as far as they can be told from the recording.
+#[derive(Debug, Clone)]
+struct Shadow {
+    registers: [u8; 256],
+    pointer: u8,
+}
+
+impl Shadow {
+    /// Creates the registers of a chip in its power-up state.
+    fn new() -> Self {
+        let mut registers = [0_u8; 256];
+
+        registers[MODE1_ADDR as usize] = MODE1_DEFAULT;
+        registers[MODE2_ADDR as usize] = MODE2_DEFAULT;
+        registers[SUBADR1_ADDR as usize] = SUBADR1_DEFAULT;
+        registers[SUBADR2_ADDR as usize] = SUBADR2_DEFAULT;
+        registers[SUBADR3_ADDR as usize] = SUBADR3_DEFAULT;
+        registers[ALLCALLADR_ADDR as usize] = ALLCALLADR_DEFAULT;
+        registers[PRE_SCALE_ADDR as usize] = PRE_SCALE_DEFAULT;
+
+        for channel in 0..LED_COUNT {
+            registers[(led_base_addr(channel) + LED_OFF_H_BASE_OFFSET) as usize] = LED_FULL_BIT;
+        }
+
+        Self {
+            registers,
+            pointer: MODE1_ADDR,
+        }
+    }
+
+    /// Computes the register the pointer moves to after an access, following the auto increment
+    ///  of the PCA9685 (as described in section "7.3").
+    fn next_pointer(&self, pointer: u8) -> u8 {
+        if self.registers[MODE1_ADDR as usize] & MODE1_AI_BIT == 0 {
+            return pointer;
+        }
+
+        match pointer {
+            LED_LAST_ADDR | PRE_SCALE_ADDR => MODE1_ADDR,
+            _ => pointer.wrapping_add(1_u8),
+        }
+    }
+
+    /// Gets the output programmed in the four registers starting at the given register.
+    fn output(&self, register: u8) -> Output {
+        let mut bytes = [0_u8; 4];
+        bytes.copy_from_slice(&self.registers[register as usize..register as usize + 4]);
+
+        Output::from_led_bytes(bytes)
+    }
+}
+
+/// Decodes the records of a recording into register operations.
+///
+/// The decoder follows the register pointer and the auto increment setting of every address, so
+///  a burst write decodes into the registers it actually reached, and it remembers the PRE_SCALE
+///  of every address to convert the outputs into pulse widths.
+pub struct Decoder {
+    osc_clock: u32,
+    shadows: HashMap<u8, Shadow>,
+    previous: Option<RecordKind>,
+}
+
+impl Decoder {
+    /// Creates a new `Decoder` instance, assuming every chip starts in its power-up state.
+    ///
+    /// # Arguments
+    ///
+    /// * `osc_clock` - The oscillator clock frequency of the chips in Hz, used to compute the
+    ///   update rates and pulse widths.
+    ///
+    /// # Returns
+    ///
+    /// A new `Decoder` instance.
+    pub fn new(osc_clock: u32) -> Self {
+        Self {
+            osc_clock,
+            shadows: HashMap::new(),
+            previous: None,
+        }
+    }
+
+    /// Decodes a record, which must be passed in the order of the recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record to decode.
+    ///
+    /// # Returns
+    ///
+    /// The register operations of the record, in the order they were sent.
+    pub fn decode(&mut self, record: &Record) -> Vec<Operation> {
+        let previous = self
+            .previous
+            .replace(record.kind)
+            .filter(|_| record.continued);
+
+        if record.address == GENERAL_CALL_ADDRESS {
+            if record.kind == RecordKind::Write && record.data == [SWRST_COMMAND] {
+                self.shadows.clear();
+                return vec![Operation::SoftwareReset];
+            }
+
+            return Vec::new();
+        }
+
+        let shadow = self
+            .shadows
+            .entry(record.address)
+            .or_insert_with(Shadow::new);
+
+        match record.kind {
+            RecordKind::Write => {
+                let mut bytes = record.data.iter();
+
+                // The first byte after a (repeated) start condition selects the register.
+                if previous != Some(RecordKind::Write) {
+                    if let Some(&pointer) = bytes.next() {
+                        shadow.pointer = pointer;
+                    }
+                }
+
+                let mut writes = Vec::new();
+
+                for &value in bytes {
+                    let register = shadow.pointer;
+
+                    writes.push((register, shadow.registers[register as usize]));
+                    shadow.registers[register as usize] = value;
+                    shadow.pointer = shadow.next_pointer(register);
+                }
+
+                Self::operations(self.osc_clock, shadow, &writes)
+            }
+            RecordKind::Read => {
+                let register = shadow.pointer;
+
+                // The bytes of a failed read are not what the chip holds.
+                for &value in record.data.iter() {
+                    if !record.failed {
+                        shadow.registers[shadow.pointer as usize] = value;
+                    }
+
+                    shadow.pointer = shadow.next_pointer(shadow.pointer);
+                }
+
+                vec![Operation::Read {
+                    register,
+                    data: record.data.clone(),
+                }]
+            }
+        }
+    }
+
+    /// Converts the written registers into register operations, combining the registers of a
+    ///  channel into a single operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `osc_clock` - The oscillator clock frequency in Hz.
+    /// * `shadow` - The registers after the writes.
+    /// * `writes` - The written registers and their values before the writes, in order.
+    fn operations(osc_clock: u32, shadow: &Shadow, writes: &[(u8, u8)]) -> Vec<Operation> {
+        let prescale = shadow.registers[PRE_SCALE_ADDR as usize];
+        let update_rate = compute_update_rate(osc_clock, prescale);
+
+        let pulse_width = |output: Output| {
+            let counts = match output {
+                Output::Pwm { on, off } => (off as i32 - on as i32).rem_euclid(4096_i32) as f64,
+                Output::FullOn => COUNTS_PER_PERIOD as f64,
+                Output::FullOff => 0_f64,
+            };
+
+            counts / COUNTS_PER_PERIOD as f64 / update_rate * 1_000_000_f64
+        };
+
+        let mut operations = Vec::new();
+
+        for &(register, previous) in writes {
+            let value = shadow.registers[register as usize];
+
+            let operation = match register {
+                MODE1_ADDR => Operation::Mode1 { previous, value },
+                MODE2_ADDR => Operation::Mode2 { value },
+                SUBADR1_ADDR..=SUBADR3_ADDR => Operation::SubAddress {
+                    sub_address: SubAddress::ALL[(register - SUBADR1_ADDR) as usize],
+                    address: value >> 1_u8,
+                },
+                ALLCALLADR_ADDR => Operation::AllCallAddress {
+                    address: value >> 1_u8,
+                },
+                LED_BASE_ADDR..=LED_LAST_ADDR => {
+                    let channel = (register - LED_BASE_ADDR) / 4_u8;
+                    let output = shadow.output(led_base_addr(channel));
+
+                    Operation::Channel {
+                        channel,
+                        output,
+                        pulse_width: pulse_width(output),
+                    }
+                }
+                ALL_LED_ON_L_ADDR..=ALL_LED_OFF_H_ADDR => {
+                    let output = shadow.output(ALL_LED_ON_L_ADDR);
+
+                    Operation::AllChannels {
+                        output,
+                        pulse_width: pulse_width(output),
+                    }
+                }
+                PRE_SCALE_ADDR => Operation::Prescale {
+                    value,
+                    update_rate: compute_update_rate(osc_clock, value),
+                },
+                _ => Operation::Register { register, value },
+            };
+
+            // The four registers of a channel are usually written together, report them once.
+            if let (
+                Some(Operation::Channel { channel: last, .. }),
+                Operation::Channel { channel, .. },
+            ) = (operations.last(), &operation)
+            {
+                if last == channel {
+                    operations.pop();
+                }
+            }
+
+            if let (Some(Operation::AllChannels { .. }), Operation::AllChannels { .. }) =
+                (operations.last(), &operation)
+            {
+                operations.pop();
+            }
+
+            operations.push(operation);
+        }
+
+        operations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{device::Device, sim::Bus, Driver};
+
+    const ADDRESS: u8 = 0x40;
+
+    fn read_records(bytes: Vec<u8>) -> Vec<Record> {
+        Reader::new(Cursor::new(bytes))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let recorder = Recorder::new(Bus::with_chip(ADDRESS));
+        let writer = SharedWriter::new();
+
+        let mut device = Device::new(recorder.clone(), ADDRESS);
+        device.write_byte(MODE2_ADDR, 0x04).await.unwrap();
+
+        recorder.start(writer.clone()).unwrap();
+        assert!(recorder.is_recording());
+
+        device.write_byte(MODE2_ADDR, 0x0C).await.unwrap();
+        assert_eq!(device.read_byte(MODE2_ADDR).await.unwrap(), 0x0C);
+
+        recorder.stop().unwrap();
+        device.write_byte(MODE2_ADDR, 0x04).await.unwrap();
+
+        let records = read_records(writer.bytes());
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].data, [MODE2_ADDR, 0x0C]);
+        assert_eq!(records[1].kind, RecordKind::Write);
+        assert_eq!(records[2].kind, RecordKind::Read);
+        assert!(records[2].continued);
+        assert_eq!(records[2].data, [0x0C]);
+    }
+
+    #[tokio::test]
+    async fn test_decode() {
+        let recorder = Recorder::new(Bus::with_chip(ADDRESS));
+        let writer = SharedWriter::new();
+        recorder.start(writer.clone()).unwrap();
+
+        let mut driver = Driver::builder(Device::new(recorder.clone(), ADDRESS))
+            .with_osc_clock(25_000_000)
+            .with_update_rate(50)
+            .build()
+            .await
+            .unwrap();
+
+        driver.write_channel(3, 0, 307).await.unwrap();
+        driver
+            .write_channels(&[(0, 0, 4095), (1, 0, 0)])
+            .await
+            .unwrap();
+        driver.wake().await.unwrap();
+
+        let mut decoder = Decoder::new(25_000_000);
+        let operations: Vec<_> = read_records(writer.bytes())
+            .iter()
+            .flat_map(|record| decoder.decode(record))
+            .collect();
+
+        assert!(operations.iter().any(|operation| matches!(
+            operation,
+            Operation::Mode1 { value, .. } if value & MODE1_AI_BIT != 0
+        )));
+        assert!(operations
+            .iter()
+            .any(|operation| matches!(operation, Operation::Prescale { value: 0x79, .. })));
+        assert_eq!(
+            operations
+                .iter()
+                .filter(|operation| operation.wakes())
+                .count(),
+            1
+        );
+
+        let channels: Vec<_> = operations
+            .iter()
+            .filter_map(|operation| match operation {
+                Operation::Channel {
+                    channel,
+                    pulse_width,
+                    ..
+                } => Some((*channel, pulse_width.round())),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(channels, [(3, 1498_f64), (0, 19984_f64), (1, 0_f64)]);
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        assert!(matches!(
+            Reader::new(Cursor::new(b"PCA".to_vec())),
+            Err(Error::HeaderError)
+        ));
+        assert!(matches!(
+            Reader::new(Cursor::new(b"PCA9685\x02".to_vec())),
+            Err(Error::VersionError(2))
+        ));
+
+        // A truncated record is an error, rather than the end of the recording.
+        let mut reader = Reader::new(Cursor::new(b"PCA9685\x01\x00\x00".to_vec())).unwrap();
+        assert!(matches!(reader.next(), Some(Err(Error::IoError(_)))));
+    }
+}
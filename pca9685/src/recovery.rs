@@ -0,0 +1,43 @@
+use std::fmt;
+
+use embedded_hal::i2c::ErrorKind;
+
+/// The reason a PCA9685 device was recovered by `Driver::check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryReason {
+    /// The device lost its configuration, such as after a power loss or a brownout.
+    Reset {
+        /// The value of the MODE1 register before the recovery.
+        mode1: u8,
+        /// The value of the PRE_SCALE register before the recovery.
+        prescale: u8,
+    },
+    /// An I2C transaction with the device failed.
+    BusError(ErrorKind),
+}
+
+/// Represents the recovery of a PCA9685 device, see `Driver::check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryEvent {
+    /// The I2C address of the device.
+    pub address: u8,
+    /// The reason the device was recovered.
+    pub reason: RecoveryReason,
+}
+
+impl fmt::Display for RecoveryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            RecoveryReason::Reset { mode1, prescale } => write!(
+                f,
+                "PCA9685 {:#04x} recovered from a reset (MODE1 {:#04x}, PRE_SCALE {:#04x})",
+                self.address, mode1, prescale
+            ),
+            RecoveryReason::BusError(kind) => write!(
+                f,
+                "PCA9685 {:#04x} recovered from an I2C error: {}",
+                self.address, kind
+            ),
+        }
+    }
+}
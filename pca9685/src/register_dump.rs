@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::{
+    memory::{
+        led_on_l_addr, ALLCALLADR_ADDR, LED_COUNT, LED_LAST_ADDR, MODE1_ADDR, MODE2_ADDR,
+        PRE_SCALE_ADDR, SUBADR1_ADDR, SUBADR2_ADDR, SUBADR3_ADDR,
+    },
+    mode2::Mode2Config,
+    Output,
+};
+
+/// Represents the values of all the registers of a PCA9685 device, as read by
+///  `Driver::read_registers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterDump {
+    registers: [u8; LED_LAST_ADDR as usize + 1],
+    prescale: u8,
+}
+
+impl RegisterDump {
+    /// Creates a new `RegisterDump` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `registers` - The values of the registers from MODE1 up to and including LED15_OFF_H.
+    /// * `prescale` - The value of the PRE_SCALE register.
+    ///
+    /// # Returns
+    ///
+    /// A new `RegisterDump` instance.
+    pub(crate) fn new(registers: [u8; LED_LAST_ADDR as usize + 1], prescale: u8) -> Self {
+        Self {
+            registers,
+            prescale,
+        }
+    }
+
+    /// Gets the value of the register at the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the register, from MODE1 up to and including LED15_OFF_H, or
+    ///   PRE_SCALE.
+    ///
+    /// # Returns
+    ///
+    /// The value of the register, or `None` if the register is not part of the dump.
+    pub fn register(&self, address: u8) -> Option<u8> {
+        match address {
+            PRE_SCALE_ADDR => Some(self.prescale),
+            _ => self.registers.get(address as usize).copied(),
+        }
+    }
+
+    /// Gets the value of the MODE1 register.
+    pub fn mode1(&self) -> u8 {
+        self.registers[MODE1_ADDR as usize]
+    }
+
+    /// Gets the configuration of the MODE2 register.
+    pub fn mode2(&self) -> Mode2Config {
+        Mode2Config::from_register(self.registers[MODE2_ADDR as usize])
+    }
+
+    /// Gets the three sub-addresses, as 7-bit addresses.
+    pub fn sub_addresses(&self) -> [u8; 3] {
+        [SUBADR1_ADDR, SUBADR2_ADDR, SUBADR3_ADDR]
+            .map(|address| self.registers[address as usize] >> 1_u8)
+    }
+
+    /// Gets the LED All Call address, as a 7-bit address.
+    pub fn all_call_address(&self) -> u8 {
+        self.registers[ALLCALLADR_ADDR as usize] >> 1_u8
+    }
+
+    /// Gets the value of the PRE_SCALE register.
+    pub fn prescale(&self) -> u8 {
+        self.prescale
+    }
+
+    /// Gets the output of the given channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number (0-15).
+    ///
+    /// # Returns
+    ///
+    /// The output of the channel, or `None` if the channel number is out of range.
+    pub fn channel(&self, channel: u8) -> Option<Output> {
+        if channel >= LED_COUNT {
+            return None;
+        }
+
+        let address = led_on_l_addr(channel) as usize;
+        let mut bytes = [0_u8; 4];
+        bytes.copy_from_slice(&self.registers[address..address + 4]);
+
+        Some(Output::from_led_bytes(bytes))
+    }
+}
+
+impl fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MODE1     {:#04x}", self.mode1())?;
+        writeln!(f, "MODE2     {:#04x}", self.registers[MODE2_ADDR as usize])?;
+
+        for (index, address) in self.sub_addresses().iter().enumerate() {
+            writeln!(f, "SUBADR{}   {:#04x}", index + 1, address)?;
+        }
+
+        writeln!(f, "ALLCALL   {:#04x}", self.all_call_address())?;
+        writeln!(f, "PRE_SCALE {:#04x}", self.prescale)?;
+
+        for channel in 0..LED_COUNT {
+            if let Some(output) = self.channel(channel) {
+                writeln!(f, "LED{:<6} {:?}", channel, output)?;
+            }
+        }
+
+        Ok(())
+    }
+}
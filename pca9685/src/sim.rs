@@ -0,0 +1,750 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+use crate::{
+    device::{GENERAL_CALL_ADDRESS, SWRST_COMMAND},
+    memory::{
+        led_base_addr, ALLCALLADR_ADDR, ALLCALLADR_DEFAULT, ALL_LED_OFF_H_ADDR, ALL_LED_ON_L_ADDR,
+        LED_BASE_ADDR, LED_COUNT, LED_COUNT_H_MASK, LED_FULL_BIT, LED_LAST_ADDR,
+        LED_OFF_H_BASE_OFFSET, LED_OFF_L_BASE_OFFSET, LED_ON_H_BASE_OFFSET, LED_ON_L_BASE_OFFSET,
+        MODE1_ADDR, MODE1_AI_BIT, MODE1_ALLCALL_BIT, MODE1_DEFAULT, MODE1_EXTCLK_BIT,
+        MODE1_RESTART_BIT, MODE1_SLEEP_BIT, MODE1_SUB1_BIT, MODE1_SUB2_BIT, MODE1_SUB3_BIT,
+        MODE2_ADDR, MODE2_DEFAULT, MODE2_OCH_BIT, PRE_SCALE_ADDR, PRE_SCALE_DEFAULT, PRE_SCALE_MIN,
+        SUBADR1_ADDR, SUBADR1_DEFAULT, SUBADR2_ADDR, SUBADR2_DEFAULT, SUBADR3_ADDR,
+        SUBADR3_DEFAULT,
+    },
+};
+
+/// The time the oscillator needs to settle after the SLEEP bit has been cleared (as described in
+///  section "7.3.1.1").
+pub const OSCILLATOR_SETTLE_TIME: Duration = Duration::from_micros(500_u64);
+
+/// Represents the state of a single LED channel of the simulated PCA9685.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelState {
+    /// The 12-bit count at which the output is turned on.
+    pub on: u16,
+    /// The 12-bit count at which the output is turned off.
+    pub off: u16,
+    /// Whether the full on bit (bit 4 of LEDn_ON_H) is set.
+    pub full_on: bool,
+    /// Whether the full off bit (bit 4 of LEDn_OFF_H) is set.
+    pub full_off: bool,
+}
+
+impl ChannelState {
+    /// Computes the fraction of the PWM period during which the output is high.
+    ///
+    /// The full off bit takes precedence over the full on bit (as described in section "7.3.3").
+    ///
+    /// # Returns
+    ///
+    /// The duty cycle of the channel, ranging from 0.0 to 1.0.
+    pub fn duty_cycle(&self) -> f64 {
+        if self.full_off {
+            return 0_f64;
+        }
+
+        if self.full_on {
+            return 1_f64;
+        }
+
+        (self.off as i32 - self.on as i32).rem_euclid(4096_i32) as f64 / 4096_f64
+    }
+}
+
+/// Represents a simulated PCA9685 chip, modelling the register map of the real device.
+#[derive(Debug, Clone)]
+pub struct Pca9685 {
+    address: u8,
+    registers: [u8; 256],
+    pointer: u8,
+    woke_at: Option<Instant>,
+}
+
+impl Pca9685 {
+    /// Creates a new simulated PCA9685 in its power-up state.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The I2C address of the chip, as selected by the A0 to A5 pins.
+    ///
+    /// # Returns
+    ///
+    /// A new `Pca9685` instance.
+    pub fn new(address: u8) -> Self {
+        let mut chip = Self {
+            address,
+            registers: [0_u8; 256],
+            pointer: MODE1_ADDR,
+            woke_at: None,
+        };
+
+        chip.reset();
+
+        chip
+    }
+
+    /// Gets the I2C address of the chip.
+    ///
+    /// # Returns
+    ///
+    /// The I2C address of the chip.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Resets all registers to their power-up values, as a power cycle or software reset would.
+    pub fn reset(&mut self) {
+        self.registers = [0_u8; 256];
+
+        self.registers[MODE1_ADDR as usize] = MODE1_DEFAULT;
+        self.registers[MODE2_ADDR as usize] = MODE2_DEFAULT;
+        self.registers[SUBADR1_ADDR as usize] = SUBADR1_DEFAULT;
+        self.registers[SUBADR2_ADDR as usize] = SUBADR2_DEFAULT;
+        self.registers[SUBADR3_ADDR as usize] = SUBADR3_DEFAULT;
+        self.registers[ALLCALLADR_ADDR as usize] = ALLCALLADR_DEFAULT;
+        self.registers[PRE_SCALE_ADDR as usize] = PRE_SCALE_DEFAULT;
+
+        // All the channels are fully off after a reset.
+        for channel in 0..LED_COUNT {
+            self.registers[(led_base_addr(channel) + LED_OFF_H_BASE_OFFSET) as usize] =
+                LED_FULL_BIT;
+        }
+
+        self.pointer = MODE1_ADDR;
+        self.woke_at = None;
+    }
+
+    /// Gets the raw value of a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the register.
+    ///
+    /// # Returns
+    ///
+    /// The value stored in the register.
+    pub fn register(&self, address: u8) -> u8 {
+        self.registers[address as usize]
+    }
+
+    /// Gets the value of the MODE1 register.
+    pub fn mode1(&self) -> u8 {
+        self.register(MODE1_ADDR)
+    }
+
+    /// Gets the value of the MODE2 register.
+    pub fn mode2(&self) -> u8 {
+        self.register(MODE2_ADDR)
+    }
+
+    /// Gets the value of the PRE_SCALE register.
+    pub fn prescale(&self) -> u8 {
+        self.register(PRE_SCALE_ADDR)
+    }
+
+    /// Checks whether the chip is in low power mode (the SLEEP bit is set).
+    pub fn is_sleeping(&self) -> bool {
+        self.mode1() & MODE1_SLEEP_BIT != 0
+    }
+
+    /// Checks whether the PWM outputs are running.
+    ///
+    /// The outputs stop when the chip goes to sleep, and stay stopped after waking up as long as
+    ///  the RESTART bit has not been cleared (as described in section "7.3.1.1").
+    pub fn outputs_active(&self) -> bool {
+        self.mode1() & (MODE1_SLEEP_BIT | MODE1_RESTART_BIT) == 0
+    }
+
+    /// Gets the state of the given channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - The channel number (0-15).
+    ///
+    /// # Returns
+    ///
+    /// The state of the channel as programmed in its LEDn registers.
+    pub fn channel(&self, channel: u8) -> ChannelState {
+        let base = led_base_addr(channel);
+
+        let on_l = self.register(base + LED_ON_L_BASE_OFFSET);
+        let on_h = self.register(base + LED_ON_H_BASE_OFFSET);
+        let off_l = self.register(base + LED_OFF_L_BASE_OFFSET);
+        let off_h = self.register(base + LED_OFF_H_BASE_OFFSET);
+
+        ChannelState {
+            on: u16::from_le_bytes([on_l, on_h & LED_COUNT_H_MASK]),
+            off: u16::from_le_bytes([off_l, off_h & LED_COUNT_H_MASK]),
+            full_on: on_h & LED_FULL_BIT != 0,
+            full_off: off_h & LED_FULL_BIT != 0,
+        }
+    }
+
+    /// Checks whether the chip acknowledges the given I2C address.
+    ///
+    /// Next to its own address the chip responds to the LED All Call address and the three
+    ///  sub-addresses, when they are enabled in the MODE1 register.
+    fn responds_to(&self, address: u8) -> bool {
+        let mode1 = self.mode1();
+
+        address == self.address
+            || [
+                (MODE1_ALLCALL_BIT, ALLCALLADR_ADDR),
+                (MODE1_SUB1_BIT, SUBADR1_ADDR),
+                (MODE1_SUB2_BIT, SUBADR2_ADDR),
+                (MODE1_SUB3_BIT, SUBADR3_ADDR),
+            ]
+            .iter()
+            .any(|&(bit, register)| mode1 & bit != 0 && self.register(register) >> 1_u8 == address)
+    }
+
+    /// Checks whether any of the channels is producing a non-zero output.
+    fn any_channel_active(&self) -> bool {
+        (0..LED_COUNT).any(|channel| self.channel(channel).duty_cycle() > 0_f64)
+    }
+
+    /// Computes the register the control register points to after an access to `pointer`.
+    ///
+    /// Auto increment wraps from the last LED register and from PRE_SCALE back to MODE1 (as
+    ///  described in section "7.3").
+    fn next_pointer(&self, pointer: u8) -> u8 {
+        if self.mode1() & MODE1_AI_BIT == 0 {
+            return pointer;
+        }
+
+        match pointer {
+            LED_LAST_ADDR | PRE_SCALE_ADDR => MODE1_ADDR,
+            _ => pointer.wrapping_add(1_u8),
+        }
+    }
+
+    /// Reads a register the way the I2C interface does.
+    fn read_register(&self, address: u8) -> u8 {
+        match address {
+            // The ALL_LED registers always read back as zero.
+            ALL_LED_ON_L_ADDR..=ALL_LED_OFF_H_ADDR => 0_u8,
+            MODE1_ADDR..=LED_LAST_ADDR | PRE_SCALE_ADDR => self.register(address),
+            _ => 0_u8,
+        }
+    }
+
+    /// Writes a register the way the I2C interface does.
+    ///
+    /// # Returns
+    ///
+    /// Whether a PWM register was written.
+    fn write_register(&mut self, address: u8, value: u8) -> bool {
+        match address {
+            MODE1_ADDR => {
+                self.write_mode1(value);
+                false
+            }
+            MODE2_ADDR => {
+                // Bits 7 to 5 are reserved and read only.
+                self.registers[address as usize] = value & 0x1F_u8;
+                false
+            }
+            SUBADR1_ADDR..=ALLCALLADR_ADDR => {
+                // Bit 0 is reserved and read only.
+                self.registers[address as usize] = value & 0xFE_u8;
+                false
+            }
+            LED_BASE_ADDR..=LED_LAST_ADDR => {
+                self.write_led_register(address, value);
+                true
+            }
+            ALL_LED_ON_L_ADDR..=ALL_LED_OFF_H_ADDR => {
+                // Writes to the ALL_LED registers are applied to the registers of every channel.
+                let offset = address - ALL_LED_ON_L_ADDR;
+
+                for channel in 0..LED_COUNT {
+                    self.write_led_register(led_base_addr(channel) + offset, value);
+                }
+
+                true
+            }
+            PRE_SCALE_ADDR => {
+                // The PRE_SCALE register can only be written while the chip is sleeping, and the
+                //  hardware forces a minimum value of 3 (as described in section "7.3.5").
+                if self.is_sleeping() {
+                    self.registers[address as usize] = value.max(PRE_SCALE_MIN);
+                }
+
+                false
+            }
+            // The test mode register and the reserved registers are not writeable.
+            _ => false,
+        }
+    }
+
+    /// Writes one of the LEDn registers, masking the reserved bits of the high registers.
+    fn write_led_register(&mut self, address: u8, value: u8) {
+        let offset = (address - LED_BASE_ADDR) % 4_u8;
+
+        self.registers[address as usize] =
+            if offset == LED_ON_H_BASE_OFFSET || offset == LED_OFF_H_BASE_OFFSET {
+                value & (LED_FULL_BIT | LED_COUNT_H_MASK)
+            } else {
+                value
+            };
+    }
+
+    /// Writes the MODE1 register, applying the SLEEP and RESTART semantics from section "7.3.1.1".
+    fn write_mode1(&mut self, value: u8) {
+        let old = self.mode1();
+        let was_sleeping = old & MODE1_SLEEP_BIT != 0;
+        let sleeping = value & MODE1_SLEEP_BIT != 0;
+
+        // The oscillator only counts as settled if it has been running for at least 500us before
+        //  this write.
+        let settled = self
+            .woke_at
+            .map(|woke_at| woke_at.elapsed() >= OSCILLATOR_SETTLE_TIME)
+            .unwrap_or(false);
+
+        let mut new = value & !(MODE1_RESTART_BIT | MODE1_EXTCLK_BIT);
+
+        // The EXTCLK bit is sticky, and can only be set while the chip is sleeping.
+        if old & MODE1_EXTCLK_BIT != 0 || (was_sleeping && value & MODE1_EXTCLK_BIT != 0) {
+            new |= MODE1_EXTCLK_BIT;
+        }
+
+        // Writing a logic 1 to the RESTART bit clears it once the oscillator is running and has
+        //  settled, writing a logic 0 has no effect.
+        let restart = value & MODE1_RESTART_BIT != 0 && !was_sleeping && !sleeping && settled;
+
+        if old & MODE1_RESTART_BIT != 0 && !restart {
+            new |= MODE1_RESTART_BIT;
+        }
+
+        // Going to sleep while any of the channels is active sets the RESTART bit.
+        if !was_sleeping && sleeping {
+            if self.any_channel_active() {
+                new |= MODE1_RESTART_BIT;
+            }
+
+            self.woke_at = None;
+        }
+
+        // Leaving sleep starts the oscillator.
+        if was_sleeping && !sleeping {
+            self.woke_at = Some(Instant::now());
+        }
+
+        self.registers[MODE1_ADDR as usize] = new;
+    }
+
+    /// Clears the RESTART bit, as happens when the PWM registers are rewritten.
+    fn clear_restart(&mut self) {
+        self.registers[MODE1_ADDR as usize] &= !MODE1_RESTART_BIT;
+    }
+
+    /// Handles a single I2C transaction addressed to this chip.
+    ///
+    /// # Arguments
+    ///
+    /// * `direct` - Whether the chip was addressed by its own address (reads are only allowed then).
+    /// * `operations` - The operations of the transaction.
+    fn transaction(
+        &mut self,
+        direct: bool,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), ErrorKind> {
+        let mut previous_was_write = false;
+        let mut pwm_written = false;
+        let mut channel_writes = [0_u8; LED_COUNT as usize];
+
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(bytes) => {
+                    let mut bytes = bytes.iter();
+
+                    // The first byte after a (repeated) start condition selects the register.
+                    if !previous_was_write {
+                        if let Some(&pointer) = bytes.next() {
+                            self.pointer = pointer;
+                        }
+                    }
+
+                    for &value in bytes {
+                        let address = self.pointer;
+
+                        if self.write_register(address, value) {
+                            pwm_written = true;
+
+                            if (LED_BASE_ADDR..=LED_LAST_ADDR).contains(&address) {
+                                let offset = address - LED_BASE_ADDR;
+                                channel_writes[(offset / 4_u8) as usize] |= 1_u8 << (offset % 4_u8);
+                            } else {
+                                channel_writes.iter_mut().for_each(|mask| {
+                                    *mask |= 1_u8 << (address - ALL_LED_ON_L_ADDR)
+                                });
+                            }
+                        }
+
+                        // When outputs change on ACK, writing all four PWM registers of a channel
+                        //  clears the RESTART bit (as described in section "7.3.1.1").
+                        if self.mode2() & MODE2_OCH_BIT != 0 && channel_writes.contains(&0x0F_u8) {
+                            self.clear_restart();
+                        }
+
+                        self.pointer = self.next_pointer(address);
+                    }
+
+                    previous_was_write = true;
+                }
+                Operation::Read(buffer) => {
+                    if !direct {
+                        return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+                    }
+
+                    for value in buffer.iter_mut() {
+                        *value = self.read_register(self.pointer);
+                        self.pointer = self.next_pointer(self.pointer);
+                    }
+
+                    previous_was_write = false;
+                }
+            }
+        }
+
+        // When outputs change on STOP, writing any PWM register clears the RESTART bit (as
+        //  described in section "7.3.1.1").
+        if pwm_written && self.mode2() & MODE2_OCH_BIT == 0 {
+            self.clear_restart();
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents a simulated I2C bus with any number of PCA9685 chips attached to it.
+///
+/// The bus can be cloned, all the clones share the same chips, so one clone can be handed to a
+///  `Device` while another one is used to inspect the state of the chips.
+#[derive(Debug, Clone, Default)]
+pub struct Bus {
+    chips: Arc<Mutex<Vec<Pca9685>>>,
+}
+
+impl Bus {
+    /// Creates a new simulated bus without any chips attached to it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new simulated bus with a single chip attached to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The I2C address of the chip.
+    ///
+    /// # Returns
+    ///
+    /// A new `Bus` instance.
+    pub fn with_chip(address: u8) -> Self {
+        let bus = Self::new();
+        bus.attach(Pca9685::new(address));
+        bus
+    }
+
+    /// Attaches a chip to the bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip` - The chip to attach.
+    pub fn attach(&self, chip: Pca9685) {
+        self.chips.lock().unwrap().push(chip);
+    }
+
+    /// Gets a snapshot of the chip with the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The I2C address of the chip.
+    ///
+    /// # Returns
+    ///
+    /// A copy of the chip, or `None` if no chip with the given address is attached.
+    pub fn chip(&self, address: u8) -> Option<Pca9685> {
+        self.chips
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|chip| chip.address == address)
+            .cloned()
+    }
+
+    /// Modifies the chip with the given address, for example to simulate a power loss.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The I2C address of the chip.
+    /// * `f` - The function to apply to the chip.
+    ///
+    /// # Returns
+    ///
+    /// The result of `f`, or `None` if no chip with the given address is attached.
+    pub fn chip_mut<R>(&self, address: u8, f: impl FnOnce(&mut Pca9685) -> R) -> Option<R> {
+        self.chips
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|chip| chip.address == address)
+            .map(f)
+    }
+}
+
+impl ErrorType for Bus {
+    type Error = ErrorKind;
+}
+
+impl I2c for Bus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut chips = self.chips.lock().unwrap();
+
+        // Handle the software reset sent to the general call address (as described in section "7.6").
+        if address == GENERAL_CALL_ADDRESS {
+            for operation in operations.iter() {
+                match operation {
+                    Operation::Write(bytes) if bytes == &[SWRST_COMMAND] => {
+                        chips.iter_mut().for_each(Pca9685::reset);
+                    }
+                    Operation::Write(_) => {}
+                    Operation::Read(_) => {
+                        return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+                    }
+                }
+            }
+
+            return Ok(());
+        }
+
+        let mut acknowledged = false;
+
+        for chip in chips.iter_mut().filter(|chip| chip.responds_to(address)) {
+            let direct = chip.address == address;
+
+            chip.transaction(direct, operations)?;
+            acknowledged = true;
+        }
+
+        if !acknowledged {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents a simulated output pin, such as the Output Enable pin of the PCA9685.
+///
+/// The pin can be cloned, all the clones share the same level.
+#[derive(Debug, Clone)]
+pub struct Pin {
+    high: Arc<AtomicBool>,
+}
+
+impl Pin {
+    /// Creates a new simulated pin, initially high like a pulled up Output Enable pin.
+    pub fn new() -> Self {
+        Self {
+            high: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Checks whether the pin is driven high.
+    pub fn is_high(&self) -> bool {
+        self.high.load(Ordering::SeqCst)
+    }
+
+    /// Checks whether the pin is driven low.
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+}
+
+impl Default for Pin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl embedded_hal::digital::ErrorType for Pin {
+    type Error = Infallible;
+}
+
+impl embedded_hal::digital::OutputPin for Pin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.high.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.high.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: u8 = 0x40_u8;
+
+    fn write(bus: &mut Bus, bytes: &[u8]) -> Result<(), ErrorKind> {
+        bus.write(ADDRESS, bytes)
+    }
+
+    fn read(bus: &mut Bus, register: u8) -> u8 {
+        let mut buffer = [0_u8];
+        bus.write_read(ADDRESS, &[register], &mut buffer).unwrap();
+        buffer[0]
+    }
+
+    #[test]
+    fn test_power_up_state() {
+        let chip = Pca9685::new(ADDRESS);
+
+        assert_eq!(chip.mode1(), 0x11_u8);
+        assert_eq!(chip.mode2(), 0x04_u8);
+        assert_eq!(chip.prescale(), 0x1E_u8);
+        assert_eq!(chip.register(ALLCALLADR_ADDR), 0xE0_u8);
+        assert!(chip.is_sleeping());
+        assert!(chip.channel(15).full_off);
+        assert_eq!(chip.channel(15).duty_cycle(), 0_f64);
+    }
+
+    #[test]
+    fn test_auto_increment() {
+        let mut bus = Bus::with_chip(ADDRESS);
+
+        // Without auto increment all the bytes go to the same register.
+        write(&mut bus, &[led_base_addr(0), 0x11, 0x22]).unwrap();
+        assert_eq!(read(&mut bus, led_base_addr(0)), 0x22);
+        assert_eq!(read(&mut bus, led_base_addr(0) + 1), 0x00);
+
+        // With auto increment the bytes go to sequential registers.
+        write(&mut bus, &[MODE1_ADDR, MODE1_DEFAULT | MODE1_AI_BIT]).unwrap();
+        write(&mut bus, &[led_base_addr(1), 0x00, 0x01, 0x34, 0x12]).unwrap();
+
+        let channel = bus.chip(ADDRESS).unwrap().channel(1);
+        assert_eq!(channel.on, 0x0100);
+        assert_eq!(channel.off, 0x0234);
+        assert!(channel.full_off);
+    }
+
+    #[test]
+    fn test_auto_increment_wraps_after_last_led_register() {
+        let mut bus = Bus::with_chip(ADDRESS);
+        write(&mut bus, &[MODE1_ADDR, MODE1_DEFAULT | MODE1_AI_BIT]).unwrap();
+
+        write(&mut bus, &[LED_LAST_ADDR, 0x00, MODE1_DEFAULT]).unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode1(), MODE1_DEFAULT);
+    }
+
+    #[test]
+    fn test_prescale_only_writeable_while_sleeping() {
+        let mut bus = Bus::with_chip(ADDRESS);
+
+        write(&mut bus, &[PRE_SCALE_ADDR, 0x79]).unwrap();
+        assert_eq!(read(&mut bus, PRE_SCALE_ADDR), 0x79);
+
+        write(&mut bus, &[PRE_SCALE_ADDR, 0x01]).unwrap();
+        assert_eq!(read(&mut bus, PRE_SCALE_ADDR), PRE_SCALE_MIN);
+
+        write(&mut bus, &[MODE1_ADDR, 0x00]).unwrap();
+        write(&mut bus, &[PRE_SCALE_ADDR, 0x50]).unwrap();
+        assert_eq!(read(&mut bus, PRE_SCALE_ADDR), PRE_SCALE_MIN);
+    }
+
+    #[test]
+    fn test_sleep_and_restart() {
+        let mut bus = Bus::with_chip(ADDRESS);
+        write(&mut bus, &[MODE1_ADDR, MODE1_AI_BIT]).unwrap();
+        write(&mut bus, &[led_base_addr(0), 0x00, 0x00, 0x00, 0x01]).unwrap();
+        assert!(bus.chip(ADDRESS).unwrap().outputs_active());
+
+        // Going to sleep with an active channel sets the RESTART bit.
+        write(&mut bus, &[MODE1_ADDR, MODE1_AI_BIT | MODE1_SLEEP_BIT]).unwrap();
+        assert_ne!(read(&mut bus, MODE1_ADDR) & MODE1_RESTART_BIT, 0);
+
+        // Waking up does not restart the outputs, and the restart is ignored while the
+        //  oscillator has not settled.
+        write(&mut bus, &[MODE1_ADDR, MODE1_AI_BIT | MODE1_RESTART_BIT]).unwrap();
+        assert_ne!(read(&mut bus, MODE1_ADDR) & MODE1_RESTART_BIT, 0);
+        assert!(!bus.chip(ADDRESS).unwrap().outputs_active());
+
+        // Writing a logic 1 to the RESTART bit after 500us restarts the outputs.
+        std::thread::sleep(OSCILLATOR_SETTLE_TIME);
+        write(&mut bus, &[MODE1_ADDR, MODE1_AI_BIT | MODE1_RESTART_BIT]).unwrap();
+        assert_eq!(read(&mut bus, MODE1_ADDR) & MODE1_RESTART_BIT, 0);
+        assert!(bus.chip(ADDRESS).unwrap().outputs_active());
+    }
+
+    #[test]
+    fn test_all_led_registers() {
+        let mut bus = Bus::with_chip(ADDRESS);
+        write(&mut bus, &[MODE1_ADDR, MODE1_SLEEP_BIT | MODE1_AI_BIT]).unwrap();
+
+        write(
+            &mut bus,
+            &[ALL_LED_ON_L_ADDR, 0x00, 0x00, 0x00, LED_FULL_BIT | 0x01],
+        )
+        .unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        for channel in 0..LED_COUNT {
+            assert_eq!(chip.channel(channel).off, 0x0100);
+            assert!(chip.channel(channel).full_off);
+        }
+
+        assert_eq!(read(&mut bus, ALL_LED_OFF_H_ADDR), 0x00);
+    }
+
+    #[test]
+    fn test_software_reset() {
+        let mut bus = Bus::with_chip(ADDRESS);
+        write(&mut bus, &[MODE1_ADDR, MODE1_AI_BIT]).unwrap();
+
+        bus.write(GENERAL_CALL_ADDRESS, &[SWRST_COMMAND]).unwrap();
+
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode1(), MODE1_DEFAULT);
+    }
+
+    #[test]
+    fn test_addressing() {
+        let mut bus = Bus::with_chip(ADDRESS);
+        bus.attach(Pca9685::new(ADDRESS + 1));
+
+        // Both chips respond to the LED All Call address, but reads are not acknowledged.
+        bus.write(ALLCALLADR_DEFAULT >> 1, &[MODE1_ADDR, MODE1_ALLCALL_BIT])
+            .unwrap();
+        assert_eq!(bus.chip(ADDRESS).unwrap().mode1(), MODE1_ALLCALL_BIT);
+        assert_eq!(bus.chip(ADDRESS + 1).unwrap().mode1(), MODE1_ALLCALL_BIT);
+        assert!(bus
+            .write_read(ALLCALLADR_DEFAULT >> 1, &[MODE1_ADDR], &mut [0_u8])
+            .is_err());
+
+        // Unknown addresses are not acknowledged.
+        assert_eq!(
+            bus.write(0x7F, &[MODE1_ADDR, 0x00]),
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+    }
+}
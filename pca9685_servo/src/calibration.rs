@@ -0,0 +1,401 @@
+use thiserror::Error;
+
+/// The number of bisection steps of the inverse mapping of a spline, which narrows the angle down
+///  to far below the resolution of a servo.
+const INVERSE_STEPS: u32 = 64_u32;
+
+/// Represents the possible errors that can occur while creating a calibration table.
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// Represents a table with fewer than two points.
+    #[error("Point Count Error: {0} points, at least 2 are required")]
+    PointCountError(usize),
+    /// Represents a point with an angle or pulse width that is not finite.
+    #[error("Point Error: angle {angle} and pulse width {pulse_width} must be finite")]
+    PointError { angle: f64, pulse_width: f64 },
+    /// Represents two points with the same angle.
+    #[error("Angle Error: angle {0} is given more than once")]
+    AngleError(f64),
+    /// Represents a table whose pulse widths do not strictly increase or strictly decrease with
+    ///  the angle, so the mapping cannot be inverted.
+    #[error(
+        "Monotonicity Error: pulse width {pulse_width} at angle {angle} breaks the monotonicity"
+    )]
+    MonotonicityError { angle: f64, pulse_width: f64 },
+}
+
+/// The interpolation between the points of a calibration table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Interpolation {
+    /// Connects the points with straight lines.
+    #[default]
+    Linear,
+    /// Connects the points with a monotone cubic (Fritsch-Carlson) spline, which is smooth at the
+    ///  points and, unlike a natural cubic spline, never overshoots between them, so the mapping
+    ///  stays invertible.
+    Spline,
+}
+
+/// Represents a calibration table of a servo, which maps angles to pulse widths through measured
+///  points, for servos that are not linear over their whole range.
+///
+/// Angles outside the range of the table are clamped to its first or last point.
+///
+/// With the `serde` feature, the table is (de)serialized as its `points` and `interpolation`, and
+///  validated while it is deserialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "CalibrationTable", into = "CalibrationTable")
+)]
+pub struct Calibration {
+    /// The points of the table as (angle, pulse width in microseconds), sorted by angle.
+    points: Vec<(f64, f64)>,
+    /// The tangents of the spline at the points, empty for linear interpolation.
+    tangents: Vec<f64>,
+    interpolation: Interpolation,
+}
+
+impl Calibration {
+    /// Creates a new `Calibration` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The measured points as (angle, pulse width in microseconds), in any order.
+    /// * `interpolation` - The interpolation between the points.
+    ///
+    /// # Returns
+    ///
+    /// A new `Calibration` instance, or an `Error` if there are fewer than two points, a point
+    ///  is not finite, an angle is given more than once, or the pulse widths are not strictly
+    ///  monotonic in the angle.
+    pub fn new(points: &[(f64, f64)], interpolation: Interpolation) -> Result<Self, Error> {
+        if points.len() < 2 {
+            return Err(Error::PointCountError(points.len()));
+        }
+
+        if let Some(&(angle, pulse_width)) = points
+            .iter()
+            .find(|(angle, pulse_width)| !angle.is_finite() || !pulse_width.is_finite())
+        {
+            return Err(Error::PointError { angle, pulse_width });
+        }
+
+        let mut points = points.to_vec();
+        points.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        // The direction of the first segment sets the direction of the whole table.
+        let increasing = points[1].1 > points[0].1;
+
+        for pair in points.windows(2) {
+            let [(angle0, pulse_width0), (angle1, pulse_width1)] = [pair[0], pair[1]];
+
+            if angle0 == angle1 {
+                return Err(Error::AngleError(angle1));
+            }
+
+            if pulse_width1 == pulse_width0 || (pulse_width1 > pulse_width0) != increasing {
+                return Err(Error::MonotonicityError {
+                    angle: angle1,
+                    pulse_width: pulse_width1,
+                });
+            }
+        }
+
+        let tangents = match interpolation {
+            Interpolation::Linear => Vec::new(),
+            Interpolation::Spline => Self::tangents(&points),
+        };
+
+        Ok(Self {
+            points,
+            tangents,
+            interpolation,
+        })
+    }
+
+    /// Gets the points of the table as (angle, pulse width in microseconds), sorted by angle.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Gets the interpolation between the points.
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Gets the angle of the first point of the table.
+    pub fn start_angle(&self) -> f64 {
+        self.points[0].0
+    }
+
+    /// Gets the angle of the last point of the table.
+    pub fn end_angle(&self) -> f64 {
+        self.points[self.points.len() - 1].0
+    }
+
+    /// Computes the pulse width for the given angle.
+    ///
+    /// # Arguments
+    ///
+    /// * `angle` - The angle, clamped to the range of the table.
+    ///
+    /// # Returns
+    ///
+    /// The pulse width in microseconds.
+    pub fn pulse_width(&self, angle: f64) -> f64 {
+        let angle = angle.clamp(self.start_angle(), self.end_angle());
+        let segment = self.segment(|(point_angle, _)| point_angle, angle);
+
+        self.interpolate(segment, angle)
+    }
+
+    /// Computes the angle for the given pulse width, which is the inverse of `pulse_width`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pulse_width` - The pulse width in microseconds, clamped to the range of the table.
+    ///
+    /// # Returns
+    ///
+    /// The angle.
+    pub fn angle(&self, pulse_width: f64) -> f64 {
+        let first = self.points[0].1;
+        let last = self.points[self.points.len() - 1].1;
+
+        // Flip decreasing tables, so the pulse widths increase along the table.
+        let sign = if last > first { 1_f64 } else { -1_f64 };
+
+        let pulse_width = (sign * pulse_width).clamp(sign * first, sign * last);
+        let segment = self.segment(
+            |(_, point_pulse_width)| sign * point_pulse_width,
+            pulse_width,
+        );
+
+        let (angle0, pulse_width0) = self.points[segment];
+        let (angle1, pulse_width1) = self.points[segment + 1];
+
+        match self.interpolation {
+            Interpolation::Linear => {
+                let t =
+                    (pulse_width - sign * pulse_width0) / (sign * (pulse_width1 - pulse_width0));
+                angle0 + t * (angle1 - angle0)
+            }
+            Interpolation::Spline => {
+                // The spline is monotonic within the segment, so bisect it.
+                let (mut low, mut high) = (angle0, angle1);
+
+                for _ in 0..INVERSE_STEPS {
+                    let middle = (low + high) / 2_f64;
+
+                    if sign * self.interpolate(segment, middle) < pulse_width {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+
+                (low + high) / 2_f64
+            }
+        }
+    }
+
+    /// Finds the segment of the table that contains the given value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Gets the value of a point, which must increase along the table.
+    /// * `value` - The value to find, within the range of the table.
+    ///
+    /// # Returns
+    ///
+    /// The index of the first point of the segment.
+    fn segment(&self, key: impl Fn((f64, f64)) -> f64, value: f64) -> usize {
+        let index = self.points[1..].partition_point(|&point| key(point) < value);
+
+        index.min(self.points.len() - 2)
+    }
+
+    /// Interpolates the pulse width for an angle within the given segment.
+    fn interpolate(&self, segment: usize, angle: f64) -> f64 {
+        let (angle0, pulse_width0) = self.points[segment];
+        let (angle1, pulse_width1) = self.points[segment + 1];
+
+        let h = angle1 - angle0;
+        let t = (angle - angle0) / h;
+
+        match self.interpolation {
+            Interpolation::Linear => pulse_width0 + t * (pulse_width1 - pulse_width0),
+            Interpolation::Spline => {
+                // Evaluate the cubic Hermite polynomial of the segment.
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                (2_f64 * t3 - 3_f64 * t2 + 1_f64) * pulse_width0
+                    + (t3 - 2_f64 * t2 + t) * h * self.tangents[segment]
+                    + (-2_f64 * t3 + 3_f64 * t2) * pulse_width1
+                    + (t3 - t2) * h * self.tangents[segment + 1]
+            }
+        }
+    }
+
+    /// Computes the tangents of a monotone cubic spline through the points, following the
+    ///  Fritsch-Carlson method.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points, sorted by angle, with strictly monotonic pulse widths.
+    ///
+    /// # Returns
+    ///
+    /// The tangent at every point.
+    fn tangents(points: &[(f64, f64)]) -> Vec<f64> {
+        let secants: Vec<_> = points
+            .windows(2)
+            .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+            .collect();
+
+        // Start with the average of the secants around every point.
+        let mut tangents = Vec::with_capacity(points.len());
+        tangents.push(secants[0]);
+
+        for pair in secants.windows(2) {
+            tangents.push((pair[0] + pair[1]) / 2_f64);
+        }
+
+        tangents.push(secants[secants.len() - 1]);
+
+        // Limit the tangents, so the spline does not overshoot within any segment.
+        for (index, &secant) in secants.iter().enumerate() {
+            let alpha = tangents[index] / secant;
+            let beta = tangents[index + 1] / secant;
+            let magnitude = alpha.hypot(beta);
+
+            if magnitude > 3_f64 {
+                tangents[index] = 3_f64 * alpha / magnitude * secant;
+                tangents[index + 1] = 3_f64 * beta / magnitude * secant;
+            }
+        }
+
+        tangents
+    }
+}
+
+/// The serialized form of a `Calibration`, which leaves out the tangents of the spline.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CalibrationTable {
+    points: Vec<(f64, f64)>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CalibrationTable> for Calibration {
+    type Error = Error;
+
+    fn try_from(table: CalibrationTable) -> Result<Self, Self::Error> {
+        Calibration::new(&table.points, table.interpolation)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Calibration> for CalibrationTable {
+    fn from(calibration: Calibration) -> Self {
+        Self {
+            points: calibration.points,
+            interpolation: calibration.interpolation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A servo that moves less per microsecond near its ends.
+    const POINTS: [(f64, f64); 5] = [
+        (-90_f64, 600_f64),
+        (-45_f64, 1080_f64),
+        (0_f64, 1500_f64),
+        (45_f64, 1920_f64),
+        (90_f64, 2400_f64),
+    ];
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            Calibration::new(&POINTS[..1], Interpolation::Linear),
+            Err(Error::PointCountError(1))
+        );
+        assert!(matches!(
+            Calibration::new(&[(0.0, 1500.0), (f64::NAN, 2000.0)], Interpolation::Linear),
+            Err(Error::PointError { .. })
+        ));
+        assert_eq!(
+            Calibration::new(&[(0.0, 1500.0), (0.0, 2000.0)], Interpolation::Linear),
+            Err(Error::AngleError(0.0))
+        );
+        assert_eq!(
+            Calibration::new(
+                &[(-90.0, 600.0), (0.0, 1500.0), (90.0, 1400.0)],
+                Interpolation::Spline
+            ),
+            Err(Error::MonotonicityError {
+                angle: 90.0,
+                pulse_width: 1400.0
+            })
+        );
+
+        // The points may be given in any order, and the pulse widths may decrease.
+        let calibration =
+            Calibration::new(&[(90.0, 600.0), (-90.0, 2400.0)], Interpolation::Linear).unwrap();
+        assert_eq!(calibration.start_angle(), -90.0);
+        assert_eq!(calibration.pulse_width(0.0), 1500.0);
+        assert_eq!(calibration.angle(1500.0), 0.0);
+    }
+
+    #[test]
+    fn test_linear() {
+        let calibration = Calibration::new(&POINTS, Interpolation::Linear).unwrap();
+
+        assert_eq!(calibration.pulse_width(-45.0), 1080.0);
+        assert_eq!(calibration.pulse_width(22.5), 1710.0);
+        assert_eq!(calibration.angle(1710.0), 22.5);
+
+        // Angles and pulse widths outside the table are clamped.
+        assert_eq!(calibration.pulse_width(120.0), 2400.0);
+        assert_eq!(calibration.angle(500.0), -90.0);
+    }
+
+    #[test]
+    fn test_spline() {
+        let calibration = Calibration::new(&POINTS, Interpolation::Spline).unwrap();
+
+        // The spline passes through the points, and increases monotonically between them.
+        for (angle, pulse_width) in POINTS {
+            assert!((calibration.pulse_width(angle) - pulse_width).abs() < 1e-9);
+        }
+
+        let mut previous = calibration.pulse_width(-90.0);
+
+        for step in 1..=180 {
+            let pulse_width = calibration.pulse_width(-90.0 + step as f64);
+            assert!(pulse_width > previous);
+            previous = pulse_width;
+        }
+
+        // The inverse mapping returns the angle the pulse width was computed for.
+        for step in 0..=36 {
+            let angle = -90.0 + step as f64 * 5.0;
+            assert!((calibration.angle(calibration.pulse_width(angle)) - angle).abs() < 1e-6);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod calibration;
 pub(crate) mod math;
+pub mod profile;
 pub mod settings;
 pub mod servo;
@@ -0,0 +1,271 @@
+use thiserror::Error;
+
+/// The peak acceleration of a minimum-jerk move, relative to `distance / duration^2`.
+const MINIMUM_JERK_PEAK_ACCELERATION: f64 = 5.773_502_691_896_258_f64;
+
+/// The peak jerk of a minimum-jerk move, relative to `distance / duration^3`.
+const MINIMUM_JERK_PEAK_JERK: f64 = 60_f64;
+
+/// Represents the possible errors of a motion profile.
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// Represents a limit that is zero, negative or NaN, which no move can respect.
+    #[error("Limit Error: {name} {value} must be positive")]
+    LimitError { name: &'static str, value: f64 },
+}
+
+/// The shape of the angle over time while a servo moves from one angle to another.
+///
+/// Accelerations are in degrees per second squared, and jerks in degrees per second cubed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MotionProfile {
+    /// Moves at a constant velocity, starting and stopping instantly.
+    #[default]
+    ConstantVelocity,
+    /// Accelerates at a constant rate, cruises at a constant velocity, and decelerates at a
+    ///  constant rate.
+    Trapezoidal { max_acceleration: f64 },
+    /// Follows a minimum-jerk (quintic) curve, which starts and stops with zero velocity and zero
+    ///  acceleration.
+    SCurve {
+        max_acceleration: f64,
+        max_jerk: f64,
+    },
+}
+
+impl MotionProfile {
+    /// Checks that the limits of the profile are positive. An infinite limit does not limit the
+    ///  move at all.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if moves can follow the profile, otherwise returns an `Error`.
+    pub fn validate(&self) -> Result<(), Error> {
+        let limits: &[(&'static str, f64)] = match *self {
+            MotionProfile::ConstantVelocity => &[],
+            MotionProfile::Trapezoidal { max_acceleration } => {
+                &[("max_acceleration", max_acceleration)]
+            }
+            MotionProfile::SCurve {
+                max_acceleration,
+                max_jerk,
+            } => &[
+                ("max_acceleration", max_acceleration),
+                ("max_jerk", max_jerk),
+            ],
+        };
+
+        match limits
+            .iter()
+            .find(|(_, value)| value.is_nan() || *value <= 0_f64)
+        {
+            Some(&(name, value)) => Err(Error::LimitError { name, value }),
+            None => Ok(()),
+        }
+    }
+
+    /// Computes the duration of a move, which is the requested duration stretched to the shortest
+    ///  duration that respects the limits of the profile. The profile must have been checked with
+    ///  `validate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - The distance to move in degrees.
+    /// * `duration` - The requested duration in seconds.
+    ///
+    /// # Returns
+    ///
+    /// The duration of the move in seconds.
+    pub fn duration(&self, distance: f64, duration: f64) -> f64 {
+        let distance = distance.abs();
+
+        let minimum_duration = match *self {
+            MotionProfile::ConstantVelocity => 0_f64,
+            MotionProfile::Trapezoidal { max_acceleration } => {
+                // A triangular profile is the fastest way to cover the distance.
+                2_f64 * (distance / max_acceleration).sqrt()
+            }
+            MotionProfile::SCurve {
+                max_acceleration,
+                max_jerk,
+            } => {
+                let acceleration_limited =
+                    (MINIMUM_JERK_PEAK_ACCELERATION * distance / max_acceleration).sqrt();
+                let jerk_limited = (MINIMUM_JERK_PEAK_JERK * distance / max_jerk).cbrt();
+
+                acceleration_limited.max(jerk_limited)
+            }
+        };
+
+        duration.max(minimum_duration)
+    }
+
+    /// Computes how far a move has progressed at the given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - The distance to move in degrees.
+    /// * `duration` - The duration of the move in seconds, as returned by `duration`.
+    /// * `time` - The time since the start of the move in seconds.
+    ///
+    /// # Returns
+    ///
+    /// The progress of the move, from 0 at the start to 1 at the end.
+    pub fn progress(&self, distance: f64, duration: f64, time: f64) -> f64 {
+        let distance = distance.abs();
+
+        if distance == 0_f64 || duration <= 0_f64 || time >= duration {
+            return 1_f64;
+        }
+
+        if time <= 0_f64 {
+            return 0_f64;
+        }
+
+        let tau = time / duration;
+
+        let progress = match *self {
+            MotionProfile::Trapezoidal { max_acceleration } if max_acceleration.is_finite() => {
+                // Solve `distance = velocity * (duration - velocity / acceleration)` for the
+                //  cruise velocity, taking the slowest solution.
+                let a = max_acceleration;
+                let discriminant = (a * a * duration * duration - 4_f64 * a * distance).max(0_f64);
+                let velocity = (a * duration - discriminant.sqrt()) / 2_f64;
+                let ramp = velocity / a;
+
+                let position = if time < ramp {
+                    a * time * time / 2_f64
+                } else if time < duration - ramp {
+                    velocity * ramp / 2_f64 + velocity * (time - ramp)
+                } else {
+                    let remaining = duration - time;
+                    distance - a * remaining * remaining / 2_f64
+                };
+
+                position / distance
+            }
+            MotionProfile::SCurve { .. } => {
+                tau * tau * tau * (10_f64 - 15_f64 * tau + 6_f64 * tau * tau)
+            }
+            _ => tau,
+        };
+
+        progress.clamp(0_f64, 1_f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration() {
+        let trapezoidal = MotionProfile::Trapezoidal {
+            max_acceleration: 100_f64,
+        };
+
+        assert_eq!(
+            MotionProfile::ConstantVelocity.duration(90_f64, 1_f64),
+            1_f64
+        );
+        assert_eq!(trapezoidal.duration(100_f64, 3_f64), 3_f64);
+        assert_eq!(trapezoidal.duration(-100_f64, 1_f64), 2_f64);
+
+        let s_curve = MotionProfile::SCurve {
+            max_acceleration: f64::INFINITY,
+            max_jerk: 60_f64,
+        };
+
+        assert!((s_curve.duration(8_f64, 0_f64) - 2_f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(MotionProfile::ConstantVelocity.validate().is_ok());
+        assert!(MotionProfile::SCurve {
+            max_acceleration: f64::INFINITY,
+            max_jerk: 60_f64,
+        }
+        .validate()
+        .is_ok());
+
+        assert_eq!(
+            MotionProfile::Trapezoidal {
+                max_acceleration: 0_f64
+            }
+            .validate(),
+            Err(Error::LimitError {
+                name: "max_acceleration",
+                value: 0_f64
+            })
+        );
+        assert_eq!(
+            MotionProfile::SCurve {
+                max_acceleration: 100_f64,
+                max_jerk: -1_f64,
+            }
+            .validate(),
+            Err(Error::LimitError {
+                name: "max_jerk",
+                value: -1_f64
+            })
+        );
+        assert!(MotionProfile::Trapezoidal {
+            max_acceleration: f64::NAN
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let profiles = [
+            MotionProfile::ConstantVelocity,
+            MotionProfile::Trapezoidal {
+                max_acceleration: 100_f64,
+            },
+            MotionProfile::SCurve {
+                max_acceleration: 100_f64,
+                max_jerk: 1000_f64,
+            },
+        ];
+
+        for profile in profiles {
+            let duration = profile.duration(90_f64, 1_f64);
+
+            assert_eq!(profile.progress(90_f64, duration, 0_f64), 0_f64);
+            assert!((profile.progress(90_f64, duration, duration / 2_f64) - 0.5).abs() < 1e-9);
+            assert_eq!(profile.progress(90_f64, duration, duration), 1_f64);
+
+            // The progress never decreases.
+            let mut previous = 0_f64;
+
+            for step in 0..=100 {
+                let progress = profile.progress(90_f64, duration, duration * step as f64 / 100_f64);
+                assert!(progress >= previous);
+                previous = progress;
+            }
+        }
+    }
+
+    #[test]
+    fn test_trapezoidal_respects_acceleration() {
+        let profile = MotionProfile::Trapezoidal {
+            max_acceleration: 100_f64,
+        };
+
+        let duration = profile.duration(50_f64, 1_f64);
+        let dt = 1e-3_f64;
+
+        // The second difference of the position approximates the acceleration.
+        for step in 1..1000 {
+            let t = step as f64 * dt;
+            let position = |t: f64| 50_f64 * profile.progress(50_f64, duration, t);
+
+            let acceleration =
+                (position(t + dt) - 2_f64 * position(t) + position(t - dt)) / (dt * dt);
+
+            assert!(acceleration.abs() <= 100_f64 + 1e-3);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub mod reader;
 pub mod writer;
 
+use embedded_hal::i2c::I2c;
+
 use crate::settings::ServoSettings;
 
 use self::{reader::ServoReader, writer::ServoWriter};
@@ -8,14 +10,25 @@ use self::{reader::ServoReader, writer::ServoWriter};
 pub struct Servo;
 
 impl Servo {
-    pub async fn new(channel: pca9685::Channel, settings: ServoSettings, initial_angle: f64) -> Result<(ServoWriter, ServoReader), writer::Error> {
+    #[allow(clippy::new_ret_no_self)]
+    pub async fn new<I2C: I2c>(
+        channel: pca9685::Channel<I2C>,
+        settings: ServoSettings,
+        initial_angle: f64,
+    ) -> Result<(ServoWriter<I2C>, ServoReader), writer::Error> {
+        // Refuse settings that cannot map the angles to pulse widths, before writing anything.
+        settings.validate()?;
+
         let (angle_sender, angle_receiver) = tokio::sync::watch::channel(initial_angle);
 
-        let mut servo_writer = ServoWriter::new(channel, settings, angle_sender);
+        // Update moving servos once every PWM period the driver actually runs at.
+        let update_interval = channel.driver().lock().await.period();
+
+        let mut servo_writer = ServoWriter::new(channel, settings, update_interval, angle_sender);
         let servo_reader = ServoReader::new(angle_receiver);
 
         servo_writer.write(initial_angle).await?;
 
         Ok((servo_writer, servo_reader))
     }
-}
\ No newline at end of file
+}
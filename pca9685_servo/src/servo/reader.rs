@@ -16,7 +16,7 @@ impl ServoReader {
     }
 
     pub async fn wait_for_angle_to_change(&mut self) -> Result<(), Error> {
-        if let Err(_) = self.angle_receiver.changed().await {
+        if self.angle_receiver.changed().await.is_err() {
             return Err(Error::ReceiverClosed);
         }
 
@@ -1,125 +1,241 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
+use embedded_hal::i2c::I2c;
 use thiserror::Error;
-use tokio::time::sleep;
+use tokio::{
+    select,
+    time::{interval, Instant, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, instrument, trace};
 
-use crate::{math::compute_duty_cycle, settings::ServoSettings};
+use crate::{
+    profile::{self, MotionProfile},
+    settings::{self, ServoSettings},
+};
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("PCA9685 Error: {0}")]
     PCA9685Error(#[from] pca9685::Error),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Profile Error: {0}")]
+    ProfileError(#[from] profile::Error),
+    #[error("Duration Error: duration {0} must be finite and non-negative")]
+    DurationError(f64),
+    #[error("Settings Error: {0}")]
+    SettingsError(#[from] settings::Error),
 }
 
-pub struct ServoWriter {
-    channel: pca9685::Channel,
+pub struct ServoWriter<I2C> {
+    channel: pca9685::Channel<I2C>,
     settings: ServoSettings,
+    update_interval: Duration,
     angle_sender: tokio::sync::watch::Sender<f64>,
 }
 
-impl ServoWriter {
+impl<I2C: I2c> ServoWriter<I2C> {
     pub(crate) fn new(
-        channel: pca9685::Channel,
+        channel: pca9685::Channel<I2C>,
         settings: ServoSettings,
+        update_interval: Duration,
         angle_sender: tokio::sync::watch::Sender<f64>,
     ) -> Self {
         Self {
             channel,
             settings,
+            update_interval,
             angle_sender,
         }
     }
 
+    /// Gets the settings of the servo.
+    pub fn settings(&self) -> &ServoSettings {
+        &self.settings
+    }
+
+    /// Gets the interval between sequential updates of a moving servo, which is the actual PWM
+    ///  period of the driver of its channel, so every update is output in its own period.
+    pub fn update_interval(&self) -> Duration {
+        self.update_interval
+    }
+
+    /// Gets the angle the servo was last written to.
+    pub fn angle(&self) -> f64 {
+        *self.angle_sender.borrow()
+    }
+
+    /// Writes the servo to a desired angle at a constant velocity, over a specified duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_angle` - The desired angle to set the servo to.
+    /// * `duration` - The duration of the movement in seconds.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the servo is successfully written to the desired angle,
+    /// otherwise returns an `Error` indicating the failure.
     pub async fn write_with_duration(
         &mut self,
         target_angle: f64,
         duration: f64,
     ) -> Result<(), Error> {
-        // Get the current angle.
-        let current_angle = *self.angle_sender.borrow();
-
-        // Compute the speed to make the movement last the gicen duration.
-        let speed = (target_angle - current_angle).abs() / duration;
+        self.write_with_profile(target_angle, duration, MotionProfile::ConstantVelocity)
+            .await
+    }
 
-        // Write the angle with the computed speed.
-        self.write_with_speed(target_angle, speed).await?;
+    /// Writes the servo to a desired angle at a constant speed.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_angle` - The desired angle to set the servo to.
+    /// * `speed` - The speed at which the servo should move in degrees per second. A speed that
+    ///   is not positive and finite moves the servo instantly.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the servo is successfully written to the desired angle,
+    /// otherwise returns an `Error` indicating the failure.
+    pub async fn write_with_speed(&mut self, target_angle: f64, speed: f64) -> Result<(), Error> {
+        // Compute the duration to make the movement happen at the given speed.
+        let duration = if speed.is_finite() && speed > 0_f64 {
+            (target_angle - self.angle()).abs() / speed
+        } else {
+            0_f64
+        };
 
-        // Return success.
-        Ok(())
+        self.write_with_duration(target_angle, duration).await
     }
 
-    /// Writes the servo to a desired angle with a specified speed.
+    /// Writes the servo to a desired angle following a motion profile.
     ///
-    /// This method calculates the necessary steps to reach the desired angle
-    /// based on the servo's settings and the given speed. It then iterates
-    /// through the steps, gradually updating the servo's angle until it reaches
-    /// the desired angle. The speed parameter determines the interval between
-    /// sequential updates.
+    /// The angle is sampled from the profile every `update_interval`, based on the time elapsed
+    /// since the start of the movement, so slow writes do not make the movement last longer.
     ///
     /// # Arguments
     ///
     /// * `target_angle` - The desired angle to set the servo to.
-    /// * `speed` - The speed at which the servo should move to the desired angle.
+    /// * `duration` - The duration of the movement in seconds. It is stretched if the movement
+    ///   cannot be made within the limits of the profile, see `MotionProfile::duration`.
+    /// * `profile` - The shape of the movement.
     ///
     /// # Returns
     ///
     /// Returns `Ok(())` if the servo is successfully written to the desired angle,
     /// otherwise returns an `Error` indicating the failure.
-    pub async fn write_with_speed(&mut self, target_angle: f64, speed: f64) -> Result<(), Error> {
-        // Destructure the settings for easier access.
-        let ServoSettings {
-            start_duty_cycle,
-            end_duty_cycle,
-            start_angle,
-            end_angle,
-        } = self.settings;
-
-        // Get the current angle.
-        let mut current_angle = *self.angle_sender.borrow();
-
-        // Calculate the range of the servo's angle (the range of the servo in radians).
-        let servo_angle_range = end_angle - start_angle;
-
-        // Calculate the steps within the duty cycle.
-        let steps_within_duty_cycle = (end_duty_cycle - start_duty_cycle) * 4095.0;
-
-        // Calculate the step size (this gives the size of a single step in radians).
-        let step_size = servo_angle_range / steps_within_duty_cycle;
-
-        // Compute the update frequency, and use it to compute the interval between sequential updates.
-        let frequency = speed / step_size;
-        let interval = 1.0 / frequency;
-
-        // Convert the interval into a duration for sleeping.
-        let sleep_duration = Duration::from_secs_f64(interval);
-
-        // Compute the number of iterations we need to perform to reach the desired angle.
-        let iterations = ((target_angle - current_angle).abs() / step_size).round() as usize;
-
-        // Perform the iterations to reach the desired angle.
-        for i in 0..iterations {
-            // Compute the new current angle.
-            current_angle = if target_angle > current_angle {
-                current_angle + i as f64 * step_size
-            } else {
-                current_angle - i as f64 * step_size
-            };
-
-            // Write the current angle.
-            self.write(current_angle).await?;
-
-            // Sleep for the computed amount of time.
-            sleep(sleep_duration).await;
+    pub async fn write_with_profile(
+        &mut self,
+        target_angle: f64,
+        duration: f64,
+        profile: MotionProfile,
+    ) -> Result<(), Error> {
+        self.write_with_profile_cancellable(
+            target_angle,
+            duration,
+            profile,
+            &CancellationToken::new(),
+        )
+        .await
+    }
+
+    /// Writes the servo to a desired angle following a motion profile, until the movement is
+    /// cancelled.
+    ///
+    /// When the token is cancelled, the servo stops at the angle it was last written to, so a
+    /// following movement starts from there.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_angle` - The desired angle to set the servo to.
+    /// * `duration` - The duration of the movement in seconds, finite and non-negative.
+    /// * `profile` - The shape of the movement, with valid limits.
+    /// * `cancellation_token` - The token to cancel the movement with.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the servo is successfully written to the desired angle,
+    /// `Error::Cancelled` if the movement was cancelled, `Error::ProfileError` or
+    /// `Error::DurationError` if the movement is invalid, otherwise returns an `Error`
+    /// indicating the failure.
+    #[instrument(
+        level = "debug",
+        skip(self, cancellation_token),
+        fields(channel = self.channel.channel())
+    )]
+    pub async fn write_with_profile_cancellable(
+        &mut self,
+        target_angle: f64,
+        duration: f64,
+        profile: MotionProfile,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), Error> {
+        profile.validate()?;
+
+        if !duration.is_finite() || duration < 0_f64 {
+            return Err(Error::DurationError(duration));
         }
 
-        // Return success.
-        Ok(())
+        if cancellation_token.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        // Get the current angle and the distance to move.
+        let start_angle = self.angle();
+        let distance = target_angle - start_angle;
+
+        // If the servo already is at the desired angle, there is nothing to do.
+        if distance == 0_f64 {
+            return Ok(());
+        }
+
+        let duration = profile.duration(distance, duration);
+
+        debug!(start_angle, duration, "moving");
+
+        if !duration.is_finite() {
+            return Err(Error::DurationError(duration));
+        }
+
+        // Move instantly if there is no time to move gradually.
+        if duration == 0_f64 {
+            return self.write(target_angle).await;
+        }
+
+        let started_at = Instant::now();
+
+        let mut interval = interval(self.update_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            select! {
+                _ = interval.tick() => {}
+                _ = cancellation_token.cancelled() => {
+                    debug!(angle = self.angle(), "move cancelled");
+                    return Err(Error::Cancelled);
+                }
+            }
+
+            let time = started_at.elapsed().as_secs_f64();
+
+            if time >= duration {
+                break;
+            }
+
+            let progress = profile.progress(distance, duration, time);
+            self.write(start_angle + distance * progress).await?;
+        }
+
+        // Always end exactly at the desired angle.
+        self.write(target_angle).await
     }
 
     /// Writes the servo to a desired angle.
     ///
-    /// This method calculates the duty cycle based on the servo's settings and
-    /// the desired angle. It then writes the duty cycle to the servo's channel.
+    /// This method calculates the pulse width based on the servo's settings and
+    /// the desired angle. It then writes the pulse width to the servo's channel, which converts
+    /// it using the actual period of the driver.
     ///
     /// # Arguments
     ///
@@ -130,30 +246,312 @@ impl ServoWriter {
     /// Returns `Ok(())` if the servo is successfully written to the desired angle,
     /// otherwise returns an `Error` indicating the failure.
     pub async fn write(&mut self, angle: f64) -> Result<(), Error> {
-        // Get the required parameters from the settings.
-        let ServoSettings {
-            start_duty_cycle,
-            end_duty_cycle,
-            start_angle,
-            end_angle,
-        } = self.settings;
-
-        // Compute the duty cycle to write based on the settings and the desired angle.
-        let duty_cycle = compute_duty_cycle(
-            start_duty_cycle,
-            end_duty_cycle,
-            start_angle,
-            end_angle,
+        // Compute the pulse width to write based on the settings and the desired angle.
+        let pulse_width = self.pulse_width(angle);
+
+        trace!(
+            channel = self.channel.channel(),
             angle,
+            pulse_width,
+            "writing servo"
         );
 
-        // Write the duty cycle to the channel.
-        self.channel.write_duty_cycle(duty_cycle).await?;
+        // Write the pulse width to the channel.
+        self.channel.write_pulse_width(pulse_width).await?;
 
-        // Update the current angle.
-        _ = self.angle_sender.send(angle);
+        // Update the current angle, even if nobody is reading it, since following movements
+        //  start from it.
+        self.angle_sender.send_replace(angle);
 
         // Return success.
         Ok(())
     }
+
+    /// Writes multiple servos to their desired angles at once, as a single frame.
+    ///
+    /// The servos are grouped by the driver of their channel, and the channels of every driver
+    ///  are written with a single `Driver::write_channels_pulse_width` call. Only servos on
+    ///  consecutive channels of a driver are guaranteed to move in the same PWM period, since a
+    ///  gap between the channels splits the write into separate I2C transactions.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The servos and the desired angle of every servo.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the servos are successfully written to the desired angles,
+    /// otherwise returns an `Error` indicating the failure.
+    pub async fn write_frame(frame: &mut [(&mut ServoWriter<I2C>, f64)]) -> Result<(), Error> {
+        let mut written = vec![false; frame.len()];
+
+        for first in 0..frame.len() {
+            if written[first] {
+                continue;
+            }
+
+            let driver = frame[first].0.channel.driver().clone();
+
+            // Collect the channels of all the servos on the same driver.
+            let mut channels = Vec::new();
+
+            for (index, (servo, angle)) in frame.iter().enumerate().skip(first) {
+                if !written[index] && Arc::ptr_eq(servo.channel.driver(), &driver) {
+                    let channel = servo.channel.channel();
+                    let pulse_width = servo.pulse_width(*angle);
+
+                    trace!(channel, angle, pulse_width, "writing servo");
+
+                    channels.push((channel, pulse_width));
+                    written[index] = true;
+                }
+            }
+
+            driver
+                .lock()
+                .await
+                .write_channels_pulse_width(&channels)
+                .await?;
+        }
+
+        // Update the current angles.
+        for (servo, angle) in frame.iter() {
+            servo.angle_sender.send_replace(*angle);
+        }
+
+        // Return success.
+        Ok(())
+    }
+
+    /// Computes the pulse width in microseconds for the given angle, based on the servo's settings.
+    fn pulse_width(&self, angle: f64) -> f64 {
+        self.settings.pulse_width(angle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pca9685::{
+        device::Device,
+        sim::{Bus, Pin},
+        Channel, Driver,
+    };
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::servo::Servo;
+
+    const ADDRESS: u8 = 0x40;
+
+    async fn build_servo(bus: &Bus) -> ServoWriter<Bus> {
+        build_servos(bus, 1).await.pop().unwrap()
+    }
+
+    async fn build_servos(bus: &Bus, count: u8) -> Vec<ServoWriter<Bus>> {
+        let driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .with_oe_pin(Pin::new())
+            .build()
+            .await
+            .unwrap();
+
+        let driver = Arc::new(Mutex::new(driver));
+        let mut writers = Vec::new();
+
+        for channel in 0..count {
+            let channel = Channel::new(driver.clone(), channel);
+
+            let (writer, _) = Servo::new(channel, ServoSettings::new(), 0_f64)
+                .await
+                .unwrap();
+
+            writers.push(writer);
+        }
+
+        writers
+    }
+
+    #[tokio::test]
+    async fn test_write_frame() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut servos = build_servos(&bus, 3).await;
+
+        let mut frame: Vec<_> = servos
+            .iter_mut()
+            .zip([-90_f64, 0_f64, 90_f64])
+            .collect();
+
+        ServoWriter::write_frame(&mut frame).await.unwrap();
+
+        let chip = bus.chip(ADDRESS).unwrap();
+        let period = servos[0].channel.driver().lock().await.period();
+
+        for (channel, expected) in [500_f64, 1500_f64, 2500_f64].into_iter().enumerate() {
+            let pulse_width = chip.channel(channel as u8).duty_cycle() * period.as_micros() as f64;
+            assert!((pulse_width - expected).abs() < 20_f64);
+        }
+
+        assert_eq!(servos[2].angle(), 90_f64);
+    }
+
+    #[tokio::test]
+    async fn test_write_with_profile() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut writer = build_servo(&bus).await;
+
+        writer
+            .write_with_profile(
+                90_f64,
+                0.2_f64,
+                MotionProfile::Trapezoidal {
+                    max_acceleration: 3600_f64,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(writer.angle(), 90_f64);
+
+        let period = writer.channel.driver().lock().await.period();
+        let pulse_width =
+            bus.chip(ADDRESS).unwrap().channel(0).duty_cycle() * period.as_micros() as f64;
+        assert!((pulse_width - ServoSettings::DEFAULT_END_PULSE_WIDTH).abs() < 20_f64);
+    }
+
+    #[tokio::test]
+    async fn test_write_at_update_rates() {
+        for update_rate in [50, 60, 330] {
+            let bus = Bus::with_chip(ADDRESS);
+
+            let driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+                .with_update_rate(update_rate)
+                .build()
+                .await
+                .unwrap();
+
+            let tick_resolution = driver.tick_resolution();
+            let period = driver.period();
+            let channel = Channel::new(Arc::new(Mutex::new(driver)), 0);
+
+            let (mut writer, _) = Servo::new(channel, ServoSettings::new(), 0_f64)
+                .await
+                .unwrap();
+
+            // A moving servo is updated once every period of the driver.
+            assert_eq!(writer.update_interval(), period);
+
+            // The same settings give the same pulse widths at any update rate.
+            for (angle, expected) in [(-90_f64, 500_f64), (0_f64, 1500_f64), (90_f64, 2500_f64)] {
+                writer.write(angle).await.unwrap();
+
+                let state = bus.chip(ADDRESS).unwrap().channel(0);
+                let pulse_width = (state.off - state.on) as f64 * tick_resolution;
+
+                assert!((pulse_width - expected).abs() <= 2_f64 * tick_resolution);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_with_invalid_profile() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut writer = build_servo(&bus).await;
+
+        // The strictest limit is rejected, rather than moving instantly.
+        let result = writer
+            .write_with_profile(
+                90_f64,
+                0.2_f64,
+                MotionProfile::Trapezoidal {
+                    max_acceleration: 0_f64,
+                },
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::ProfileError(profile::Error::LimitError { .. }))
+        ));
+
+        for duration in [f64::NAN, f64::INFINITY, -1_f64] {
+            assert!(matches!(
+                writer.write_with_duration(90_f64, duration).await,
+                Err(Error::DurationError(_))
+            ));
+        }
+
+        assert_eq!(writer.angle(), 0_f64);
+    }
+
+    #[tokio::test]
+    async fn test_new_with_invalid_settings() {
+        let bus = Bus::with_chip(ADDRESS);
+
+        let driver = Driver::builder(Device::new(bus.clone(), ADDRESS))
+            .build()
+            .await
+            .unwrap();
+
+        let channel = Channel::new(Arc::new(Mutex::new(driver)), 0);
+        let state = bus.chip(ADDRESS).unwrap().channel(0);
+
+        // The equal angles would divide by zero, so nothing is written to the channel.
+        let settings = ServoSettings::new()
+            .with_start_angle(0_f64)
+            .with_end_angle(0_f64);
+        let result = Servo::new(channel, settings, 0_f64).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::SettingsError(settings::Error::RangeError(_)))
+        ));
+        assert_eq!(bus.chip(ADDRESS).unwrap().channel(0), state);
+    }
+
+    #[tokio::test]
+    async fn test_write_with_zero_duration() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut writer = build_servo(&bus).await;
+
+        writer.write_with_duration(45_f64, 0_f64).await.unwrap();
+        assert_eq!(writer.angle(), 45_f64);
+
+        writer.write_with_speed(-45_f64, 0_f64).await.unwrap();
+        assert_eq!(writer.angle(), -45_f64);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_with_profile_cancellable() {
+        let bus = Bus::with_chip(ADDRESS);
+        let mut writer = build_servo(&bus).await;
+
+        let cancellation_token = CancellationToken::new();
+
+        let cancel = {
+            let cancellation_token = cancellation_token.clone();
+
+            async move {
+                // Between the updates at 80 ms and 100 ms, with the clock paused.
+                tokio::time::sleep(Duration::from_millis(90)).await;
+                cancellation_token.cancel();
+            }
+        };
+
+        let (result, _) = tokio::join!(
+            writer.write_with_profile_cancellable(
+                90_f64,
+                0.2_f64,
+                MotionProfile::ConstantVelocity,
+                &cancellation_token,
+            ),
+            cancel,
+        );
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        // The servo stops at the angle of the last update, and a new movement starts from there.
+        let angle = writer.angle();
+        assert!((angle - 36_f64).abs() < 1e-9, "{}", angle);
+
+        writer.write_with_duration(0_f64, 0_f64).await.unwrap();
+        assert_eq!(writer.angle(), 0_f64);
+    }
 }
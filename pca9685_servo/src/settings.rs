@@ -1,21 +1,74 @@
+use thiserror::Error;
+
+use crate::{
+    calibration::Calibration,
+    math::{compute_pulse_width, map},
+};
+
+/// Represents the possible errors of servo settings that cannot map angles to pulse widths.
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    /// Represents an angle or pulse width that is not finite.
+    #[error("Value Error: {name} {value} must be finite")]
+    ValueError { name: &'static str, value: f64 },
+    /// Represents a starting angle equal to the ending angle, which leaves no range to map.
+    #[error("Range Error: start_angle and end_angle {0} must differ")]
+    RangeError(f64),
+    /// Represents a pulse width that is not positive.
+    #[error("Pulse Width Error: {name} {value} must be positive")]
+    PulseWidthError { name: &'static str, value: f64 },
+}
+
 /// Represents the settings for a servo.
+///
+/// The servo is calibrated with the widths of the pulses at its starting and ending angle, in
+///  microseconds, rather than with duty cycles, so the same settings hold at any update rate.
+///  Servos that are not linear over their range can be calibrated with a table of measured points
+///  instead.
+///
+/// With the `serde` feature, the settings are (de)serialized with the field names of the
+///  builder, all of which are optional. The limits are left out, since they belong to the joint
+///  the servo moves rather than to its calibration.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ServoSettingsTable")
+)]
 pub struct ServoSettings {
     /// The starting angle of the servo.
     pub(crate) start_angle: f64,
     /// The ending angle of the servo.
     pub(crate) end_angle: f64,
-    /// The starting duty cycle of the servo.
-    pub(crate) start_duty_cycle: f64,
-    /// The ending duty cycle of the servo.
-    pub(crate) end_duty_cycle: f64,
+    /// The width of the pulses at the starting angle, in microseconds.
+    pub(crate) start_pulse_width: f64,
+    /// The width of the pulses at the ending angle, in microseconds.
+    pub(crate) end_pulse_width: f64,
+    /// The calibration table, which replaces the linear mapping between the starting and ending
+    ///  angle when set.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub(crate) calibration: Option<Calibration>,
+    /// The minimum and maximum angle the servo may be moved to, within its range.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) limits: Option<(f64, f64)>,
 }
 
 impl ServoSettings {
     pub const DEFAULT_START_ANGLE: f64 = -90_f64;
     pub const DEFAULT_END_ANGLE: f64 = 90_f64;
+    pub const DEFAULT_START_PULSE_WIDTH: f64 = 500_f64;
+    pub const DEFAULT_END_PULSE_WIDTH: f64 = 2500_f64;
+
+    #[deprecated(note = "use `DEFAULT_START_PULSE_WIDTH` instead")]
     pub const DEFAULT_START_DUTY_CYCLE: f64 = 0.025_f64;
+    #[deprecated(note = "use `DEFAULT_END_PULSE_WIDTH` instead")]
     pub const DEFAULT_END_DUTY_CYCLE: f64 = 0.125_f64;
 
+    /// The period the duty cycles of `with_start_duty_cycle` and `with_end_duty_cycle` are
+    ///  converted with, in microseconds, which is the period at the standard servo update rate of
+    ///  50 Hz.
+    pub const DUTY_CYCLE_PERIOD: f64 = 20_000_f64;
+
     /// Creates a new `ServoSettings` instance with default values.
     ///
     /// # Returns
@@ -25,13 +78,17 @@ impl ServoSettings {
         Self {
             start_angle: Self::DEFAULT_START_ANGLE,
             end_angle: Self::DEFAULT_END_ANGLE,
-            start_duty_cycle: Self::DEFAULT_START_DUTY_CYCLE,
-            end_duty_cycle: Self::DEFAULT_END_DUTY_CYCLE,
+            start_pulse_width: Self::DEFAULT_START_PULSE_WIDTH,
+            end_pulse_width: Self::DEFAULT_END_PULSE_WIDTH,
+            calibration: None,
+            limits: None,
         }
     }
 
     /// Sets the starting angle of the servo and returns the modified `Settings` instance.
     ///
+    /// This clears the calibration table, if any, which would no longer match the range.
+    ///
     /// # Arguments
     ///
     /// * `start_angle`: The starting angle of the servo.
@@ -41,11 +98,14 @@ impl ServoSettings {
     /// The modified `Settings` instance.
     pub fn with_start_angle(mut self, start_angle: f64) -> Self {
         self.start_angle = start_angle;
+        self.calibration = None;
         self
     }
 
     /// Sets the ending angle of the servo and returns the modified `Settings` instance.
     ///
+    /// This clears the calibration table, if any, which would no longer match the range.
+    ///
     /// # Arguments
     ///
     /// * `end_angle`: The ending angle of the servo.
@@ -55,11 +115,51 @@ impl ServoSettings {
     /// The modified `Settings` instance.
     pub fn with_end_angle(mut self, end_angle: f64) -> Self {
         self.end_angle = end_angle;
+        self.calibration = None;
+        self
+    }
+
+    /// Sets the width of the pulses at the starting angle and returns the modified `Settings`
+    ///  instance.
+    ///
+    /// This clears the calibration table, if any, which would no longer match the pulse widths.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_pulse_width`: The width of the pulses at the starting angle, in microseconds.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Settings` instance.
+    pub fn with_start_pulse_width(mut self, start_pulse_width: f64) -> Self {
+        self.start_pulse_width = start_pulse_width;
+        self.calibration = None;
+        self
+    }
+
+    /// Sets the width of the pulses at the ending angle and returns the modified `Settings`
+    ///  instance.
+    ///
+    /// This clears the calibration table, if any, which would no longer match the pulse widths.
+    ///
+    /// # Arguments
+    ///
+    /// * `end_pulse_width`: The width of the pulses at the ending angle, in microseconds.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Settings` instance.
+    pub fn with_end_pulse_width(mut self, end_pulse_width: f64) -> Self {
+        self.end_pulse_width = end_pulse_width;
+        self.calibration = None;
         self
     }
 
     /// Sets the starting duty cycle of the servo and returns the modified `Settings` instance.
     ///
+    /// The duty cycle is converted into the width of the pulses at the starting angle with
+    ///  `DUTY_CYCLE_PERIOD`, so it only holds at an update rate of 50 Hz.
+    ///
     /// # Arguments
     ///
     /// * `start_duty_cycle`: The starting duty cycle of the servo.
@@ -67,13 +167,16 @@ impl ServoSettings {
     /// # Returns
     ///
     /// The modified `Settings` instance.
-    pub fn with_start_duty_cycle(mut self, start_duty_cycle: f64) -> Self {
-        self.start_duty_cycle = start_duty_cycle;
-        self
+    #[deprecated(note = "use `with_start_pulse_width`, which holds at any update rate")]
+    pub fn with_start_duty_cycle(self, start_duty_cycle: f64) -> Self {
+        self.with_start_pulse_width(start_duty_cycle * Self::DUTY_CYCLE_PERIOD)
     }
 
     /// Sets the ending duty cycle of the servo and returns the modified `Settings` instance.
     ///
+    /// The duty cycle is converted into the width of the pulses at the ending angle with
+    ///  `DUTY_CYCLE_PERIOD`, so it only holds at an update rate of 50 Hz.
+    ///
     /// # Arguments
     ///
     /// * `end_duty_cycle`: The ending duty cycle of the servo.
@@ -81,8 +184,321 @@ impl ServoSettings {
     /// # Returns
     ///
     /// The modified `Settings` instance.
-    pub fn with_end_duty_cycle(mut self, end_duty_cycle: f64) -> Self {
-        self.end_duty_cycle = end_duty_cycle;
+    #[deprecated(note = "use `with_end_pulse_width`, which holds at any update rate")]
+    pub fn with_end_duty_cycle(self, end_duty_cycle: f64) -> Self {
+        self.with_end_pulse_width(end_duty_cycle * Self::DUTY_CYCLE_PERIOD)
+    }
+
+    /// Sets the calibration table of the servo and returns the modified `Settings` instance.
+    ///
+    /// The starting and ending angle and their pulse widths are taken from the first and last
+    ///  point of the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `calibration`: The calibration table of the servo.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Settings` instance.
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        let points = calibration.points();
+        (self.start_angle, self.start_pulse_width) = points[0];
+        (self.end_angle, self.end_pulse_width) = points[points.len() - 1];
+
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Sets the limits of the servo and returns the modified `Settings` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_angle`: The minimum angle the servo may be moved to.
+    /// * `max_angle`: The maximum angle the servo may be moved to.
+    ///
+    /// # Returns
+    ///
+    /// The modified `Settings` instance.
+    pub fn with_limits(mut self, min_angle: f64, max_angle: f64) -> Self {
+        self.limits = Some((min_angle, max_angle));
         self
     }
+
+    /// Checks that the settings map every angle to a finite, positive pulse width, which requires
+    ///  finite angles and pulse widths, distinct starting and ending angles, and positive pulse
+    ///  widths.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the settings are valid, otherwise returns an `Error`.
+    pub fn validate(&self) -> Result<(), Error> {
+        let values = [
+            ("start_angle", self.start_angle),
+            ("end_angle", self.end_angle),
+            ("start_pulse_width", self.start_pulse_width),
+            ("end_pulse_width", self.end_pulse_width),
+        ];
+
+        if let Some(&(name, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(Error::ValueError { name, value });
+        }
+
+        if self.start_angle == self.end_angle {
+            return Err(Error::RangeError(self.start_angle));
+        }
+
+        if let Some(&(name, value)) = values[2..].iter().find(|(_, value)| *value <= 0_f64) {
+            return Err(Error::PulseWidthError { name, value });
+        }
+
+        Ok(())
+    }
+
+    /// Gets the starting angle of the servo.
+    pub fn start_angle(&self) -> f64 {
+        self.start_angle
+    }
+
+    /// Gets the ending angle of the servo.
+    pub fn end_angle(&self) -> f64 {
+        self.end_angle
+    }
+
+    /// Gets the width of the pulses at the starting angle, in microseconds.
+    pub fn start_pulse_width(&self) -> f64 {
+        self.start_pulse_width
+    }
+
+    /// Gets the width of the pulses at the ending angle, in microseconds.
+    pub fn end_pulse_width(&self) -> f64 {
+        self.end_pulse_width
+    }
+
+    /// Gets the limits of the servo, if any.
+    pub fn limits(&self) -> Option<(f64, f64)> {
+        self.limits
+    }
+
+    /// Gets the calibration table of the servo, if any.
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    /// Gets the minimum and maximum angle the servo may be moved to, which lie between the
+    ///  starting and ending angle, and within the limits if set.
+    pub fn angle_range(&self) -> (f64, f64) {
+        let min_angle = self.start_angle.min(self.end_angle);
+        let max_angle = self.start_angle.max(self.end_angle);
+
+        match self.limits {
+            Some((min_limit, max_limit)) => (min_angle.max(min_limit), max_angle.min(max_limit)),
+            None => (min_angle, max_angle),
+        }
+    }
+
+    /// Computes the pulse width for the given angle, from the calibration table if set, or else
+    ///  linearly between the starting and ending angle.
+    ///
+    /// # Arguments
+    ///
+    /// * `angle`: The angle of the servo.
+    ///
+    /// # Returns
+    ///
+    /// The pulse width in microseconds.
+    pub fn pulse_width(&self, angle: f64) -> f64 {
+        match &self.calibration {
+            Some(calibration) => calibration.pulse_width(angle),
+            None => compute_pulse_width(
+                self.start_pulse_width,
+                self.end_pulse_width,
+                self.start_angle,
+                self.end_angle,
+                angle,
+            ),
+        }
+    }
+
+    /// Computes the angle for the given pulse width, which is the inverse of `pulse_width`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pulse_width`: The pulse width in microseconds.
+    ///
+    /// # Returns
+    ///
+    /// The angle of the servo.
+    pub fn angle(&self, pulse_width: f64) -> f64 {
+        match &self.calibration {
+            Some(calibration) => calibration.angle(pulse_width),
+            None => map(
+                pulse_width,
+                self.start_pulse_width,
+                self.end_pulse_width,
+                self.start_angle,
+                self.end_angle,
+            ),
+        }
+    }
+
+    /// Checks whether the given angle lies within the range of the servo.
+    ///
+    /// # Arguments
+    ///
+    /// * `angle`: The angle to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the angle lies between the starting and ending angle (inclusive), and within
+    ///  the limits if set.
+    pub fn contains_angle(&self, angle: f64) -> bool {
+        let (min_angle, max_angle) = self.angle_range();
+
+        (min_angle..=max_angle).contains(&angle)
+    }
+}
+
+/// The serialized form of `ServoSettings`, which applies the calibration table after the other
+///  fields, as `ServoSettings::with_calibration` overrides them.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServoSettingsTable {
+    start_angle: f64,
+    end_angle: f64,
+    start_pulse_width: f64,
+    end_pulse_width: f64,
+    calibration: Option<Calibration>,
+}
+
+#[cfg(feature = "serde")]
+impl Default for ServoSettingsTable {
+    fn default() -> Self {
+        Self {
+            start_angle: ServoSettings::DEFAULT_START_ANGLE,
+            end_angle: ServoSettings::DEFAULT_END_ANGLE,
+            start_pulse_width: ServoSettings::DEFAULT_START_PULSE_WIDTH,
+            end_pulse_width: ServoSettings::DEFAULT_END_PULSE_WIDTH,
+            calibration: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ServoSettingsTable> for ServoSettings {
+    fn from(table: ServoSettingsTable) -> Self {
+        let settings = ServoSettings::new()
+            .with_start_angle(table.start_angle)
+            .with_end_angle(table.end_angle)
+            .with_start_pulse_width(table.start_pulse_width)
+            .with_end_pulse_width(table.end_pulse_width);
+
+        match table.calibration {
+            Some(calibration) => settings.with_calibration(calibration),
+            None => settings,
+        }
+    }
+}
+
+impl Default for ServoSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::Interpolation;
+
+    #[test]
+    fn test_endpoints_clear_calibration() {
+        let calibration = Calibration::new(
+            &[(-90.0, 600.0), (0.0, 1400.0), (90.0, 2400.0)],
+            Interpolation::Linear,
+        )
+        .unwrap();
+
+        let settings = ServoSettings::new().with_calibration(calibration);
+        assert_eq!(settings.angle_range(), (-90.0, 90.0));
+        assert_eq!(settings.pulse_width(0.0), 1400.0);
+
+        // Changing the range replaces the table with the linear mapping of the new range.
+        let settings = settings.with_start_angle(-45.0);
+        assert!(settings.calibration().is_none());
+        assert_eq!(settings.angle_range(), (-45.0, 90.0));
+        assert_eq!(settings.pulse_width(-45.0), 600.0);
+        assert_eq!(settings.pulse_width(90.0), 2400.0);
+        assert_eq!(settings.angle(2400.0), 90.0);
+
+        let settings = ServoSettings::new()
+            .with_calibration(
+                Calibration::new(&[(-90.0, 600.0), (90.0, 2400.0)], Interpolation::Linear).unwrap(),
+            )
+            .with_end_pulse_width(2000.0);
+        assert!(settings.calibration().is_none());
+        assert_eq!(settings.pulse_width(90.0), 2000.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(ServoSettings::new().validate(), Ok(()));
+
+        let settings = ServoSettings::new().with_end_angle(f64::NAN);
+        assert!(matches!(
+            settings.validate(),
+            Err(Error::ValueError {
+                name: "end_angle",
+                ..
+            })
+        ));
+
+        let settings = ServoSettings::new().with_start_pulse_width(f64::INFINITY);
+        assert!(matches!(
+            settings.validate(),
+            Err(Error::ValueError {
+                name: "start_pulse_width",
+                ..
+            })
+        ));
+
+        let settings = ServoSettings::new().with_start_angle(90.0);
+        assert_eq!(settings.validate(), Err(Error::RangeError(90.0)));
+
+        let settings = ServoSettings::new().with_end_pulse_width(0.0);
+        assert_eq!(
+            settings.validate(),
+            Err(Error::PulseWidthError {
+                name: "end_pulse_width",
+                value: 0.0
+            })
+        );
+
+        let settings = ServoSettings::new().with_start_pulse_width(-500.0);
+        assert!(matches!(
+            settings.validate(),
+            Err(Error::PulseWidthError {
+                name: "start_pulse_width",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_duty_cycles() {
+        let settings = ServoSettings::new()
+            .with_start_duty_cycle(ServoSettings::DEFAULT_START_DUTY_CYCLE)
+            .with_end_duty_cycle(ServoSettings::DEFAULT_END_DUTY_CYCLE);
+
+        assert_eq!(settings, ServoSettings::new());
+
+        let settings = ServoSettings::new()
+            .with_start_duty_cycle(0.033)
+            .with_end_duty_cycle(0.127);
+
+        assert_eq!(settings.start_pulse_width(), 660.0);
+        assert_eq!(settings.end_pulse_width(), 2540.0);
+    }
 }